@@ -1,9 +1,40 @@
 use std::path::PathBuf;
 
-use argparse::{ArgumentParser, Print, Store};
+use argparse::{ArgumentParser, Print, Store, StoreTrue};
+
+use crate::{
+    devices_manager::DEFAULT_DISCOVERY_INTERVAL,
+    gpu_device::{
+        simulated_device::ThermalModel,
+        sysfs::DEFAULT_SYSFS_ROOT,
+        thermal_failsafe::DEFAULT_FAILSAFE_HYSTERESIS,
+    },
+    overclock_guard::{DEFAULT_GUARD_FILE, DEFAULT_STABLE_TIME},
+    runtime_state::DEFAULT_STATE_FILE,
+};
 
 pub struct ArgsOptions {
     pub config_file_path: PathBuf,
+    pub sysfs_root: PathBuf,
+    pub simulated_gpus: u32,
+    // Thermal model shared by the simulated GPUs
+    pub thermal_model: ThermalModel,
+    // Devices discovery interval in seconds, 0 to disable it
+    pub discovery_interval: u64,
+    // Thermal failsafe temperature, 0 to use the device thresholds
+    pub failsafe_temp: u32,
+    pub failsafe_hysteresis: u32,
+    pub failsafe_power_drop: bool,
+    // Give the fans back to the driver when one of them stall
+    pub auto_on_fan_stall: bool,
+    // Record the devices taken over to recover them after a crash
+    pub state_file_path: PathBuf,
+    // Only restore the devices recorded in the state file and exit
+    pub recover: bool,
+    // Quarantine the overclocks never confirmed stable before a reboot
+    pub overclock_guard_path: PathBuf,
+    // Overclock uptime in seconds before it is confirmed stable
+    pub overclock_stable_time: u64,
 }
 
 impl ArgsOptions {
@@ -20,6 +51,118 @@ impl ArgsOptions {
                 "The file path of the configuration file",
             );
 
+            // Sysfs root used to discover the AMD GPUs
+            parser.refer(&mut options.sysfs_root).add_option(
+                &["--sysfs-root"],
+                Store,
+                "The mount point of the sysfs file system",
+            );
+
+            // Number of simulated GPUs
+            parser.refer(&mut options.simulated_gpus).add_option(
+                &["--simulate"],
+                Store,
+                "Add the given number of simulated GPUs",
+            );
+
+            // Simulated GPUs thermal model
+            parser.refer(&mut options.thermal_model.ambient_temp).add_option(
+                &["--sim-ambient-temp"],
+                Store,
+                "The ambient temperature of the simulated GPUs in degrees \
+                Celsius",
+            );
+            parser.refer(&mut options.thermal_model.heat_capacity).add_option(
+                &["--sim-heat-capacity"],
+                Store,
+                "The heat capacity of the simulated GPUs in J/°C",
+            );
+            parser
+                .refer(&mut options.thermal_model.passive_cooling)
+                .add_option(
+                    &["--sim-passive-cooling"],
+                    Store,
+                    "The cooling of the simulated GPUs with the fans \
+                    stopped in W/°C",
+                );
+            parser.refer(&mut options.thermal_model.fan_cooling).add_option(
+                &["--sim-fan-cooling"],
+                Store,
+                "The cooling added by the fans of the simulated GPUs at \
+                full speed in W/°C",
+            );
+            parser.refer(&mut options.thermal_model.load_pattern).add_option(
+                &["--sim-load"],
+                Store,
+                "The load of the simulated GPUs, either constant:LOAD, \
+                square:PERIOD:LOW:HIGH or sine:PERIOD:LOW:HIGH with the \
+                period in seconds and the loads from 0 to 1",
+            );
+
+            // Devices discovery interval
+            parser.refer(&mut options.discovery_interval).add_option(
+                &["--discovery-interval"],
+                Store,
+                "Look for added or removed GPUs every given number of \
+                seconds, 0 to only look for them at start-up",
+            );
+
+            // Thermal failsafe settings
+            parser.refer(&mut options.failsafe_temp).add_option(
+                &["--failsafe-temp"],
+                Store,
+                "Force the fans to full speed at the given temperature, \
+                0 to use the slowdown temperature of each GPU",
+            );
+            parser.refer(&mut options.failsafe_hysteresis).add_option(
+                &["--failsafe-hysteresis"],
+                Store,
+                "Release the thermal failsafe once the temperature is \
+                the given number of degrees below the failsafe temperature",
+            );
+            parser.refer(&mut options.failsafe_power_drop).add_option(
+                &["--failsafe-power-drop"],
+                StoreTrue,
+                "Drop the power limit to its minimum while the thermal \
+                failsafe is engaged",
+            );
+
+            // Fan stall handling
+            parser.refer(&mut options.auto_on_fan_stall).add_option(
+                &["--auto-on-fan-stall"],
+                StoreTrue,
+                "Switch the fans of a GPU to automatic when one of them \
+                stall",
+            );
+
+            // Crash recovery
+            parser.refer(&mut options.state_file_path).add_option(
+                &["--state-file"],
+                Store,
+                "The file path where the settings of the GPUs taken over \
+                are recorded",
+            );
+            parser.refer(&mut options.recover).add_option(
+                &["--recover"],
+                StoreTrue,
+                "Restore the GPUs left behind by a crashed instance to \
+                their original settings and exit",
+            );
+
+            // Overclock boot-loop protection
+            parser.refer(&mut options.overclock_guard_path).add_option(
+                &["--overclock-guard-file"],
+                Store,
+                "The file path where the overclocks not yet confirmed \
+                stable are recorded",
+            );
+            parser.refer(&mut options.overclock_stable_time).add_option(
+                &["--overclock-stable-time"],
+                Store,
+                "Confirm an overclock as stable after it ran for the \
+                given number of seconds",
+            );
+
             // Show daemon version
             parser.add_option(
                 &["-V", "--version"],
@@ -39,6 +182,18 @@ impl Default for ArgsOptions {
     fn default() -> Self {
         Self { 
             config_file_path: PathBuf::from("moss/config.json"),
+            sysfs_root: PathBuf::from(DEFAULT_SYSFS_ROOT),
+            simulated_gpus: 0,
+            thermal_model: ThermalModel::default(),
+            discovery_interval: DEFAULT_DISCOVERY_INTERVAL.as_secs(),
+            failsafe_temp: 0,
+            failsafe_hysteresis: DEFAULT_FAILSAFE_HYSTERESIS,
+            failsafe_power_drop: false,
+            auto_on_fan_stall: false,
+            state_file_path: PathBuf::from(DEFAULT_STATE_FILE),
+            recover: false,
+            overclock_guard_path: PathBuf::from(DEFAULT_GUARD_FILE),
+            overclock_stable_time: DEFAULT_STABLE_TIME.as_secs(),
         }
     }
 }
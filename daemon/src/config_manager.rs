@@ -24,10 +24,17 @@ use tracing::{debug, error, info, trace, warn};
 
 use crate::{
     errors::MossdError,
-    fan_curve::{fan_curve_info::FanCurveInfo, fan_mode::FanMode},
+    fan_curve::{
+        fan_curve_info::{FanCurveInfo, FanCurveType},
+        fan_mode::FanMode,
+        filter_curve::TempFilter,
+        pid_curve::PidSettings,
+        zero_rpm_curve::ZeroRpmSettings,
+    },
     gpu_device::{
         DEFAULT_FAN_UPDATE_INTERVAL,
-        gpu_config::{GpuConfig, NvidiaConfig},
+        gpu_config::{AmdConfig, GpuConfig, NvidiaConfig},
+        sensor_fault::SensorFaultPolicy,
     },
 };
 
@@ -63,12 +70,24 @@ pub enum ConfigError {
     TxError { reason: String },
 }
 
+// Fan mode and fan curve assigned by a profile to a single fan,
+// None values fall back to the profile settings for all the fans
+#[derive(Debug, Clone)]
+pub struct FanAssignment {
+    pub index: u32,
+
+    pub fan_mode: Option<FanMode>,
+    pub fan_curve: Option<FanCurveInfo>,
+}
+
 // Store the answer to the configuration request
 #[derive(Debug)]
 pub enum ConfigMessageAnswer {
     FanMode(FanMode),
     FanCurve(Option<FanCurveInfo>),
+    FanAssignments(Vec<FanAssignment>),
     FanUpdateInterval(Option<Duration>),
+    SensorFaultPolicy(SensorFaultPolicy),
     Config(Option<GpuConfig>),
 }
 
@@ -89,6 +108,12 @@ pub enum ConfigMessage {
         uuid: String,
         tx: Responder,
     },
+    // Get the per fan mode and curve assignments for the given device
+    // Return an empty list if the profile doesn't assign single fans
+    GetFanAssignments {
+        uuid: String,
+        tx: Responder,
+    },
     // Get the config for the given device
     // Return None if the device doesn't exist in the configuration
     GetConfig {
@@ -101,6 +126,12 @@ pub enum ConfigMessage {
         uuid: String,
         tx: Responder,
     },
+    // Get the sensor fault policy for the given device
+    // Return the default policy if the profile doesn't set one
+    GetSensorFaultPolicy {
+        uuid: String,
+        tx: Responder,
+    },
 
     // Assign the given profile on the given device
     AssignProfile {
@@ -117,10 +148,23 @@ pub enum ConfigMessage {
         profile: String,
         curve_name: Option<String>,
     },
+    // Set the fan mode and fan curve of a single fan for a profile,
+    // the fan assignment is removed if both are None
+    SetProfileFanAssignment {
+        profile: String,
+        index: u32,
+        mode: Option<FanMode>,
+        curve_name: Option<String>,
+    },
     SetFanUpdateInterval {
         profile: String,
         update_intrerval: Duration,
     },
+    // Set the sensor fault policy for a profile
+    SetProfileSensorFaultPolicy {
+        profile: String,
+        policy: SensorFaultPolicy,
+    },
     // Set a config for a profile
     SetProfileConfig {
         profile: String,
@@ -156,6 +200,19 @@ struct ProfileData {
     pub fan_curve: Option<String>,
     pub config: Option<String>,
     pub update_interval: Duration,
+    // Fan control used when the temperature can't be read
+    pub sensor_fault: SensorFaultPolicy,
+
+    // Fan mode and fan curve overrides for single fans
+    pub fans: Vec<FanProfileData>,
+}
+
+#[derive(Debug, Clone)]
+struct FanProfileData {
+    pub index: u32,
+
+    pub fan_mode: Option<FanMode>,
+    pub fan_curve: Option<String>,
 }
 
 // Json data types for serialization
@@ -168,6 +225,18 @@ struct ProfileJson {
     pub fan_curve: Option<String>,
     pub config: Option<String>,
     pub update_interval: Option<f32>,
+    pub sensor_fault: Option<SensorFaultJson>,
+
+    // Per fan overrides of the profile fan mode and fan curve
+    pub fans: Option<Vec<FanProfileJson>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct FanProfileJson {
+    pub index: u32,
+
+    pub fan_mode: Option<FanModeJson>,
+    pub fan_curve: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
@@ -178,13 +247,69 @@ struct FanModeJson {
     pub manaul_speed: Option<u8>,
 }
 
+// Only one of the policies can be set, the last good
+// temperature is held for the given number of seconds
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct SensorFaultJson {
+    pub last_good: Option<f32>,
+    pub fixed_duty: Option<u8>,
+    pub auto: Option<bool>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct FanCurveJson {
     pub name: String,
 
+    // Linear when not set
+    #[serde(rename = "type")]
+    pub curve_type: Option<FanCurveType>,
+    // PID curves don't have points
+    #[serde(default)]
     pub points: Vec<(i32, u8)>,
     pub hysteresis_up: Option<u32>,
     pub hysteresis_down: Option<u32>,
+    // Time hysteresis in seconds
+    pub hysteresis_hold: Option<f32>,
+    pub hysteresis_delay: Option<f32>,
+    // Maximum fan speed change in percent per second
+    pub ramp_up: Option<f32>,
+    pub ramp_down: Option<f32>,
+    pub pid: Option<PidJson>,
+    pub filter: Option<TempFilterJson>,
+    pub zero_rpm: Option<ZeroRpmJson>,
+}
+
+// The kick-start time is in seconds, without kick-start
+// duty the fans restart at the fan curve speed
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct ZeroRpmJson {
+    pub stop_temp: i32,
+    pub start_temp: i32,
+    pub kick_duty: Option<u8>,
+    pub kick_time: Option<f32>,
+    pub min_duty: Option<u8>,
+}
+
+// Only one of the filters can be set, the moving average time
+// constant is in seconds and the windows in number of fan updates
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct TempFilterJson {
+    pub ema: Option<f32>,
+    pub mean: Option<usize>,
+    pub max: Option<usize>,
+}
+
+// Only the target temperature and proportional gain are required,
+// the maximum rate is in percent of fan speed per second
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct PidJson {
+    pub target: i32,
+    pub kp: f32,
+    pub ki: Option<f32>,
+    pub kd: Option<f32>,
+    pub min_duty: Option<u8>,
+    pub max_duty: Option<u8>,
+    pub max_rate: Option<f32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -193,12 +318,21 @@ struct NvidiaConfigJson {
     pub mem_offset: Option<i32>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct AmdConfigJson {
+    pub core_offset: Option<i32>,
+    pub mem_offset: Option<i32>,
+    pub voltage_offset: Option<i32>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 struct ConfigJson {
     pub name: String,
 
     pub power_limit: Option<f32>,
     pub nvidia: Option<NvidiaConfigJson>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub amd: Option<AmdConfigJson>,
 }
 
 // Manage the stored daemon Json configuration
@@ -278,6 +412,12 @@ impl ConfigManager {
                 ConfigMessage::GetFanUpdateInterval { uuid: _, tx: _ } => {
                     self.handle_get_message(message)?;
                 }
+                ConfigMessage::GetSensorFaultPolicy { uuid: _, tx: _ } => {
+                    self.handle_get_message(message)?;
+                }
+                ConfigMessage::GetFanAssignments { uuid: _, tx: _ } => {
+                    self.handle_get_message(message)?;
+                }
                 ConfigMessage::GetConfig { uuid: _, tx: _ } => {
                     self.handle_get_message(message)?;
                 }
@@ -300,12 +440,26 @@ impl ConfigManager {
                 } => {
                     self.hadle_set_message(message)?;
                 }
+                ConfigMessage::SetProfileFanAssignment {
+                    profile: _,
+                    index: _,
+                    mode: _,
+                    curve_name: _,
+                } => {
+                    self.hadle_set_message(message)?;
+                }
                 ConfigMessage::SetFanUpdateInterval {
                     profile: _,
                     update_intrerval: _,
                 } => {
                     self.hadle_set_message(message)?;
                 }
+                ConfigMessage::SetProfileSensorFaultPolicy {
+                    profile: _,
+                    policy: _,
+                } => {
+                    self.hadle_set_message(message)?;
+                }
                 ConfigMessage::SetProfileConfig {
                     profile: _,
                     config_name: _,
@@ -378,6 +532,33 @@ impl ConfigManager {
                     self.profile_datas.insert(profile, new_profile);
                 }
             }
+            ConfigMessage::SetProfileFanAssignment {
+                profile,
+                index,
+                mode,
+                curve_name,
+            } => {
+                if profile == DEFAULT_PROFILE_NAME {
+                    return Err(ConfigError::Set {
+                        reason: "Can't modify default profile".to_string(),
+                    });
+                }
+
+                // Create e new profile if it doesn't already exist
+                let profile_data =
+                    self.profile_datas.entry(profile).or_default();
+
+                // Replace the previous assignment of the fan
+                profile_data.fans.retain(|fan| fan.index != index);
+
+                if mode.is_some() || curve_name.is_some() {
+                    profile_data.fans.push(FanProfileData {
+                        index,
+                        fan_mode: mode,
+                        fan_curve: curve_name,
+                    });
+                }
+            }
             ConfigMessage::SetFanUpdateInterval {
                 profile,
                 update_intrerval,
@@ -400,6 +581,17 @@ impl ConfigManager {
                     self.profile_datas.insert(profile, new_profile);
                 }
             }
+            ConfigMessage::SetProfileSensorFaultPolicy { profile, policy } => {
+                if profile == DEFAULT_PROFILE_NAME {
+                    return Err(ConfigError::Set {
+                        reason: "Can't modify default profile".to_string(),
+                    });
+                }
+
+                // Create e new profile if it doesn't already exist
+                self.profile_datas.entry(profile).or_default().sensor_fault =
+                    policy;
+            }
             ConfigMessage::SetProfileConfig {
                 profile,
                 config_name,
@@ -474,6 +666,25 @@ impl ConfigManager {
 
                 (tx, ConfigMessageAnswer::FanMode(fan_mode))
             }
+            ConfigMessage::GetFanAssignments { uuid, tx } => {
+                let profile = self.get_profile(&uuid)?;
+
+                let fan_assignments = profile
+                    .fans
+                    .iter()
+                    .map(|fan| FanAssignment {
+                        index: fan.index,
+                        fan_mode: fan.fan_mode,
+                        fan_curve: fan
+                            .fan_curve
+                            .as_ref()
+                            .and_then(|name| self.fan_curve_datas.get(name))
+                            .cloned(),
+                    })
+                    .collect();
+
+                (tx, ConfigMessageAnswer::FanAssignments(fan_assignments))
+            }
             ConfigMessage::GetFanUpdateInterval { uuid, tx } => {
                 let profile = self.get_profile(&uuid)?;
 
@@ -481,6 +692,13 @@ impl ConfigManager {
 
                 (tx, ConfigMessageAnswer::FanUpdateInterval(updata_interval))
             }
+            ConfigMessage::GetSensorFaultPolicy { uuid, tx } => {
+                let profile = self.get_profile(&uuid)?;
+
+                let policy = profile.sensor_fault;
+
+                (tx, ConfigMessageAnswer::SensorFaultPolicy(policy))
+            }
             ConfigMessage::GetConfig { uuid, tx } => {
                 let profile = self.get_profile(&uuid)?;
 
@@ -767,6 +985,8 @@ impl Default for ProfileData {
             config: None,
             fan_mode: FanMode::Auto,
             update_interval: DEFAULT_FAN_UPDATE_INTERVAL,
+            sensor_fault: SensorFaultPolicy::default(),
+            fans: Vec::new(),
         }
     }
 }
@@ -819,6 +1039,37 @@ impl TryFrom<FanModeJson> for FanMode {
     }
 }
 
+impl TryFrom<SensorFaultJson> for SensorFaultPolicy {
+    type Error = ConfigError;
+
+    fn try_from(
+        value: SensorFaultJson,
+    ) -> std::result::Result<SensorFaultPolicy, Self::Error> {
+        let auto = value.auto.unwrap_or(false);
+
+        let policy = match (value.last_good, value.fixed_duty, auto) {
+            (Some(hold), None, false) if hold.is_finite() && hold >= 0.0 => {
+                SensorFaultPolicy::LastGood(Duration::from_secs_f32(hold))
+            }
+            (None, Some(duty), false) => {
+                SensorFaultPolicy::FixedDuty(duty.clamp(0, 100))
+            }
+            (None, None, true) => SensorFaultPolicy::Auto,
+            _ => {
+                return Err(ConfigError::Json {
+                    reason: "Invalid sensor fault policy".to_string(),
+                    error: anyhow!(
+                        "Exactly one of last_good, fixed_duty \
+                        and auto must be set"
+                    ),
+                });
+            }
+        };
+
+        Ok(policy)
+    }
+}
+
 impl TryFrom<NvidiaConfigJson> for NvidiaConfig {
     type Error = ConfigError;
 
@@ -832,6 +1083,20 @@ impl TryFrom<NvidiaConfigJson> for NvidiaConfig {
     }
 }
 
+impl TryFrom<AmdConfigJson> for AmdConfig {
+    type Error = ConfigError;
+
+    fn try_from(
+        value: AmdConfigJson,
+    ) -> std::result::Result<AmdConfig, Self::Error> {
+        Ok(Self {
+            core_clock_offset: value.core_offset,
+            mem_clock_offset: value.mem_offset,
+            voltage_offset: value.voltage_offset,
+        })
+    }
+}
+
 impl TryFrom<ProfileJson> for ProfileData {
     type Error = ConfigError;
 
@@ -844,11 +1109,56 @@ impl TryFrom<ProfileJson> for ProfileData {
             DEFAULT_FAN_UPDATE_INTERVAL
         };
 
+        // Parse the per fan overrides
+        let mut fans: Vec<FanProfileData> = Vec::new();
+
+        for fan in value.fans.unwrap_or_default() {
+            // If the fan is already assigned ignore it
+            if fans.iter().any(|f| f.index == fan.index) {
+                warn!(
+                    "Redefinition of fan {} in profile: \"{}\", ignoring it",
+                    fan.index, value.name
+                );
+
+                continue;
+            }
+
+            fans.push(fan.try_into()?);
+        }
+
+        let sensor_fault = if let Some(sensor_fault) = value.sensor_fault {
+            sensor_fault.try_into()?
+        } else {
+            SensorFaultPolicy::default()
+        };
+
         Ok(Self {
             fan_mode: value.fan_mode.try_into()?,
             fan_curve: value.fan_curve,
             config: value.config,
             update_interval,
+            sensor_fault,
+            fans,
+        })
+    }
+}
+
+impl TryFrom<FanProfileJson> for FanProfileData {
+    type Error = ConfigError;
+
+    fn try_from(
+        value: FanProfileJson,
+    ) -> std::result::Result<FanProfileData, Self::Error> {
+        let fan_mode = if let Some(fan_mode) = value.fan_mode {
+            Some(fan_mode.try_into()?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            index: value.index,
+            fan_mode,
+            fan_curve: value.fan_curve,
         })
     }
 }
@@ -859,14 +1169,199 @@ impl TryFrom<FanCurveJson> for FanCurveInfo {
     fn try_from(
         value: FanCurveJson,
     ) -> std::result::Result<FanCurveInfo, Self::Error> {
+        let curve_type = value.curve_type.unwrap_or_default();
+
+        let pid = match (curve_type, value.pid) {
+            (_, Some(pid)) => Some(pid.try_into()?),
+            (FanCurveType::Pid, None) => {
+                return Err(ConfigError::Json {
+                    reason: "Invalid fan curve".to_string(),
+                    error: anyhow!("PID fan curve without pid settings"),
+                });
+            }
+            (_, None) => None,
+        };
+
+        let seconds = |time: Option<f32>| match time {
+            Some(time) if !time.is_finite() || time < 0.0 => {
+                Err(ConfigError::Json {
+                    reason: "Invalid fan curve".to_string(),
+                    error: anyhow!("Hysteresis times must be positive"),
+                })
+            }
+            _ => Ok(time.map(Duration::from_secs_f32)),
+        };
+
+        let hold_time = seconds(value.hysteresis_hold)?;
+        let decrease_delay = seconds(value.hysteresis_delay)?;
+
+        let ramp_valid = [value.ramp_up, value.ramp_down]
+            .iter()
+            .flatten()
+            .all(|rate| rate.is_finite() && *rate > 0.0);
+
+        if !ramp_valid {
+            return Err(ConfigError::Json {
+                reason: "Invalid fan curve".to_string(),
+                error: anyhow!("Ramp rates must be greater than 0"),
+            });
+        }
+
         Ok(Self {
+            curve_type,
             points: value.points,
+            pid,
             upper_threshold: value.hysteresis_up,
             lower_threshold: value.hysteresis_down,
+            hold_time,
+            decrease_delay,
+            ramp_up: value.ramp_up,
+            ramp_down: value.ramp_down,
+            filter: value.filter.map(TempFilter::try_from).transpose()?,
+            zero_rpm: value
+                .zero_rpm
+                .map(ZeroRpmSettings::try_from)
+                .transpose()?,
+        })
+    }
+}
+
+impl TryFrom<TempFilterJson> for TempFilter {
+    type Error = ConfigError;
+
+    fn try_from(
+        value: TempFilterJson,
+    ) -> std::result::Result<TempFilter, Self::Error> {
+        let filter = match (value.ema, value.mean, value.max) {
+            (Some(time), None, None) if time.is_finite() && time >= 0.0 => {
+                TempFilter::Ema(Duration::from_secs_f32(time))
+            }
+            (None, Some(window), None) if window > 0 => {
+                TempFilter::Mean(window)
+            }
+            (None, None, Some(window)) if window > 0 => TempFilter::Max(window),
+            _ => {
+                return Err(ConfigError::Json {
+                    reason: "Invalid temperature filter".to_string(),
+                    error: anyhow!(
+                        "Exactly one of ema, mean and max must be set"
+                    ),
+                });
+            }
+        };
+
+        Ok(filter)
+    }
+}
+
+impl TryFrom<TempFilter> for TempFilterJson {
+    type Error = ConfigError;
+
+    fn try_from(
+        value: TempFilter,
+    ) -> std::result::Result<TempFilterJson, Self::Error> {
+        let mut filter_json = TempFilterJson::default();
+
+        match value {
+            TempFilter::Ema(time) => filter_json.ema = Some(time.as_secs_f32()),
+            TempFilter::Mean(window) => filter_json.mean = Some(window),
+            TempFilter::Max(window) => filter_json.max = Some(window),
+        }
+
+        Ok(filter_json)
+    }
+}
+
+impl TryFrom<ZeroRpmJson> for ZeroRpmSettings {
+    type Error = ConfigError;
+
+    fn try_from(
+        value: ZeroRpmJson,
+    ) -> std::result::Result<ZeroRpmSettings, Self::Error> {
+        let kick_time = value.kick_time.unwrap_or(0.0);
+
+        if value.start_temp < value.stop_temp
+            || !kick_time.is_finite()
+            || kick_time < 0.0
+        {
+            return Err(ConfigError::Json {
+                reason: "Invalid zero RPM settings".to_string(),
+                error: anyhow!(
+                    "The start temperature must not be below the stop \
+                    temperature and the kick-start time must be positive"
+                ),
+            });
+        }
+
+        Ok(ZeroRpmSettings {
+            stop_temp: value.stop_temp,
+            start_temp: value.start_temp,
+
+            kick_duty: value.kick_duty.unwrap_or(0).clamp(0, 100),
+            kick_time: Duration::from_secs_f32(kick_time),
+
+            min_duty: value.min_duty.unwrap_or(0).clamp(0, 100),
+        })
+    }
+}
+
+impl TryFrom<ZeroRpmSettings> for ZeroRpmJson {
+    type Error = ConfigError;
+
+    fn try_from(
+        value: ZeroRpmSettings,
+    ) -> std::result::Result<ZeroRpmJson, Self::Error> {
+        Ok(Self {
+            stop_temp: value.stop_temp,
+            start_temp: value.start_temp,
+            kick_duty: Some(value.kick_duty),
+            kick_time: Some(value.kick_time.as_secs_f32()),
+            min_duty: Some(value.min_duty),
         })
     }
 }
 
+impl TryFrom<PidJson> for PidSettings {
+    type Error = ConfigError;
+
+    fn try_from(
+        value: PidJson,
+    ) -> std::result::Result<PidSettings, Self::Error> {
+        let settings = PidSettings {
+            target: value.target,
+
+            kp: value.kp,
+            ki: value.ki.unwrap_or(0.0),
+            kd: value.kd.unwrap_or(0.0),
+
+            min_duty: value.min_duty.unwrap_or(0),
+            max_duty: value.max_duty.unwrap_or(100),
+            max_rate: value.max_rate,
+        };
+
+        let gains_valid = [settings.kp, settings.ki, settings.kd]
+            .iter()
+            .all(|gain| gain.is_finite() && *gain >= 0.0);
+        let duty_valid =
+            settings.min_duty <= settings.max_duty && settings.max_duty <= 100;
+        let rate_valid = settings
+            .max_rate
+            .is_none_or(|rate| rate.is_finite() && rate > 0.0);
+
+        if !(gains_valid && duty_valid && rate_valid) {
+            return Err(ConfigError::Json {
+                reason: "Invalid PID settings".to_string(),
+                error: anyhow!(
+                    "Gains must be positive, the duties between 0 and \
+                    100 and the maximum rate greater than 0"
+                ),
+            });
+        }
+
+        Ok(settings)
+    }
+}
+
 impl TryFrom<ConfigJson> for GpuConfig {
     type Error = ConfigError;
 
@@ -879,6 +1374,12 @@ impl TryFrom<ConfigJson> for GpuConfig {
             NvidiaConfig::default()
         };
 
+        let amd_config = if let Some(amd) = value.amd {
+            amd.try_into()?
+        } else {
+            AmdConfig::default()
+        };
+
         let power_limit = if let Some(limit) = value.power_limit {
             Some((limit * 1000.0) as u32)
         } else {
@@ -887,6 +1388,7 @@ impl TryFrom<ConfigJson> for GpuConfig {
 
         Ok(Self {
             nvidia_config,
+            amd_config,
             power_limit,
         })
     }
@@ -907,6 +1409,20 @@ impl TryFrom<NvidiaConfig> for NvidiaConfigJson {
     }
 }
 
+impl TryFrom<AmdConfig> for AmdConfigJson {
+    type Error = ConfigError;
+
+    fn try_from(
+        value: AmdConfig,
+    ) -> std::result::Result<AmdConfigJson, Self::Error> {
+        Ok(Self {
+            core_offset: value.core_clock_offset,
+            mem_offset: value.mem_clock_offset,
+            voltage_offset: value.voltage_offset,
+        })
+    }
+}
+
 impl TryFrom<(&String, &GpuConfig)> for ConfigJson {
     type Error = ConfigError;
 
@@ -919,10 +1435,22 @@ impl TryFrom<(&String, &GpuConfig)> for ConfigJson {
             None
         };
 
+        // Only store the AMD section of the profiles that set it
+        let amd_config = value.1.amd_config;
+        let amd = if amd_config.core_clock_offset.is_some()
+            || amd_config.mem_clock_offset.is_some()
+            || amd_config.voltage_offset.is_some()
+        {
+            Some(amd_config.try_into()?)
+        } else {
+            None
+        };
+
         Ok(Self {
             name: value.0.clone(),
             power_limit,
             nvidia: Some(value.1.nvidia_config.try_into()?),
+            amd,
         })
     }
 }
@@ -948,18 +1476,76 @@ impl TryFrom<FanMode> for FanModeJson {
     }
 }
 
+impl TryFrom<SensorFaultPolicy> for SensorFaultJson {
+    type Error = ConfigError;
+
+    fn try_from(
+        value: SensorFaultPolicy,
+    ) -> std::result::Result<SensorFaultJson, Self::Error> {
+        let mut sensor_fault_json = SensorFaultJson::default();
+
+        match value {
+            SensorFaultPolicy::LastGood(hold) => {
+                sensor_fault_json.last_good = Some(hold.as_secs_f32())
+            }
+            SensorFaultPolicy::FixedDuty(duty) => {
+                sensor_fault_json.fixed_duty = Some(duty)
+            }
+            SensorFaultPolicy::Auto => sensor_fault_json.auto = Some(true),
+        }
+
+        Ok(sensor_fault_json)
+    }
+}
+
 impl TryFrom<(&String, &ProfileData)> for ProfileJson {
     type Error = ConfigError;
 
     fn try_from(
         value: (&String, &ProfileData),
     ) -> std::result::Result<ProfileJson, Self::Error> {
+        // Only write the per fan overrides if the profile has any
+        let fans = if value.1.fans.is_empty() {
+            None
+        } else {
+            Some(
+                value
+                    .1
+                    .fans
+                    .iter()
+                    .map(|fan| fan.try_into())
+                    .collect::<Result<Vec<_>>>()?,
+            )
+        };
+
         Ok(Self {
             name: value.0.clone(),
             fan_mode: value.1.fan_mode.try_into()?,
             fan_curve: value.1.fan_curve.clone(),
             config: value.1.config.clone(),
             update_interval: Some(value.1.update_interval.as_secs_f32()),
+            sensor_fault: Some(value.1.sensor_fault.try_into()?),
+            fans,
+        })
+    }
+}
+
+impl TryFrom<&FanProfileData> for FanProfileJson {
+    type Error = ConfigError;
+
+    fn try_from(
+        value: &FanProfileData,
+    ) -> std::result::Result<FanProfileJson, Self::Error> {
+        let fan_mode = if let Some(fan_mode) = value.fan_mode {
+            Some(fan_mode.try_into()?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            index: value.index,
+            fan_mode,
+            fan_curve: value.fan_curve.clone(),
         })
     }
 }
@@ -972,9 +1558,42 @@ impl TryFrom<(&String, &FanCurveInfo)> for FanCurveJson {
     ) -> std::result::Result<FanCurveJson, Self::Error> {
         Ok(Self {
             name: value.0.clone(),
+            curve_type: Some(value.1.curve_type),
             points: value.1.points.clone(),
             hysteresis_up: value.1.upper_threshold,
             hysteresis_down: value.1.lower_threshold,
+            hysteresis_hold: value.1.hold_time.map(|time| time.as_secs_f32()),
+            hysteresis_delay: value
+                .1
+                .decrease_delay
+                .map(|time| time.as_secs_f32()),
+            ramp_up: value.1.ramp_up,
+            ramp_down: value.1.ramp_down,
+            pid: value.1.pid.map(PidJson::try_from).transpose()?,
+            filter: value.1.filter.map(TempFilterJson::try_from).transpose()?,
+            zero_rpm: value
+                .1
+                .zero_rpm
+                .map(ZeroRpmJson::try_from)
+                .transpose()?,
+        })
+    }
+}
+
+impl TryFrom<PidSettings> for PidJson {
+    type Error = ConfigError;
+
+    fn try_from(
+        value: PidSettings,
+    ) -> std::result::Result<PidJson, Self::Error> {
+        Ok(Self {
+            target: value.target,
+            kp: value.kp,
+            ki: Some(value.ki),
+            kd: Some(value.kd),
+            min_duty: Some(value.min_duty),
+            max_duty: Some(value.max_duty),
+            max_rate: value.max_rate,
         })
     }
 }
@@ -1,18 +1,31 @@
-use std::fmt::format;
+use std::collections::HashMap;
 
 use anyhow::anyhow;
 use thiserror::Error;
 use tokio::{
     select,
-    sync::{mpsc::Sender, oneshot},
+    sync::{
+        mpsc::{Receiver, Sender},
+        oneshot,
+    },
 };
 use tokio_util::sync::CancellationToken;
 use tracing::{error, info, trace};
-use zbus::{Connection, interface};
+use zbus::{
+    Connection,
+    fdo::ObjectManager,
+    interface,
+    object_server::{InterfaceRef, SignalEmitter},
+    zvariant::Value,
+};
 
 use crate::{
     errors::MossdError,
-    gpu_device::gpu_info::{GpuInfo, GpuVendorInfo},
+    fan_curve::{fan_curve_info::FanCurveInfo, filter_curve::TempFilter},
+    gpu_device::{
+        fan_monitor::{FanHealth, FanMonitor},
+        gpu_info::{GpuInfo, GpuVendorInfo},
+    },
 };
 
 macro_rules! extract_answer {
@@ -30,6 +43,8 @@ macro_rules! extract_answer {
 }
 
 const SERVICE_NAME: &str = "com.github.Mossd1";
+// Root of the GPU objects, the object manager live here
+const SERVICE_PATH: &str = "/com/github/Mossd1";
 
 type Responder = oneshot::Sender<DBusServiceAnswer>;
 
@@ -67,6 +82,30 @@ pub enum DBusServiceMessage {
     // Get the GPU infos
     GetGpuInfo { uuid: String, tx: Responder },
     GetGpuVendorInfo { uuid: String, tx: Responder },
+
+    // Get the info of the fan curve used by every GPU fan
+    GetFanCurves { uuid: String, tx: Responder },
+
+    // Get the overclock quarantine state of the GPU
+    GetOverclockQuarantined { uuid: String, tx: Responder },
+    // Allow the clock offsets of the GPU to be applied again
+    ClearOverclockQuarantine { uuid: String },
+
+    // Restore the GPU settings from before the daemon started
+    ResetGpu { uuid: String },
+}
+
+// This is the event enum that the state manager will use to
+// notify the D-Bus service about the GPUs added or removed at runtime
+// and about the GPUs thermal failsafe and fans health
+#[derive(Debug)]
+pub enum DBusServiceEvent {
+    GpuAdded { uuid: String },
+    GpuRemoved { uuid: String },
+
+    FailsafeChanged { uuid: String, engaged: bool, temp: u32 },
+    FanHealthChanged { uuid: String, fan: u32, health: FanHealth },
+    OverclockQuarantineChanged { uuid: String, quarantined: bool },
 }
 
 // This is the answer enum that the state manager will use to
@@ -77,9 +116,19 @@ pub enum DBusServiceAnswer {
 
     GpuInfo(GpuInfo),
     GpuVendorInfo(GpuVendorInfo),
+
+    FanCurves(Vec<FanCurveInfo>),
+
+    OverclockQuarantined(bool),
 }
 
-pub struct DBusService;
+pub struct DBusService {
+    // Store the object path and vendor of each GPU object by UUID
+    gpu_objects: HashMap<String, (String, GpuVendorInfo)>,
+    // Number of GPU objects created so far, object paths are never
+    // reused so clients can't mistake a new GPU for a removed one
+    gpu_count: u32,
+}
 
 // GPU D-Bus interface
 struct GpuInterface {
@@ -89,6 +138,10 @@ struct GpuInterface {
     tx_err: Sender<MossdError>,
 
     gpu_info: GpuInfo,
+    // Health of every fan, updated by the fans health events
+    fan_health: Vec<FanHealth>,
+    // Updated by the overclock quarantine events
+    overclock_quarantined: bool,
 }
 
 impl GpuInterface {
@@ -116,6 +169,30 @@ impl GpuInterface {
         })?;
 
         let gpu_info = extract_answer!(DBusServiceAnswer::GpuInfo, answer)?;
+        let fan_health = FanMonitor::new(&gpu_info).health();
+
+        // Get the overclock quarantine state
+        let (tx, rx) = oneshot::channel();
+        let message = DBusServiceMessage::GetOverclockQuarantined {
+            uuid: uuid.clone(),
+            tx,
+        };
+
+        tx_dbus_service.send(message).await.map_err(|_| {
+            DbusServiceError::TX {
+                reason: "Failed to send message to state manager".to_string(),
+            }
+        })?;
+
+        let answer = rx.await.map_err(|e| DbusServiceError::RX {
+            reason: "Failed to receive answer from state manager".to_string(),
+            error: e.into(),
+        })?;
+
+        let overclock_quarantined = extract_answer!(
+            DBusServiceAnswer::OverclockQuarantined,
+            answer
+        )?;
 
         Ok(Self {
             uuid,
@@ -124,6 +201,8 @@ impl GpuInterface {
             tx_err,
 
             gpu_info,
+            fan_health,
+            overclock_quarantined,
         })
     }
 }
@@ -161,6 +240,228 @@ impl GpuInterface {
     async fn power_limit_default(&self) -> u32 {
         self.gpu_info.power_limit_default
     }
+
+    // Fan speed range in percent, fan curves and manual
+    // speeds outside of it are clamped by the daemon
+    #[zbus(property)]
+    async fn fan_count(&self) -> u32 {
+        self.gpu_info.fan_count
+    }
+    #[zbus(property)]
+    async fn fan_speed_min(&self) -> u32 {
+        self.gpu_info.fan_speed_min
+    }
+    #[zbus(property)]
+    async fn fan_speed_max(&self) -> u32 {
+        self.gpu_info.fan_speed_max
+    }
+
+    // Features supported by the device, the matching
+    // settings and data are unavailable when false
+    #[zbus(property)]
+    async fn supports_fan_control(&self) -> bool {
+        self.gpu_info.capabilities.fan_control
+    }
+    #[zbus(property)]
+    async fn supports_fan_rpm(&self) -> bool {
+        self.gpu_info.capabilities.fan_rpm
+    }
+    #[zbus(property)]
+    async fn supports_clock_offsets(&self) -> bool {
+        self.gpu_info.capabilities.clock_offsets
+    }
+    #[zbus(property)]
+    async fn supports_power_limit(&self) -> bool {
+        self.gpu_info.capabilities.power_limit
+    }
+    #[zbus(property)]
+    async fn supports_power_usage(&self) -> bool {
+        self.gpu_info.capabilities.power_usage
+    }
+    #[zbus(property)]
+    async fn supports_mem_temp(&self) -> bool {
+        self.gpu_info.capabilities.mem_temp
+    }
+    // False if the fans can't be stopped, the zero RPM mode
+    // then keeps the fans at the lowest supported speed
+    #[zbus(property)]
+    async fn supports_zero_rpm(&self) -> bool {
+        self.gpu_info.supports_zero_rpm()
+    }
+
+    // Health of every fan, either "ok", "stalled" or "unknown"
+    // if the GPU doesn't report the fans speed in RPM
+    #[zbus(property)]
+    async fn fan_health(&self) -> Vec<String> {
+        self.fan_health
+            .iter()
+            .map(|health| health.as_str().to_string())
+            .collect()
+    }
+
+    // True if the last overclock of the GPU was never confirmed
+    // stable before a reboot, its clock offsets aren't applied
+    // until the quarantine is cleared
+    #[zbus(property)]
+    async fn overclock_quarantined(&self) -> bool {
+        self.overclock_quarantined
+    }
+
+    // Emitted when a fan doesn't spin, or too slowly,
+    // for the duty it was given
+    #[zbus(signal)]
+    async fn fan_stalled(
+        emitter: &SignalEmitter<'_>,
+        fan: u32,
+    ) -> zbus::Result<()>;
+
+    // Emitted when the GPU temperature reach the thermal failsafe
+    // threshold and the fans are forced to full speed, and again
+    // once the GPU cooled down and the failsafe is released
+    #[zbus(signal)]
+    async fn thermal_failsafe(
+        emitter: &SignalEmitter<'_>,
+        engaged: bool,
+        temp: u32,
+    ) -> zbus::Result<()>;
+
+    // Restore the settings the GPU had before the daemon
+    // started, they stay until the next profile is applied
+    async fn reset(&self) -> zbus::fdo::Result<()> {
+        let message = DBusServiceMessage::ResetGpu {
+            uuid: self.uuid.clone(),
+        };
+
+        self.tx_dbus_service.send(message).await.map_err(|_| {
+            zbus::fdo::Error::Failed(
+                "Failed to send message to state manager".to_string(),
+            )
+        })
+    }
+
+    // Clear the overclock quarantine, the clock
+    // offsets of the profile are applied again
+    async fn clear_overclock_quarantine(&self) -> zbus::fdo::Result<()> {
+        let message = DBusServiceMessage::ClearOverclockQuarantine {
+            uuid: self.uuid.clone(),
+        };
+
+        self.tx_dbus_service.send(message).await.map_err(|_| {
+            zbus::fdo::Error::Failed(
+                "Failed to send message to state manager".to_string(),
+            )
+        })
+    }
+
+    // Fan curve of every fan, whatever its fan mode, described
+    // with the field names of the configuration file, the unset
+    // fields are left out and the times are in seconds
+    async fn fan_curves(
+        &self,
+    ) -> zbus::fdo::Result<Vec<HashMap<String, Value<'static>>>> {
+        let (tx, rx) = oneshot::channel();
+        let message = DBusServiceMessage::GetFanCurves {
+            uuid: self.uuid.clone(),
+            tx,
+        };
+
+        self.tx_dbus_service.send(message).await.map_err(|_| {
+            zbus::fdo::Error::Failed(
+                "Failed to send message to state manager".to_string(),
+            )
+        })?;
+
+        let answer = rx.await.map_err(|_| {
+            zbus::fdo::Error::Failed(
+                "Failed to receive answer from state manager".to_string(),
+            )
+        })?;
+
+        let fan_curves = extract_answer!(DBusServiceAnswer::FanCurves, answer)
+            .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))?;
+
+        Ok(fan_curves.iter().map(fan_curve_dict).collect())
+    }
+}
+
+// Convert the fan curve info to a D-Bus dictionary
+fn fan_curve_dict(info: &FanCurveInfo) -> HashMap<String, Value<'static>> {
+    let mut dict = HashMap::new();
+    let mut insert = |key: &str, value: Value<'static>| {
+        dict.insert(key.to_string(), value);
+    };
+
+    insert("type", info.curve_type.as_str().into());
+    insert("points", info.points.clone().into());
+
+    if let Some(threshold) = info.upper_threshold {
+        insert("hysteresis_up", threshold.into());
+    }
+    if let Some(threshold) = info.lower_threshold {
+        insert("hysteresis_down", threshold.into());
+    }
+    if let Some(time) = info.hold_time {
+        insert("hysteresis_hold", time.as_secs_f64().into());
+    }
+    if let Some(time) = info.decrease_delay {
+        insert("hysteresis_delay", time.as_secs_f64().into());
+    }
+
+    if let Some(rate) = info.ramp_up {
+        insert("ramp_up", (rate as f64).into());
+    }
+    if let Some(rate) = info.ramp_down {
+        insert("ramp_down", (rate as f64).into());
+    }
+
+    if let Some(pid) = info.pid {
+        let mut pid_dict: HashMap<String, Value<'static>> = HashMap::from([
+            ("target".to_string(), pid.target.into()),
+            ("kp".to_string(), (pid.kp as f64).into()),
+            ("ki".to_string(), (pid.ki as f64).into()),
+            ("kd".to_string(), (pid.kd as f64).into()),
+            ("min_duty".to_string(), pid.min_duty.into()),
+            ("max_duty".to_string(), pid.max_duty.into()),
+        ]);
+        if let Some(rate) = pid.max_rate {
+            pid_dict.insert("max_rate".to_string(), (rate as f64).into());
+        }
+
+        insert("pid", pid_dict.into());
+    }
+
+    if let Some(filter) = info.filter {
+        let filter_dict: HashMap<String, Value<'static>> = match filter {
+            TempFilter::Ema(time) => {
+                HashMap::from([("ema".to_string(), time.as_secs_f64().into())])
+            }
+            TempFilter::Mean(window) => {
+                HashMap::from([("mean".to_string(), (window as u64).into())])
+            }
+            TempFilter::Max(window) => {
+                HashMap::from([("max".to_string(), (window as u64).into())])
+            }
+        };
+
+        insert("filter", filter_dict.into());
+    }
+
+    if let Some(zero_rpm) = info.zero_rpm {
+        let zero_rpm_dict: HashMap<String, Value<'static>> = HashMap::from([
+            ("stop_temp".to_string(), zero_rpm.stop_temp.into()),
+            ("start_temp".to_string(), zero_rpm.start_temp.into()),
+            ("kick_duty".to_string(), zero_rpm.kick_duty.into()),
+            (
+                "kick_time".to_string(),
+                zero_rpm.kick_time.as_secs_f64().into(),
+            ),
+            ("min_duty".to_string(), zero_rpm.min_duty.into()),
+        ]);
+
+        insert("zero_rpm", zero_rpm_dict.into());
+    }
+
+    dict
 }
 
 struct NvidiaInterface {
@@ -267,15 +568,121 @@ impl NvidiaInterface {
     }
 }
 
+struct AmdInterface {
+    gpu_vendor_info: GpuVendorInfo,
+}
+
+impl AmdInterface {
+    fn new(gpu_vendor_info: GpuVendorInfo) -> Self {
+        Self { gpu_vendor_info }
+    }
+}
+
+#[interface(name = "com.github.Mossd1.Amd")]
+impl AmdInterface {
+    // GPU vendor info properties
+    #[zbus(property)]
+    async fn vbios(&self) -> &str {
+        if let GpuVendorInfo::AMD { vbios, .. } = &self.gpu_vendor_info {
+            vbios
+        } else {
+            "VENDOR INFO NOT AMD!"
+        }
+    }
+    #[zbus(property)]
+    async fn device_id(&self) -> &str {
+        if let GpuVendorInfo::AMD { device_id, .. } = &self.gpu_vendor_info {
+            device_id
+        } else {
+            "VENDOR INFO NOT AMD!"
+        }
+    }
+
+    #[zbus(property)]
+    async fn edge_max_temp(&self) -> u32 {
+        if let GpuVendorInfo::AMD { edge_max_temp, .. } = self.gpu_vendor_info
+        {
+            edge_max_temp.unwrap_or(0)
+        } else {
+            0
+        }
+    }
+    #[zbus(property)]
+    async fn junction_max_temp(&self) -> u32 {
+        if let GpuVendorInfo::AMD {
+            junction_max_temp, ..
+        } = self.gpu_vendor_info
+        {
+            junction_max_temp.unwrap_or(0)
+        } else {
+            0
+        }
+    }
+    #[zbus(property)]
+    async fn mem_max_temp(&self) -> u32 {
+        if let GpuVendorInfo::AMD { mem_max_temp, .. } = self.gpu_vendor_info
+        {
+            mem_max_temp.unwrap_or(0)
+        } else {
+            0
+        }
+    }
+    #[zbus(property)]
+    async fn shutdown_temp(&self) -> u32 {
+        if let GpuVendorInfo::AMD { shutdown_temp, .. } = self.gpu_vendor_info
+        {
+            shutdown_temp.unwrap_or(0)
+        } else {
+            0
+        }
+    }
+}
+
+struct IntelInterface {
+    gpu_vendor_info: GpuVendorInfo,
+}
+
+impl IntelInterface {
+    fn new(gpu_vendor_info: GpuVendorInfo) -> Self {
+        Self { gpu_vendor_info }
+    }
+}
+
+#[interface(name = "com.github.Mossd1.Intel")]
+impl IntelInterface {
+    // GPU vendor info properties
+    #[zbus(property)]
+    async fn driver(&self) -> &str {
+        if let GpuVendorInfo::Intel { driver, .. } = &self.gpu_vendor_info {
+            driver
+        } else {
+            "VENDOR INFO NOT INTEL!"
+        }
+    }
+    #[zbus(property)]
+    async fn device_id(&self) -> &str {
+        if let GpuVendorInfo::Intel { device_id, .. } = &self.gpu_vendor_info
+        {
+            device_id
+        } else {
+            "VENDOR INFO NOT INTEL!"
+        }
+    }
+}
+
 impl DBusService {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            gpu_objects: HashMap::new(),
+            gpu_count: 0,
+        }
     }
 
     pub async fn run(
         &mut self,
         run_token: CancellationToken,
         tx_dbus_service: Sender<DBusServiceMessage>,
+        mut rx_event: Receiver<DBusServiceEvent>,
         tx_err: Sender<MossdError>,
     ) {
         // Connect to the system D-Bus
@@ -303,16 +710,12 @@ impl DBusService {
 
         trace!("DBus connection enstablished");
 
-        if let Err(err) = Self::initialize_service(
-            &connection,
-            tx_dbus_service,
-            tx_err.clone(),
-        )
-        .await
+        if let Err(err) = self
+            .initialize_service(&connection, &tx_dbus_service, &tx_err)
+            .await
+            && let Err(cerr) = tx_err.send(err.into()).await
         {
-            if let Err(cerr) = tx_err.send(err.into()).await {
-                error!("Failed to send error over channel: {}", cerr);
-            }
+            error!("Failed to send error over channel: {}", cerr);
         }
 
         loop {
@@ -321,14 +724,74 @@ impl DBusService {
                     info!("DBus service: Quiting");
                     break;
                 }
+                Some(event) = rx_event.recv() => {
+                    let result = match event {
+                        DBusServiceEvent::GpuAdded { uuid } => {
+                            self.add_gpu(
+                                uuid,
+                                &connection,
+                                &tx_dbus_service,
+                                &tx_err,
+                            )
+                            .await
+                        }
+                        DBusServiceEvent::GpuRemoved { uuid } => {
+                            self.remove_gpu(&uuid, &connection).await
+                        }
+                        DBusServiceEvent::FailsafeChanged {
+                            uuid,
+                            engaged,
+                            temp,
+                        } => {
+                            self.emit_failsafe(
+                                &uuid,
+                                engaged,
+                                temp,
+                                &connection,
+                            )
+                            .await
+                        }
+                        DBusServiceEvent::FanHealthChanged {
+                            uuid,
+                            fan,
+                            health,
+                        } => {
+                            self.update_fan_health(
+                                &uuid,
+                                fan,
+                                health,
+                                &connection,
+                            )
+                            .await
+                        }
+                        DBusServiceEvent::OverclockQuarantineChanged {
+                            uuid,
+                            quarantined,
+                        } => {
+                            self.update_overclock_quarantine(
+                                &uuid,
+                                quarantined,
+                                &connection,
+                            )
+                            .await
+                        }
+                    };
+
+                    if let Err(err) = result {
+                        tx_err.send(err.into()).await.unwrap_or_else(|err| {
+                            error!("Failed to send error over channel: {err}");
+                        });
+                    }
+                }
             }
         }
     }
 
     async fn initialize_service(
+        &mut self,
         connection: &Connection,
-        tx_dbus_service: Sender<DBusServiceMessage>,
-        tx_err: Sender<MossdError>,
+        tx_dbus_service: &Sender<DBusServiceMessage>,
+        tx_err: &Sender<MossdError>,
     ) -> Result<()> {
         // Query the state manager to get a list of the available GPUs
         let (tx, rx) = oneshot::channel();
@@ -354,24 +817,20 @@ impl DBusService {
             })
         }?;
 
-        // Create a D-Bus object for each GPUs on the system
-        let mut gpu_count = 1;
+        // Announce the GPU objects added and removed at runtime
+        connection
+            .object_server()
+            .at(SERVICE_PATH, ObjectManager)
+            .await
+            .map_err(|e| DbusServiceError::DBusObject {
+                reason: "Error while initializing object manager".to_string(),
+                error: e.into(),
+            })?;
 
+        // Create a D-Bus object for each GPUs on the system
         for uuid in gpu_uuids {
-            trace!("Creating D-Bus object for GPU: {}", uuid);
-
-            let path = format!("/com/github/Mossd1/Gpu{}", gpu_count);
-
-            Self::initialize_object(
-                path,
-                uuid,
-                connection,
-                tx_dbus_service.clone(),
-                tx_err.clone(),
-            )
-            .await?;
-
-            gpu_count += 1;
+            self.add_gpu(uuid, connection, tx_dbus_service, tx_err)
+                .await?;
         }
 
         // Request the service name
@@ -387,6 +846,192 @@ impl DBusService {
         Ok(())
     }
 
+    // Create the D-Bus object of a GPU if it doesn't exist yet
+    async fn add_gpu(
+        &mut self,
+        uuid: String,
+        connection: &Connection,
+        tx_dbus: &Sender<DBusServiceMessage>,
+        tx_err: &Sender<MossdError>,
+    ) -> Result<()> {
+        if self.gpu_objects.contains_key(&uuid) {
+            return Ok(());
+        }
+
+        trace!("Creating D-Bus object for GPU: {}", uuid);
+
+        self.gpu_count += 1;
+        let path = format!("{}/Gpu{}", SERVICE_PATH, self.gpu_count);
+
+        let gpu_vendor_info = Self::initialize_object(
+            path.clone(),
+            uuid.clone(),
+            connection,
+            tx_dbus.clone(),
+            tx_err.clone(),
+        )
+        .await?;
+
+        self.gpu_objects.insert(uuid, (path, gpu_vendor_info));
+
+        Ok(())
+    }
+
+    // Remove the D-Bus object of a GPU with all its interfaces
+    async fn remove_gpu(
+        &mut self,
+        uuid: &str,
+        connection: &Connection,
+    ) -> Result<()> {
+        let Some((path, gpu_vendor_info)) = self.gpu_objects.remove(uuid)
+        else {
+            return Ok(());
+        };
+
+        trace!("Removing D-Bus object for GPU: {}", uuid);
+
+        let object_server = connection.object_server();
+
+        // Remove the vendor interface first, the
+        // object is destroyed with its last interface
+        let vendor_result = match gpu_vendor_info {
+            GpuVendorInfo::Nvidia { .. } => {
+                object_server
+                    .remove::<NvidiaInterface, _>(path.as_str())
+                    .await
+            }
+            GpuVendorInfo::AMD { .. } => {
+                object_server.remove::<AmdInterface, _>(path.as_str()).await
+            }
+            GpuVendorInfo::Intel { .. } => {
+                object_server
+                    .remove::<IntelInterface, _>(path.as_str())
+                    .await
+            }
+            _ => Ok(false),
+        };
+        let gpu_result =
+            object_server.remove::<GpuInterface, _>(path.as_str()).await;
+
+        vendor_result.and(gpu_result).map_err(|e| DbusServiceError::DBusObject {
+            reason: "Error while removing GPU object".to_string(),
+            error: e.into(),
+        })?;
+
+        Ok(())
+    }
+
+    // Emit the thermal failsafe signal on the D-Bus object of a GPU
+    async fn emit_failsafe(
+        &self,
+        uuid: &str,
+        engaged: bool,
+        temp: u32,
+        connection: &Connection,
+    ) -> Result<()> {
+        let Some(interface) = self.gpu_interface(uuid, connection).await?
+        else {
+            return Ok(());
+        };
+
+        GpuInterface::thermal_failsafe(
+            interface.signal_emitter(),
+            engaged,
+            temp,
+        )
+        .await
+        .map_err(|e| DbusServiceError::DBusObject {
+            reason: "Error while emitting thermal failsafe signal".to_string(),
+            error: e.into(),
+        })
+    }
+
+    // Update the fan health property of a GPU object,
+    // a stalled fan is also announced with a signal
+    async fn update_fan_health(
+        &self,
+        uuid: &str,
+        fan: u32,
+        health: FanHealth,
+        connection: &Connection,
+    ) -> Result<()> {
+        let Some(interface) = self.gpu_interface(uuid, connection).await?
+        else {
+            return Ok(());
+        };
+
+        let emitter = interface.signal_emitter();
+        let mut gpu_interface = interface.get_mut().await;
+
+        if let Some(fan_health) = gpu_interface.fan_health.get_mut(fan as usize)
+        {
+            *fan_health = health;
+        }
+
+        let mut result = gpu_interface.fan_health_changed(emitter).await;
+
+        if health == FanHealth::Stalled {
+            result = result.and(GpuInterface::fan_stalled(emitter, fan).await);
+        }
+
+        result.map_err(|e| DbusServiceError::DBusObject {
+            reason: "Error while updating fan health".to_string(),
+            error: e.into(),
+        })
+    }
+
+    // Update the overclock quarantine property of a GPU object
+    async fn update_overclock_quarantine(
+        &self,
+        uuid: &str,
+        quarantined: bool,
+        connection: &Connection,
+    ) -> Result<()> {
+        let Some(interface) = self.gpu_interface(uuid, connection).await?
+        else {
+            return Ok(());
+        };
+
+        let emitter = interface.signal_emitter();
+        let mut gpu_interface = interface.get_mut().await;
+
+        gpu_interface.overclock_quarantined = quarantined;
+
+        gpu_interface
+            .overclock_quarantined_changed(emitter)
+            .await
+            .map_err(|e| DbusServiceError::DBusObject {
+                reason: "Error while updating overclock quarantine"
+                    .to_string(),
+                error: e.into(),
+            })
+    }
+
+    // Return the GPU interface of the D-Bus object of a GPU,
+    // None if the GPU doesn't have an object
+    async fn gpu_interface(
+        &self,
+        uuid: &str,
+        connection: &Connection,
+    ) -> Result<Option<InterfaceRef<GpuInterface>>> {
+        let Some((path, _)) = self.gpu_objects.get(uuid) else {
+            return Ok(None);
+        };
+
+        let interface = connection
+            .object_server()
+            .interface::<_, GpuInterface>(path.as_str())
+            .await
+            .map_err(|e| DbusServiceError::DBusObject {
+                reason: "Error while accessing GPU object".to_string(),
+                error: e.into(),
+            })?;
+
+        Ok(Some(interface))
+    }
+
+    // Create the D-Bus object of a GPU and
+    // return the GPU vendor infos
     async fn initialize_object(
         path: String,
         uuid: String,
@@ -395,7 +1040,7 @@ impl DBusService {
 
         tx_dbus: Sender<DBusServiceMessage>,
         tx_err: Sender<MossdError>,
-    ) -> Result<()> {
+    ) -> Result<GpuVendorInfo> {
         // Get the GPU vendor infos
         let (tx, rx) = oneshot::channel();
         let message = DBusServiceMessage::GetGpuVendorInfo {
@@ -443,7 +1088,7 @@ impl DBusService {
                     path.clone(),
                     NvidiaInterface::new(
                         uuid.clone(),
-                        gpu_vendor_info,
+                        gpu_vendor_info.clone(),
                         tx_dbus.clone(),
                         tx_err.clone(),
                     )
@@ -456,6 +1101,30 @@ impl DBusService {
                 })?;
         }
 
-        Ok(())
+        // Create an AMD interface if the GPU is AMD
+        if matches!(gpu_vendor_info, GpuVendorInfo::AMD { .. }) {
+            connection
+                .object_server()
+                .at(path.clone(), AmdInterface::new(gpu_vendor_info.clone()))
+                .await
+                .map_err(|e| DbusServiceError::DBusObject {
+                    reason: "Error while initializing GPU object".to_string(),
+                    error: e.into(),
+                })?;
+        }
+
+        // Create an Intel interface if the GPU is Intel
+        if matches!(gpu_vendor_info, GpuVendorInfo::Intel { .. }) {
+            connection
+                .object_server()
+                .at(path.clone(), IntelInterface::new(gpu_vendor_info.clone()))
+                .await
+                .map_err(|e| DbusServiceError::DBusObject {
+                    reason: "Error while initializing GPU object".to_string(),
+                    error: e.into(),
+                })?;
+        }
+
+        Ok(gpu_vendor_info)
     }
 }
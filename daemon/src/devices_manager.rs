@@ -1,35 +1,67 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
     sync::Arc,
     time::{Duration, Instant},
 };
 
-use nvml_wrapper::Nvml;
+use nvml_wrapper::{Nvml, error::NvmlError};
 use thiserror::Error;
 use tokio::{
     select,
     sync::{
         mpsc::{Receiver, Sender},
-        oneshot,
+        oneshot, watch,
     },
+    time::MissedTickBehavior,
 };
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, trace, warn};
 
 use crate::{
     errors::MossdError,
-    fan_curve::{FanCurve, fan_mode::FanMode},
+    fan_curve::{
+        FanCurve,
+        fan_curve_info::FanCurveInfo,
+        fan_mode::{FanMode, FanSelection},
+    },
     gpu_device::{
-        DEFAULT_FAN_UPDATE_INTERVAL, DeviceError, GpuDevice,
+        DEFAULT_FAN_UPDATE_INTERVAL, DeviceError, GpuDevice, GpuVendor,
+        amd_device::AmdDevice,
+        fan_monitor::{FanHealth, FanMonitor},
         gpu_config::GpuConfig,
         gpu_data::{GpuData, GpuVendorData},
         gpu_info::{GpuInfo, GpuVendorInfo},
+        intel_device::IntelDevice,
         nvidia_device::NvidiaDevice,
+        nvml_backend::{NvmlFailure, NvmlWrapperBackend},
+        sensor_fault::SensorFaultPolicy,
+        simulated_device::{SimulatedDevice, ThermalModel},
+        sysfs,
+        thermal_failsafe::{FailsafeSettings, ThermalFailsafe},
     },
+    runtime_state::{RuntimeState, RuntimeStateError},
+    systemd,
 };
 
 type Responder = oneshot::Sender<DevicesManagerAnswer>;
 
+// Default interval between two devices discovery
+pub const DEFAULT_DISCOVERY_INTERVAL: Duration = Duration::from_secs(5);
+
+// Interval between two heartbeats telling the watchdog that
+// the manager loop is progressing while there is no device
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+// First and maximum delay between two NVML initialization attempts,
+// the delay is doubled after every failed attempt
+const NVML_RETRY_MIN_DELAY: Duration = Duration::from_secs(1);
+const NVML_RETRY_MAX_DELAY: Duration = Duration::from_secs(60);
+
+// Number of consecutive unknown errors of the NVML probe
+// after which the driver is considered lost
+const NVML_PROBE_FAILURES: u32 = 3;
+
 // Alias the result type for this module
 type Result<T> = std::result::Result<T, DevicesManagerError>;
 
@@ -46,6 +78,10 @@ pub enum DevicesManagerError {
     TX { reason: String },
     #[error("Device manager channel invalid device error: {reason}")]
     InvalidDevice { reason: String },
+    #[error("Device manager fan failure error: {reason}")]
+    FanFailure { reason: String },
+    #[error(transparent)]
+    RuntimeState(#[from] RuntimeStateError),
 }
 
 #[derive(Debug)]
@@ -82,27 +118,77 @@ pub enum DevicesManagerMessage {
         interval: Duration,
     },
 
-    // Set the device fan mode
+    // Get the info of the fan curve used by every device fan
+    GetDeviceFanCurves {
+        uuid: String,
+        tx: Responder,
+    },
+    // Set the fan mode of the selected device fans
     SetDeviceFanMode {
         uuid: String,
+        fans: FanSelection,
         fan_mode: FanMode,
     },
-    // Set the device fan curve
+    // Set the fan curve of the selected device fans
     SetDeviceFanCurve {
         uuid: String,
-        fan_curve: Box<dyn FanCurve + Send>,
+        fans: FanSelection,
+        fan_curve: Box<dyn FanCurve>,
     },
     // Set the fan update interval for the device
     SetDeviceFanUpdateInterval {
         uuid: String,
         interval: Duration,
     },
+    // Set the policy applied to the device fans
+    // when its temperature can't be read
+    SetDeviceSensorFaultPolicy {
+        uuid: String,
+        policy: SensorFaultPolicy,
+    },
 
     // Apply the given GPU configuration to the device
     ApplyDeviceGpuConfig {
         uuid: String,
         config: GpuConfig,
     },
+    // Restore the device settings from before the daemon started
+    ResetDevice {
+        uuid: String,
+    },
+}
+
+// Notifications sent by the devices manager
+// when the devices on the system change
+#[derive(Debug)]
+pub enum DevicesManagerEvent {
+    // A new device was discovered and is ready to be configured
+    DeviceAdded {
+        uuid: String,
+    },
+    // A device was unplugged or lost and is no longer managed
+    DeviceRemoved {
+        uuid: String,
+    },
+
+    // The device temperature reached the failsafe threshold
+    // and the fans were forced to full speed
+    FailsafeEngaged {
+        uuid: String,
+        temp: u32,
+    },
+    // The device cooled down and the fans are back to their mode
+    FailsafeReleased {
+        uuid: String,
+        temp: u32,
+    },
+
+    // The health of a device fan changed
+    FanHealthChanged {
+        uuid: String,
+        fan: u32,
+        health: FanHealth,
+    },
 }
 
 #[derive(Debug)]
@@ -114,82 +200,180 @@ pub enum DevicesManagerAnswer {
 
     DeviceData(Option<GpuData>),
     DeviceVendorData(Option<GpuVendorData>),
+
+    DeviceFanCurves(Vec<FanCurveInfo>),
 }
 
 pub struct DevicesManager {
     devices: HashMap<String, Box<dyn GpuDevice + Send>>,
 
+    // NVML context, None if NVML failed to initialize
+    nvml: Option<Arc<Nvml>>,
+    // Delay before the next NVML initialization attempt,
+    // None if NVML is initialized or won't be retried
+    nvml_retry_delay: Option<Duration>,
+    // Instant of the next NVML initialization attempt
+    nvml_next_retry: Instant,
+    // Consecutive unknown errors of the NVML probe
+    nvml_probe_failures: u32,
+    // Sysfs mount point used to discover the AMD and Intel GPUs
+    sysfs_root: PathBuf,
+    // Interval between two devices discovery, None to disable it
+    discovery_interval: Option<Duration>,
+
     // Store the fan update interval for all the devices
     fan_update_intervals: HashMap<String, Duration>,
     // Store the last fan update instant for all the devices
     last_fan_updates: HashMap<String, Instant>,
+
+    // Thermal failsafe settings and state of all the devices
+    failsafe_settings: FailsafeSettings,
+    failsafes: HashMap<String, ThermalFailsafe>,
+    // Store the last GPU configuration applied to all the devices,
+    // it is applied again when the failsafe restore the power limit
+    gpu_configs: HashMap<String, GpuConfig>,
+    // Store the fans health of all the devices
+    fan_monitors: HashMap<String, FanMonitor>,
+
+    // Record the original settings of the devices taken over
+    runtime_state: RuntimeState,
+    // Devices recorded by a previous instance that didn't exit
+    // cleanly, their settings are restored once they are found
+    pending_recovery: HashSet<String>,
 }
 
 impl DevicesManager {
-    pub fn new() -> Self {
-        // Attempt to initialize NVML
-        let nvml = if let Ok(nvml) = Nvml::init() {
-            info!("NVML successfully initialized");
+    // Create the devices manager, the AMD and Intel GPUs are discovered
+    // from the given sysfs mount point, the simulated GPUs are added
+    // on top of the real ones
+    // The devices are discovered again at the given interval
+    // to handle the GPUs added or removed at runtime
+    // The thermal failsafe of every device use the given settings
+    // The devices left behind by a previous instance in the given
+    // state file are restored before anything else is applied
+    pub fn new(
+        sysfs_root: &Path,
+        simulated_gpus: u32,
+        thermal_model: ThermalModel,
+        discovery_interval: Option<Duration>,
+        failsafe_settings: FailsafeSettings,
+        state_file: &Path,
+    ) -> Self {
+        let runtime_state =
+            RuntimeState::load(state_file).unwrap_or_else(|e| {
+                warn!("Failed to load runtime state, can't recover: {}", e);
+                RuntimeState::new(state_file)
+            });
+
+        let pending_recovery: HashSet<String> =
+            runtime_state.uuids().into_iter().collect();
+
+        if !pending_recovery.is_empty() {
+            warn!(
+                "Previous instance didn't exit cleanly, recovering: {:?}",
+                pending_recovery
+            );
+        }
 
-            Some(Arc::new(nvml))
-        } else {
-            None
+        let mut devices_manager = Self {
+            devices: HashMap::new(),
+
+            nvml: None,
+            nvml_retry_delay: None,
+            nvml_next_retry: Instant::now(),
+            nvml_probe_failures: 0,
+            sysfs_root: sysfs_root.to_path_buf(),
+            discovery_interval,
+
+            fan_update_intervals: HashMap::new(),
+            last_fan_updates: HashMap::new(),
+
+            failsafe_settings,
+            failsafes: HashMap::new(),
+            gpu_configs: HashMap::new(),
+            fan_monitors: HashMap::new(),
+
+            runtime_state,
+            pending_recovery,
         };
 
-        let mut devices: HashMap<String, Box<dyn GpuDevice + Send>> =
-            HashMap::new();
+        // Attempt to initialize NVML, on failure
+        // it is retried in the background
+        devices_manager.init_nvml();
 
-        // If NVML was initialized find the Nvidia GPUs on the system
-        if let Some(nvml) = nvml.clone() {
-            Self::discover_nvidia_gpus(nvml, &mut devices).unwrap_or_else(
-                |e| {
-                    warn!("Error during Nvidia GPUs discovery: {}", e);
-                },
-            );
-        }
+        // Find the GPUs on the system
+        devices_manager.discover_devices();
 
-        // Create a hash map with the default fan update interval
-        // for each device
-        // Create a hash map with the last fan update instant
-        let mut fan_update_intervals = HashMap::new();
-        let mut last_fan_updates = HashMap::new();
+        // Create the simulated GPUs
+        for i in 0..simulated_gpus {
+            let device = Box::new(SimulatedDevice::new(i, thermal_model));
+            let uuid = device.get_info().uuid;
 
-        for (uuid, device) in devices.iter_mut() {
-            // Update the fan speed for the first time
-            if let Err(e) = device.update_fan() {
-                warn!(
-                    "Error while updating fan speed on device creation: {}",
-                    e
-                )
-            }
+            info!("Adding simulated device: \"{}\"", uuid);
 
-            last_fan_updates.insert(uuid.clone(), Instant::now());
-            fan_update_intervals
-                .insert(uuid.clone(), DEFAULT_FAN_UPDATE_INTERVAL);
+            devices_manager.devices.insert(uuid.clone(), device);
+            devices_manager.init_device(&uuid);
         }
 
-        Self {
-            devices,
-            fan_update_intervals,
-            last_fan_updates,
-        }
+        devices_manager
     }
 
     pub async fn run(
         &mut self,
         run_token: CancellationToken,
         mut rx_message: Receiver<DevicesManagerMessage>,
+        tx_event: Sender<DevicesManagerEvent>,
+        tx_heartbeat: watch::Sender<Instant>,
         tx_err: Sender<MossdError>,
     ) {
-        let (mut next_fan_update_device, mut next_fan_update_time) =
-            self.schedule_fan_update();
+        let mut heartbeat_interval = tokio::time::interval(HEARTBEAT_INTERVAL);
+        heartbeat_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut idle_deadline = Instant::now() + HEARTBEAT_INTERVAL;
+
+        // Start the discovery interval one period from now since the
+        // devices were just discovered, the period is only used if
+        // the discovery is enabled
+        let discovery_period = self
+            .discovery_interval
+            .unwrap_or(DEFAULT_DISCOVERY_INTERVAL);
+        let mut discovery_interval = tokio::time::interval_at(
+            tokio::time::Instant::now() + discovery_period,
+            discovery_period,
+        );
+        discovery_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        let mut status = String::new();
 
         loop {
+            // Tell systemd about the devices, failsafe and driver changes
+            let new_status = self.status();
+            if new_status != status {
+                systemd::notify_status(&new_status);
+                status = new_status;
+            }
+
+            // The deadline is absolute, the fan update isn't
+            // delayed by the other branches completing first
+            let next_fan_update = self.schedule_fan_update();
+            let fan_update_deadline =
+                next_fan_update.as_ref().map(|(_, deadline)| *deadline);
+
+            // Tell the watchdog when the loop must progress again, the
+            // deadline only move forward once the fans are updated
+            let heartbeat_deadline =
+                fan_update_deadline.unwrap_or(idle_deadline);
+            tx_heartbeat.send_if_modified(|deadline| {
+                let modified = *deadline != heartbeat_deadline;
+                *deadline = heartbeat_deadline;
+
+                modified
+            });
+
             select! {
                 _ = run_token.cancelled() => {
                     info!("devices manager: Quiting");
 
-                    if let Err(err) = self.quit_manager() {
+                    if let Err(err) = self.release_devices() {
                         error!("Error while quitting devices manager: {}", err);
                     }
 
@@ -201,31 +385,514 @@ impl DevicesManager {
                     if let Err(err) = self.parse_message(message) {
                         error!("Error during message handling: {}", err);
 
-                        tx_err.send(err.into()).await.unwrap_or_else(|err| {
-                            error!("Failed to send error over channel: {err}");
-                        });
+                        Self::send_error(&tx_err, err).await;
                     }
                 },
-                // Update the fan and schedule the next update
-                _ = tokio::time::sleep(next_fan_update_time) => {
-                    if let Err(err) = self.update_fans(&next_fan_update_device)
-                    {
-                        error!("Error during fan update: {}", err);
-
-                        tx_err.send(err.into()).await.unwrap_or_else(|err| {
-                            error!("Failed to send error over channel: {err}");
-                        });
+                // Update the fan once its deadline is reached, the
+                // thermal failsafe is checked first and the fans
+                // health once they reached their new speed
+                _ = sleep_until_deadline(fan_update_deadline) => {
+                    let Some((uuid, _)) = next_fan_update else {
+                        continue;
+                    };
+
+                    self.fan_update(&uuid, &tx_event, &tx_err).await;
+                }
+                // Without devices there is no fan update to wait
+                // for, keep the heartbeat deadline moving
+                _ = heartbeat_interval.tick(),
+                    if fan_update_deadline.is_none() =>
+                {
+                    idle_deadline = Instant::now() + HEARTBEAT_INTERVAL;
+                }
+                // Look for added or removed devices
+                _ = discovery_interval.tick(),
+                    if self.discovery_interval.is_some() =>
+                {
+                    let mut removed = self.check_nvml();
+                    removed.extend(self.remove_lost_devices());
+                    let added = self.discover_devices();
+
+                    Self::send_events(&tx_event, added, removed).await;
+                }
+                // Retry to initialize NVML and add the Nvidia devices,
+                // the state manager apply their profile once added
+                _ = tokio::time::sleep_until(self.nvml_next_retry.into()),
+                    if self.nvml.is_none() && self.nvml_retry_delay.is_some() =>
+                {
+                    if self.init_nvml() {
+                        let added = self.discover_devices();
+
+                        Self::send_events(&tx_event, added, Vec::new()).await;
                     }
+                }
+            }
+        }
+    }
+
+    // Describe the state of the devices for the systemd status
+    fn status(&self) -> String {
+        let mut status =
+            format!("Controlling the fans of {} GPUs", self.devices.len());
+
+        let engaged = self
+            .failsafes
+            .values()
+            .filter(|failsafe| failsafe.is_engaged())
+            .count();
+        if engaged > 0 {
+            status.push_str(&format!(
+                ", thermal failsafe engaged on {} GPUs",
+                engaged
+            ));
+        }
+
+        if self.nvml.is_none() && self.nvml_retry_delay.is_some() {
+            status.push_str(", Nvidia driver unavailable, retrying NVML");
+        }
+
+        status
+    }
+
+    // Discover the GPUs on the system not already managed,
+    // return the UUIDs of the added devices
+    fn discover_devices(&mut self) -> Vec<String> {
+        let known: HashSet<String> = self.devices.keys().cloned().collect();
+
+        // If NVML was initialized find the Nvidia GPUs on the system
+        if let Some(nvml) = self.nvml.clone() {
+            Self::discover_nvidia_gpus(nvml, &mut self.devices).unwrap_or_else(
+                |e| {
+                    warn!("Error during Nvidia GPUs discovery: {}", e);
+                },
+            );
+        }
+
+        // Find the AMD GPUs on the system
+        Self::discover_amd_gpus(&self.sysfs_root, &mut self.devices)
+            .unwrap_or_else(|e| {
+                warn!("Error during AMD GPUs discovery: {}", e);
+            });
+
+        // Find the Intel GPUs on the system
+        Self::discover_intel_gpus(&self.sysfs_root, &mut self.devices)
+            .unwrap_or_else(|e| {
+                warn!("Error during Intel GPUs discovery: {}", e);
+            });
+
+        let added: Vec<String> = self
+            .devices
+            .keys()
+            .filter(|uuid| !known.contains(*uuid))
+            .cloned()
+            .collect();
+
+        for uuid in added.iter() {
+            info!("Adding device: \"{}\"", uuid);
+            self.init_device(uuid);
+        }
+
+        added
+    }
+
+    // Remove the devices that are no longer reachable,
+    // return the UUIDs of the removed devices
+    fn remove_lost_devices(&mut self) -> Vec<String> {
+        let lost: Vec<String> = self
+            .devices
+            .iter()
+            .filter(|(_, device)| !device.is_available())
+            .map(|(uuid, _)| uuid.clone())
+            .collect();
+
+        for uuid in lost.iter() {
+            info!("Removing lost device: \"{}\"", uuid);
+            self.remove_device(uuid);
+        }
+
+        lost
+    }
+
+    // Stop managing the given device
+    fn remove_device(&mut self, uuid: &str) {
+        self.devices.remove(uuid);
+        self.fan_update_intervals.remove(uuid);
+        self.last_fan_updates.remove(uuid);
+        self.failsafes.remove(uuid);
+        self.gpu_configs.remove(uuid);
+        self.fan_monitors.remove(uuid);
+
+        if let Err(e) = self.runtime_state.remove(uuid) {
+            warn!("Failed to update runtime state: {}", e);
+        }
+    }
+
+    // Attempt to initialize NVML, on failure the next attempt
+    // is scheduled with an exponential backoff
+    // Return true if NVML was initialized
+    fn init_nvml(&mut self) -> bool {
+        let error = match Nvml::init() {
+            Ok(nvml) => {
+                info!("NVML successfully initialized");
+
+                self.nvml = Some(Arc::new(nvml));
+                self.nvml_retry_delay = None;
+
+                return true;
+            }
+            Err(error) => error,
+        };
+
+        // Only report the first failure, the following
+        // attempts are expected to fail until the driver is ready
+        let first_attempt = self.nvml_retry_delay.is_none();
+
+        match NvmlFailure::classify(&error) {
+            // Without the library there is no Nvidia driver
+            // on the system, there is no point in retrying
+            NvmlFailure::LibraryNotFound => {
+                info!("NVML library not found, Nvidia GPUs won't be managed");
+
+                self.nvml_retry_delay = None;
+                return false;
+            }
+            NvmlFailure::DriverNotLoaded if first_attempt => {
+                warn!("Nvidia driver not loaded, retrying NVML initialization")
+            }
+            NvmlFailure::NoPermission if first_attempt => {
+                error!(
+                    "Permission denied while initializing NVML, retrying: {}",
+                    error
+                )
+            }
+            _ if first_attempt => {
+                warn!("Failed to initialize NVML, retrying: {}", error)
+            }
+            _ => debug!("Failed to initialize NVML: {}", error),
+        }
+
+        let delay = self.nvml_retry_delay.unwrap_or(NVML_RETRY_MIN_DELAY);
+
+        self.nvml_next_retry = Instant::now() + delay;
+        self.nvml_retry_delay = Some((delay * 2).min(NVML_RETRY_MAX_DELAY));
+
+        false
+    }
+
+    // Drop the NVML context and the Nvidia devices if the driver
+    // stopped working, NVML is then initialized again in the background
+    // Return the UUIDs of the removed devices
+    fn check_nvml(&mut self) -> Vec<String> {
+        let Some(nvml) = &self.nvml else {
+            return Vec::new();
+        };
+
+        match nvml.device_count() {
+            Err(e) if NvmlFailure::classify(&e).is_driver_level() => {
+                warn!(
+                    "Nvidia driver stopped working, reinitializing NVML: {}",
+                    e
+                )
+            }
+            // An unknown error can come from a single flaky call,
+            // the driver is only lost if the probe keeps failing
+            Err(NvmlError::Unknown) => {
+                self.nvml_probe_failures += 1;
+
+                if self.nvml_probe_failures < NVML_PROBE_FAILURES {
+                    debug!("NVML probe failed with an unknown error");
+                    return Vec::new();
+                }
+
+                warn!("NVML probe keeps failing, reinitializing NVML")
+            }
+            _ => {
+                self.nvml_probe_failures = 0;
+                return Vec::new();
+            }
+        }
+
+        let removed: Vec<String> = self
+            .devices
+            .iter()
+            .filter(|(_, device)| {
+                matches!(device.get_vendor(), GpuVendor::Nvidia)
+            })
+            .map(|(uuid, _)| uuid.clone())
+            .collect();
+
+        for uuid in removed.iter() {
+            self.remove_device(uuid);
+        }
+
+        // The devices hold a reference to the context,
+        // it is released once they are all removed
+        self.nvml = None;
+        self.nvml_probe_failures = 0;
+        self.nvml_retry_delay = Some(NVML_RETRY_MIN_DELAY);
+        self.nvml_next_retry = Instant::now();
+
+        removed
+    }
+
+    // Restore the settings recorded by a previous instance that
+    // didn't exit cleanly, before this instance touch the device
+    fn recover_device(&mut self, uuid: &str) {
+        if !self.pending_recovery.remove(uuid) {
+            return;
+        }
+
+        let (Some(device), Some(settings)) =
+            (self.devices.get_mut(uuid), self.runtime_state.get(uuid))
+        else {
+            return;
+        };
+
+        info!("Restoring the recorded settings of \"{}\"", uuid);
+
+        device.set_original_settings(settings.clone());
+
+        if let Err(e) = device.reset() {
+            warn!("Failed to restore the settings of \"{}\": {}", uuid, e);
+        }
+    }
+
+    // Set up the fan update schedule of a newly added device
+    fn init_device(&mut self, uuid: &str) {
+        self.recover_device(uuid);
+
+        if let Some(device) = self.devices.get_mut(uuid) {
+            // Record the device before taking it over, the
+            // simulated devices don't outlive the daemon
+            let simulated = matches!(device.get_vendor(), GpuVendor::Simulated);
+
+            if !simulated
+                && let Err(e) = self
+                    .runtime_state
+                    .insert(uuid, device.get_original_settings())
+            {
+                warn!("Failed to update runtime state: {}", e);
+            }
+
+            // Update the fan speed for the first time
+            if let Err(e) = device.update_fan() {
+                warn!(
+                    "Error while updating fan speed on device creation: {}",
+                    e
+                )
+            }
+
+            self.last_fan_updates
+                .insert(uuid.to_string(), Instant::now());
+            self.fan_update_intervals
+                .insert(uuid.to_string(), DEFAULT_FAN_UPDATE_INTERVAL);
+
+            let failsafe = ThermalFailsafe::new(
+                &self.failsafe_settings,
+                &device.get_vendor_info(),
+            );
+
+            match failsafe.threshold() {
+                Some(temp) => {
+                    debug!("Thermal failsafe of \"{}\" at {}°C", uuid, temp)
+                }
+                None => warn!(
+                    "No temperature threshold for \"{}\", \
+                    thermal failsafe disabled",
+                    uuid
+                ),
+            }
+
+            self.failsafes.insert(uuid.to_string(), failsafe);
+            self.fan_monitors
+                .insert(uuid.to_string(), FanMonitor::new(&device.get_info()));
+        }
+    }
+
+    // Engage or release the thermal failsafe of the given device
+    // according to its temperature, the failsafe state change
+    // events are added to the given list
+    fn check_failsafe(
+        &mut self,
+        uuid: &str,
+        events: &mut Vec<DevicesManagerEvent>,
+    ) -> Result<()> {
+        let (Some(device), Some(failsafe)) =
+            (self.devices.get_mut(uuid), self.failsafes.get_mut(uuid))
+        else {
+            return Ok(());
+        };
+
+        // The failsafe state is kept while the temperature can't be read,
+        // the fans are then handled by the sensor fault policy
+        let Some(temp) = device.get_data()?.temp_gpu else {
+            return Ok(());
+        };
+
+        let changed = failsafe.update(temp);
+        let engaged = failsafe.is_engaged();
 
-                    (next_fan_update_device, next_fan_update_time) =
-                        self.schedule_fan_update();
+        match changed {
+            Some(true) => {
+                warn!(
+                    "\"{}\" reached {}°C, thermal failsafe engaged",
+                    uuid, temp
+                );
+                events.push(DevicesManagerEvent::FailsafeEngaged {
+                    uuid: uuid.to_string(),
+                    temp,
+                });
+            }
+            Some(false) => {
+                info!(
+                    "\"{}\" cooled down to {}°C, thermal failsafe released",
+                    uuid, temp
+                );
+                events.push(DevicesManagerEvent::FailsafeReleased {
+                    uuid: uuid.to_string(),
+                    temp,
+                });
+            }
+            None => (),
+        }
+
+        // The fan failsafe is applied on every update so
+        // a failed attempt is retried on the next one
+        device.set_fan_failsafe(engaged)?;
+
+        if changed.is_some() && self.failsafe_settings.drop_power_limit {
+            let config =
+                self.gpu_configs.get(uuid).cloned().unwrap_or_default();
+
+            self.apply_gpu_config(uuid, config)?;
+        }
+
+        Ok(())
+    }
+
+    // Update the fans health of the given device, the fans health change
+    // events are added to the given list
+    // Return the index of the fans that just stalled
+    fn check_fans_health(
+        &mut self,
+        uuid: &str,
+        events: &mut Vec<DevicesManagerEvent>,
+    ) -> Result<Vec<u32>> {
+        let (Some(device), Some(fan_monitor)) =
+            (self.devices.get_mut(uuid), self.fan_monitors.get_mut(uuid))
+        else {
+            return Ok(Vec::new());
+        };
+
+        let changed = fan_monitor.update(&device.get_data()?.fans);
+        let mut stalled = Vec::new();
+
+        for (fan, health) in changed {
+            match health {
+                FanHealth::Stalled => stalled.push(fan),
+                _ => {
+                    info!("Fan {} of \"{}\" is {}", fan, uuid, health.as_str())
                 }
             }
+
+            events.push(DevicesManagerEvent::FanHealthChanged {
+                uuid: uuid.to_string(),
+                fan,
+                health,
+            });
+        }
+
+        // Let the driver handle the fans as a precaution
+        if !stalled.is_empty() && self.failsafe_settings.auto_on_fan_stall {
+            warn!(
+                "Fan stalled, giving fans control of \"{}\" back to the driver",
+                uuid
+            );
+
+            device.set_fan_mode(FanSelection::All, FanMode::Auto)?;
+        }
+
+        Ok(stalled)
+    }
+
+    // Apply the given GPU configuration to the device, the power
+    // limit is kept at its minimum while the failsafe is engaged
+    fn apply_gpu_config(
+        &mut self,
+        uuid: &str,
+        config: GpuConfig,
+    ) -> Result<()> {
+        let device = self.devices.get_mut(uuid).ok_or_else(|| {
+            DevicesManagerError::InvalidDevice {
+                reason: "Trying to access non-existing device".to_string(),
+            }
+        })?;
+
+        self.gpu_configs.insert(uuid.to_string(), config.clone());
+
+        let engaged = self
+            .failsafes
+            .get(uuid)
+            .is_some_and(|failsafe| failsafe.is_engaged());
+        let info = device.get_info();
+
+        let config = if engaged
+            && self.failsafe_settings.drop_power_limit
+            && info.capabilities.power_limit
+        {
+            GpuConfig {
+                power_limit: Some(info.power_limit_min),
+                ..config
+            }
+        } else {
+            config
+        };
+
+        device.apply_gpu_config(config)?;
+
+        Ok(())
+    }
+
+    // Send the given event to the state manager
+    async fn send_event(
+        tx_event: &Sender<DevicesManagerEvent>,
+        event: DevicesManagerEvent,
+    ) {
+        tx_event.send(event).await.unwrap_or_else(|err| {
+            error!("Failed to send event over channel: {err}");
+        });
+    }
+
+    // Send the given error to the state manager
+    async fn send_error(tx_err: &Sender<MossdError>, err: DevicesManagerError) {
+        tx_err.send(err.into()).await.unwrap_or_else(|err| {
+            error!("Failed to send error over channel: {err}");
+        });
+    }
+
+    // Notify the state manager about the added and removed devices
+    async fn send_events(
+        tx_event: &Sender<DevicesManagerEvent>,
+        added: Vec<String>,
+        removed: Vec<String>,
+    ) {
+        let events = removed
+            .into_iter()
+            .map(|uuid| DevicesManagerEvent::DeviceRemoved { uuid })
+            .chain(
+                added
+                    .into_iter()
+                    .map(|uuid| DevicesManagerEvent::DeviceAdded { uuid }),
+            );
+
+        for event in events {
+            Self::send_event(tx_event, event).await;
         }
     }
 
     // Discover Nvidia GPUs on the system, create the associated
     // GPU devices and add them to the given hash map
+    // The devices already in the hash map are skipped
     fn discover_nvidia_gpus(
         nvml: Arc<Nvml>,
         devices_map: &mut HashMap<String, Box<dyn GpuDevice + Send>>,
@@ -254,10 +921,99 @@ impl DevicesManager {
                     error: e.into(),
                 })?;
 
+            if devices_map.contains_key(&uuid) {
+                continue;
+            }
+
             debug!("Found Nvidia device: \"{}\"", uuid);
 
-            // Create the GPU device
-            let device = Box::new(NvidiaDevice::new(nvml.clone(), &uuid)?);
+            // Create the GPU device, a device that fail to initialize
+            // is skipped so the other GPUs can still be managed
+            let backend = NvmlWrapperBackend::new(nvml.clone(), &uuid);
+            let device = match NvidiaDevice::new(Box::new(backend)) {
+                Ok(device) => Box::new(device),
+                Err(e) => {
+                    warn!("Skipping Nvidia device \"{}\": {}", uuid, e);
+                    continue;
+                }
+            };
+
+            // Add the device to the hash map
+            devices_map.insert(uuid, device);
+        }
+
+        Ok(())
+    }
+
+    // Discover AMD GPUs under the given sysfs root, create the
+    // associated GPU devices and add them to the given hash map
+    // The devices already in the hash map are skipped
+    fn discover_amd_gpus(
+        sysfs_root: &Path,
+        devices_map: &mut HashMap<String, Box<dyn GpuDevice + Send>>,
+    ) -> Result<()> {
+        for device_path in sysfs::drm_card_devices(sysfs_root) {
+            if !AmdDevice::is_amd_device(&device_path)
+                || devices_map.contains_key(&AmdDevice::get_uuid(&device_path))
+            {
+                continue;
+            }
+
+            // Create the GPU device, a device that fail to initialize
+            // is skipped so the other GPUs can still be managed
+            let device = match AmdDevice::new(&device_path) {
+                Ok(device) => Box::new(device),
+                Err(e) => {
+                    warn!(
+                        "Skipping AMD device \"{}\": {}",
+                        device_path.display(),
+                        e
+                    );
+                    continue;
+                }
+            };
+            let uuid = device.get_info().uuid;
+
+            debug!("Found AMD device: \"{}\"", uuid);
+
+            // Add the device to the hash map
+            devices_map.insert(uuid, device);
+        }
+
+        Ok(())
+    }
+
+    // Discover Intel GPUs under the given sysfs root, create the
+    // associated GPU devices and add them to the given hash map
+    // The devices already in the hash map are skipped
+    fn discover_intel_gpus(
+        sysfs_root: &Path,
+        devices_map: &mut HashMap<String, Box<dyn GpuDevice + Send>>,
+    ) -> Result<()> {
+        for device_path in sysfs::drm_card_devices(sysfs_root) {
+            if !IntelDevice::is_intel_device(&device_path)
+                || devices_map
+                    .contains_key(&IntelDevice::get_uuid(&device_path))
+            {
+                continue;
+            }
+
+            // Create the GPU device, a device that fail to initialize
+            // is skipped so the other GPUs can still be managed
+            let device = match IntelDevice::new(&device_path) {
+                Ok(device) => Box::new(device),
+                Err(e) => {
+                    warn!(
+                        "Skipping Intel device \"{}\": {}",
+                        device_path.display(),
+                        e
+                    );
+                    continue;
+                }
+            };
+            let uuid = device.get_info().uuid;
+
+            debug!("Found Intel device: \"{}\"", uuid);
 
             // Add the device to the hash map
             devices_map.insert(uuid, device);
@@ -374,83 +1130,192 @@ impl DevicesManager {
                 device.set_data_update_interval(interval);
             }
 
-            DevicesManagerMessage::SetDeviceFanMode { uuid, fan_mode } => {
+            DevicesManagerMessage::GetDeviceFanCurves { uuid, tx } => {
+                let device = self.devices.get(&uuid).ok_or_else(|| {
+                    DevicesManagerError::InvalidDevice {
+                        reason: "Trying to access non-existing device"
+                            .to_string(),
+                    }
+                })?;
+
+                let answer = DevicesManagerAnswer::DeviceFanCurves(
+                    device.get_fan_curves(),
+                );
+                tx.send(answer).map_err(|v| DevicesManagerError::TX {
+                    reason: format!(
+                        "Failed to send answer over channel: ({:?})",
+                        v
+                    ),
+                })?
+            }
+            DevicesManagerMessage::SetDeviceFanMode {
+                uuid,
+                fans,
+                fan_mode,
+            } => {
                 let device = self.devices.get_mut(&uuid).ok_or_else(|| {
                     DevicesManagerError::InvalidDevice {
                         reason: format!("Trying to access non-existing device"),
                     }
                 })?;
 
-                device.set_fan_mode(fan_mode)?;
+                device.set_fan_mode(fans, fan_mode)?;
             }
-            DevicesManagerMessage::SetDeviceFanCurve { uuid, fan_curve } => {
+            DevicesManagerMessage::SetDeviceFanCurve {
+                uuid,
+                fans,
+                fan_curve,
+            } => {
                 let device = self.devices.get_mut(&uuid).ok_or_else(|| {
                     DevicesManagerError::InvalidDevice {
                         reason: format!("Trying to access non-existing device"),
                     }
                 })?;
 
-                device.set_fan_curve(fan_curve);
+                device.set_fan_curve(fans, fan_curve)?;
             }
             DevicesManagerMessage::SetDeviceFanUpdateInterval {
                 uuid,
                 interval,
             } => {
+                let device = self.devices.get_mut(&uuid).ok_or_else(|| {
+                    DevicesManagerError::InvalidDevice {
+                        reason: "Trying to access non-existing device"
+                            .to_string(),
+                    }
+                })?;
+
+                device.set_fan_update_interval(interval);
                 self.fan_update_intervals.insert(uuid, interval);
             }
+            DevicesManagerMessage::SetDeviceSensorFaultPolicy {
+                uuid,
+                policy,
+            } => {
+                let device = self.devices.get_mut(&uuid).ok_or_else(|| {
+                    DevicesManagerError::InvalidDevice {
+                        reason: "Trying to access non-existing device"
+                            .to_string(),
+                    }
+                })?;
+
+                device.set_sensor_fault_policy(policy);
+            }
 
             DevicesManagerMessage::ApplyDeviceGpuConfig { uuid, config } => {
+                self.apply_gpu_config(&uuid, config)?;
+            }
+            DevicesManagerMessage::ResetDevice { uuid } => {
                 let device = self.devices.get_mut(&uuid).ok_or_else(|| {
                     DevicesManagerError::InvalidDevice {
-                        reason: format!("Trying to access non-existing device"),
+                        reason: "Trying to access non-existing device"
+                            .to_string(),
                     }
                 })?;
 
-                device.apply_gpu_config(config)?;
+                device.reset()?;
+
+                // The reset release the fans, the failsafe
+                // engage again on the next update if needed
+                self.gpu_configs.remove(&uuid);
+                self.failsafes.insert(
+                    uuid,
+                    ThermalFailsafe::new(
+                        &self.failsafe_settings,
+                        &device.get_vendor_info(),
+                    ),
+                );
             }
         }
 
         Ok(())
     }
 
-    // Return the duration until the next required fan update
-    // also return the UUID of the device to update
-    fn schedule_fan_update(&self) -> (String, Duration) {
-        let mut smallest_delta = Duration::MAX;
-        let mut update_device = String::new();
-
-        for (uuid, last_update) in self.last_fan_updates.iter() {
-            let interval = self.fan_update_intervals.get(uuid).unwrap().clone();
-
-            // Time since the last update
-            let elapsed = last_update.elapsed();
-            // Time to the next update
-            let delta = if interval > elapsed {
-                interval - elapsed
-            } else {
-                Duration::from_secs(0)
-            };
+    // Return the instant of the next required fan update and the
+    // UUID of the device to update, None if there is no device
+    fn schedule_fan_update(&self) -> Option<(String, Instant)> {
+        self.last_fan_updates
+            .iter()
+            .filter_map(|(uuid, last_update)| {
+                let interval = self.fan_update_intervals.get(uuid)?;
+
+                Some((uuid.clone(), *last_update + *interval))
+            })
+            .min_by_key(|(_, deadline)| *deadline)
+    }
+
+    // Check the thermal failsafe and the fans health of the given device
+    // then update its fans, the fans are updated even if the checks
+    // failed so the fan update deadline always move forward
+    // A stalled fan is reported on its own, it isn't a failed update
+    async fn fan_update(
+        &mut self,
+        uuid: &str,
+        tx_event: &Sender<DevicesManagerEvent>,
+        tx_err: &Sender<MossdError>,
+    ) {
+        let mut events = Vec::new();
+        let failsafe = self.check_failsafe(uuid, &mut events);
+        let fans_health = self.check_fans_health(uuid, &mut events);
+        let update = self.update_fans(uuid);
+
+        for event in events {
+            Self::send_event(tx_event, event).await;
+        }
+
+        let mut errors: Vec<DevicesManagerError> =
+            failsafe.err().into_iter().collect();
+
+        match fans_health {
+            Ok(stalled) if !stalled.is_empty() => {
+                let err = DevicesManagerError::FanFailure {
+                    reason: format!(
+                        "Fan {:?} of \"{}\" stalled, its speed is far below \
+                        the expected one",
+                        stalled, uuid
+                    ),
+                };
+                error!("{}", err);
+
+                Self::send_error(tx_err, err).await;
+            }
+            Ok(_) => (),
+            Err(err) => errors.push(err),
+        }
+
+        errors.extend(update.err());
+
+        if errors.is_empty() {
+            return;
+        }
 
-            if delta < smallest_delta {
-                smallest_delta = delta;
-                update_device = uuid.clone();
+        // Don't report the errors if the device or the
+        // driver was lost, remove them right away instead
+        let mut removed = self.check_nvml();
+        removed.extend(self.remove_lost_devices());
+
+        if removed.is_empty() {
+            for err in errors {
+                error!("Error during fan update: {}", err);
+
+                Self::send_error(tx_err, err).await;
             }
         }
 
-        (update_device, smallest_delta)
+        Self::send_events(tx_event, Vec::new(), removed).await;
     }
 
     // Update the fans on the given device and update the last
-    // fan update time
+    // fan update time, even if the update reported an error
     fn update_fans(&mut self, uuid: &str) -> Result<()> {
         if let Some(device) = self.devices.get_mut(uuid) {
-            device.update_fan()?;
+            let result = device.update_fan();
 
             // Update last update time
             self.last_fan_updates
                 .insert(uuid.to_string(), Instant::now());
 
-            Ok(())
+            Ok(result?)
         } else {
             Err(DevicesManagerError::InvalidDevice {
                 reason: format!(
@@ -461,13 +1326,116 @@ impl DevicesManager {
         }
     }
 
-    // Restore the default setting for all device before quitting
-    fn quit_manager(&mut self) -> Result<()> {
-        for (_, device) in self.devices.iter_mut() {
-            device.set_fan_mode(FanMode::Auto)?;
-            device.apply_gpu_config(GpuConfig::default())?;
+    // Restore the original settings of all device before quitting,
+    // a failure doesn't prevent the other devices from being restored
+    // The devices that failed to be restored are left in the runtime
+    // state so the next instance can try again
+    pub fn release_devices(&mut self) -> Result<()> {
+        let mut result = Ok(());
+
+        for (uuid, device) in self.devices.iter_mut() {
+            if let Err(err) = device.reset() {
+                error!("Failed to restore the settings of \"{}\"", uuid);
+
+                if result.is_ok() {
+                    result = Err(err.into());
+                }
+
+                continue;
+            }
+
+            if let Err(err) = self.runtime_state.remove(uuid)
+                && result.is_ok()
+            {
+                result = Err(err.into());
+            }
         }
 
-        Ok(())
+        result
+    }
+}
+
+// Wait until the given deadline, forever if there is none
+async fn sleep_until_deadline(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gpu_device::{
+        fake_nvml_backend::{FakeNvmlBackend, FakeNvmlState},
+        fan_monitor::STALL_UPDATES,
+    };
+    use tokio::sync::mpsc;
+
+    #[tokio::test]
+    async fn stalled_fan_does_not_stop_the_fan_update() {
+        let temp_dir = std::env::temp_dir();
+        let state_file = temp_dir
+            .join(format!("mossd-stall-state-{}.json", std::process::id()));
+
+        let mut devices_manager = DevicesManager::new(
+            &temp_dir.join("mossd-no-sysfs"),
+            0,
+            ThermalModel::default(),
+            None,
+            FailsafeSettings::default(),
+            &state_file,
+        );
+
+        // Fan that never spins whatever its duty
+        let fake = FakeNvmlBackend::new(FakeNvmlState {
+            fan_speeds_rpm: vec![0],
+            ..Default::default()
+        });
+        let mut device = NvidiaDevice::new(Box::new(fake.clone())).unwrap();
+        device.set_data_update_interval(Duration::ZERO);
+        device
+            .set_fan_mode(FanSelection::All, FanMode::Curve)
+            .unwrap();
+
+        let uuid = device.get_info().uuid;
+        devices_manager
+            .devices
+            .insert(uuid.clone(), Box::new(device));
+        devices_manager.init_device(&uuid);
+
+        let (tx_event, mut rx_event) = mpsc::channel(16);
+        let (tx_err, mut rx_err) = mpsc::channel(16);
+
+        for _ in 0..STALL_UPDATES {
+            fake.state().fan_speeds[0] = 90;
+            let last_update = devices_manager.last_fan_updates[&uuid];
+
+            devices_manager.fan_update(&uuid, &tx_event, &tx_err).await;
+
+            // The default curve is applied and the deadline moved forward
+            assert_eq!(fake.state().fan_speeds[0], 100);
+            assert!(devices_manager.last_fan_updates[&uuid] > last_update);
+        }
+
+        let _ = std::fs::remove_file(&state_file);
+
+        assert!(matches!(
+            rx_event.try_recv(),
+            Ok(DevicesManagerEvent::FanHealthChanged {
+                health: FanHealth::Stalled,
+                ..
+            })
+        ));
+        assert!(matches!(
+            rx_err.try_recv(),
+            Ok(MossdError::DevicesManager(
+                DevicesManagerError::FanFailure { .. }
+            ))
+        ));
+
+        // The stall is reported once and the device is kept
+        assert!(rx_err.try_recv().is_err());
+        assert!(devices_manager.devices.contains_key(&uuid));
     }
 }
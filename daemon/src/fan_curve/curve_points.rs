@@ -0,0 +1,50 @@
+use std::{collections::BTreeMap, ops::Deref};
+
+// Points of a fan curve sorted by temperature, every
+// fan speed is clamped to 100% when it is inserted
+#[derive(Debug, Clone, Default)]
+pub struct CurvePoints {
+    points: BTreeMap<i32, u8>,
+}
+
+impl CurvePoints {
+    pub fn new(points: &[(i32, u8)]) -> CurvePoints {
+        let mut curve_points = Self::default();
+
+        // Add the provided points to the curve
+        for point in points {
+            curve_points.insert(*point);
+        }
+
+        curve_points
+    }
+
+    // Add a point or replace the fan speed of an existing one
+    // Points are always specified as (temp, fan_speed)
+    pub fn insert(&mut self, point: (i32, u8)) {
+        self.points.insert(point.0, point.1.clamp(0, 100));
+    }
+
+    // Remove the point at the given temperature
+    pub fn remove(&mut self, temp: i32) {
+        self.points.remove(&temp);
+    }
+
+    // Return the points sorted by temperature
+    pub fn to_vec(&self) -> Vec<(i32, u8)> {
+        self.points
+            .iter()
+            .map(|(temp, speed)| (*temp, *speed))
+            .collect()
+    }
+}
+
+// Give read access to the points, they can only be
+// modified through the methods clamping the fan speed
+impl Deref for CurvePoints {
+    type Target = BTreeMap<i32, u8>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.points
+    }
+}
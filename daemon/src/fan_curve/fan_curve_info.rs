@@ -1,8 +1,127 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::fan_curve::{
+    FanCurve,
+    filter_curve::{FilterCurve, TempFilter},
+    hysteresis_curve::HysteresisCurve,
+    linear_curve::LinearCurve,
+    monotone_curve::MonotoneCurve,
+    pid_curve::{PidCurve, PidSettings},
+    slew_rate_curve::SlewRateCurve,
+    step_curve::StepCurve,
+    zero_rpm_curve::{ZeroRpmCurve, ZeroRpmSettings},
+};
+
+// Interpolation used between the points of a fan curve
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FanCurveType {
+    #[default]
+    Linear,
+    // The fan speed is held until the next point
+    Step,
+    // Smooth monotone cubic interpolation
+    Monotone,
+    // Controller holding a target temperature, without points
+    Pid,
+}
+
+impl FanCurveType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FanCurveType::Linear => "linear",
+            FanCurveType::Step => "step",
+            FanCurveType::Monotone => "monotone",
+            FanCurveType::Pid => "pid",
+        }
+    }
+}
+
 // Store the data required to create an hysteresis fan curve
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq)]
 pub struct FanCurveInfo {
+    pub curve_type: FanCurveType,
     pub points: Vec<(i32, u8)>,
+    // Only used by the PID curves
+    pub pid: Option<PidSettings>,
 
     pub lower_threshold: Option<u32>,
-    pub upper_threshold: Option<u32>
+    pub upper_threshold: Option<u32>,
+    // Minimum time the fan speed is held after an increase, and
+    // time a decrease must be requested before it is applied
+    pub hold_time: Option<Duration>,
+    pub decrease_delay: Option<Duration>,
+
+    // Maximum fan speed increase and decrease in percent per second
+    pub ramp_up: Option<f32>,
+    pub ramp_down: Option<f32>,
+
+    // Filter applied to the temperature before the curve
+    pub filter: Option<TempFilter>,
+    // Stop the fans at low temperature
+    pub zero_rpm: Option<ZeroRpmSettings>,
+}
+
+impl FanCurveInfo {
+    // Create the hysteresis fan curve described by the info
+    pub fn build_curve(&self) -> Box<dyn FanCurve> {
+        let curve: Box<dyn FanCurve> = match self.curve_type {
+            FanCurveType::Linear => {
+                self.hysteresis(LinearCurve::new(&self.points))
+            }
+            FanCurveType::Step => self.hysteresis(StepCurve::new(&self.points)),
+            FanCurveType::Monotone => {
+                self.hysteresis(MonotoneCurve::new(&self.points))
+            }
+            // The controller must run on every update, holding
+            // its output would break the integral term
+            FanCurveType::Pid => {
+                Box::new(PidCurve::new(self.pid.unwrap_or_default()))
+            }
+        };
+
+        // The fan speed changes are limited after the hysteresis
+        let curve: Box<dyn FanCurve> =
+            if self.ramp_up.is_none() && self.ramp_down.is_none() {
+                curve
+            } else {
+                Box::new(SlewRateCurve::new(
+                    curve,
+                    self.ramp_up,
+                    self.ramp_down,
+                ))
+            };
+
+        // Stopping and restarting the fans isn't slowed down
+        let curve: Box<dyn FanCurve> = match self.zero_rpm {
+            Some(settings) => Box::new(ZeroRpmCurve::new(curve, settings)),
+            None => curve,
+        };
+
+        // The hysteresis compare the filtered temperatures
+        match self.filter {
+            Some(filter) => Box::new(FilterCurve::new(curve, filter)),
+            None => curve,
+        }
+    }
+
+    // Wrap the given curve in the temperature and time hysteresis
+    fn hysteresis<T: FanCurve + Clone + 'static>(
+        &self,
+        curve: T,
+    ) -> Box<dyn FanCurve> {
+        let curve = HysteresisCurve::from_curve(
+            curve,
+            self.lower_threshold.unwrap_or(0),
+            self.upper_threshold.unwrap_or(0),
+        )
+        .with_time_hysteresis(
+            self.hold_time.unwrap_or_default(),
+            self.decrease_delay.unwrap_or_default(),
+        );
+
+        Box::new(curve)
+    }
 }
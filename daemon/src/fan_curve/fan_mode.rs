@@ -1,5 +1,7 @@
+use serde::{Deserialize, Serialize};
+
 // Device fan mode
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum FanMode {
     Auto,
     Curve,
@@ -7,3 +9,25 @@ pub enum FanMode {
     Manual(u8),
 }
 
+// Fans targeted by a fan mode or fan curve assignment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FanSelection {
+    // Every fan on the device
+    All,
+    // A single fan, identified by its index
+    Index(u32),
+}
+
+impl FanSelection {
+    // Return the fan indices of the selection for a device with
+    // the given number of fans, return None if the index is out of range
+    pub fn indices(&self, fan_count: u32) -> Option<Vec<u32>> {
+        match *self {
+            FanSelection::All => Some((0..fan_count).collect()),
+            FanSelection::Index(index) if index < fan_count => {
+                Some(vec![index])
+            }
+            FanSelection::Index(_) => None,
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::fan_curve::{
+    CurveInput, CurveState, FanCurve, fan_curve_info::FanCurveInfo,
+};
+
+// Filter applied to the temperature before it is given to a fan curve
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TempFilter {
+    // Exponential moving average with the given time constant
+    Ema(Duration),
+    // Mean of the temperatures of the given number of updates
+    Mean(usize),
+    // Highest temperature of the given number of updates
+    Max(usize),
+}
+
+// Smooth the temperature given to a fan curve, so short
+// temperature spikes don't change the fan speed
+#[derive(Debug, Clone)]
+pub struct FilterCurve {
+    curve: Box<dyn FanCurve>,
+    filter: TempFilter,
+}
+
+// State of the filter between two updates
+#[derive(Debug, Clone, Default)]
+pub struct FilterState {
+    // Temperatures of the last updates, only used by the windows
+    pub samples: VecDeque<f32>,
+    // Last filtered temperature
+    pub filtered: Option<f32>,
+    pub last_update: Option<Instant>,
+}
+
+impl FilterCurve {
+    pub fn new(curve: Box<dyn FanCurve>, filter: TempFilter) -> FilterCurve {
+        Self { curve, filter }
+    }
+
+    // Add the temperature to the filter and return the filtered one
+    fn filter(
+        &self,
+        state: &mut FilterState,
+        input: &CurveInput,
+        now: Instant,
+    ) -> f32 {
+        let temp = input.temp as f32;
+
+        let dt = input.elapsed(state.last_update, now).as_secs_f32();
+        state.last_update = Some(now);
+
+        let filtered = match self.filter {
+            TempFilter::Ema(time_constant) => match state.filtered {
+                Some(average) if !time_constant.is_zero() => {
+                    let alpha = 1.0 - (-dt / time_constant.as_secs_f32()).exp();
+
+                    average + alpha * (temp - average)
+                }
+                _ => temp,
+            },
+            TempFilter::Mean(window) | TempFilter::Max(window) => {
+                state.samples.push_back(temp);
+                while state.samples.len() > window.max(1) {
+                    state.samples.pop_front();
+                }
+
+                let samples = state.samples.iter().copied();
+
+                match self.filter {
+                    TempFilter::Max(_) => samples.fold(f32::MIN, f32::max),
+                    _ => samples.sum::<f32>() / state.samples.len() as f32,
+                }
+            }
+        };
+
+        state.filtered = Some(filtered);
+
+        filtered
+    }
+}
+
+impl FanCurve for FilterCurve {
+    fn evaluate(
+        &self,
+        state: &mut CurveState,
+        input: &CurveInput,
+        now: Instant,
+    ) -> u8 {
+        let filtered = self.filter(&mut state.filter, input, now);
+
+        let input = CurveInput {
+            temp: filtered.round() as i32,
+            ..*input
+        };
+
+        self.curve.evaluate(state, &input, now)
+    }
+
+    fn add_point(&mut self, point: (i32, u8)) {
+        self.curve.add_point(point);
+    }
+
+    fn update_point(&mut self, point: (i32, u8)) {
+        self.curve.update_point(point);
+    }
+
+    fn remove_point(&mut self, temp: i32) {
+        self.curve.remove_point(temp);
+    }
+
+    fn points_num(&self) -> usize {
+        self.curve.points_num()
+    }
+
+    fn points(&self) -> Vec<(i32, u8)> {
+        self.curve.points()
+    }
+
+    fn to_info(&self) -> FanCurveInfo {
+        FanCurveInfo {
+            filter: Some(self.filter),
+            ..self.curve.to_info()
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn FanCurve> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fan_curve::{
+        linear_curve::LinearCurve, test_utils::curve_speeds_at,
+    };
+
+    #[test]
+    fn temperature_filters_smooth_the_curve_input() {
+        let linear = || Box::new(LinearCurve::new(&[(0, 0), (100, 100)]));
+
+        // Time constant equal to the time between the updates
+        let ema =
+            FilterCurve::new(linear(), TempFilter::Ema(Duration::from_secs(1)));
+        let mut state = CurveState::default();
+
+        let speeds = curve_speeds_at(&ema, &mut state, &[(0, 50), (1, 60)]);
+        assert_eq!(speeds, [50, 56]);
+        assert!((state.filter.filtered.unwrap() - 56.32).abs() < 0.01);
+
+        let max = FilterCurve::new(linear(), TempFilter::Max(2));
+        let mut state = CurveState::default();
+
+        let speeds =
+            curve_speeds_at(&max, &mut state, &[(0, 70), (1, 50), (2, 40)]);
+        assert_eq!(speeds, [70, 70, 50]);
+    }
+}
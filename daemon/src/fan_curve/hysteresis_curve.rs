@@ -1,14 +1,16 @@
-use std::{cell::RefCell, cmp::Ordering};
+use std::{
+    cmp::Ordering,
+    time::{Duration, Instant},
+};
 
-use crate::fan_curve::{fan_curve_info::FanCurveInfo, linear_curve::LinearCurve, FanCurve};
+use crate::fan_curve::{
+    fan_curve_info::FanCurveInfo, CurveInput, CurveState, FanCurve,
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct HysteresisCurve<T: FanCurve> {
     curve: T,
 
-    // Store the temperature and fan speed of the last update
-    last_update: RefCell<Option<(i32, u8)>>,
-
     // Hysteresis lower threshold
     // When the Delta-T is negative and greater than this
     // threshold an update will occur
@@ -18,6 +20,23 @@ pub struct HysteresisCurve<T: FanCurve> {
     // When the Delta-T is positive and greater than this
     // threshold an update will occur
     upper_threshold: u32,
+
+    // Minimum time the fan speed is held after an increase
+    hold_time: Duration,
+    // Time a decrease must keep being requested before it is applied
+    decrease_delay: Duration,
+}
+
+// State of the hysteresis between two updates
+#[derive(Debug, Clone, Default)]
+pub struct HysteresisState {
+    // Store the temperature and fan speed of the last update
+    pub last_update: Option<(i32, u8)>,
+
+    // Instant the fan speed last increased
+    pub increased_at: Option<Instant>,
+    // Instant a decrease was first requested
+    pub decrease_since: Option<Instant>,
 }
 
 impl<T: FanCurve> HysteresisCurve<T> {
@@ -30,56 +49,77 @@ impl<T: FanCurve> HysteresisCurve<T> {
         Self {
             curve,
 
-            last_update: RefCell::new(None),
-
             lower_threshold,
             upper_threshold,
-        }
-    }
 
-    // Create a hysteresis curve using a linear curve as the base
-    pub fn new(
-        points: &[(i32, u8)],
-        lower_threshold: u32,
-        upper_threshold: u32,
-    ) -> HysteresisCurve<LinearCurve> {
-        HysteresisCurve::<LinearCurve> {
-            curve: LinearCurve::new(points),
-            last_update: RefCell::new(None),
-            lower_threshold,
-            upper_threshold,
+            hold_time: Duration::ZERO,
+            decrease_delay: Duration::ZERO,
         }
     }
 
-    // Create a hysteresis curve using a linear curve
-    // as the base from the given info structure
-    pub fn from_info(info: &FanCurveInfo) -> HysteresisCurve<LinearCurve> {
-        HysteresisCurve::<LinearCurve> {
-            curve: LinearCurve::new(&info.points),
-            last_update: RefCell::new(None),
-            lower_threshold: info.lower_threshold.unwrap_or(0),
-            upper_threshold: info.upper_threshold.unwrap_or(0),
-        }
+    // Hold the fan speed for the given time after an increase, and
+    // only decrease it once requested for the given delay
+    pub fn with_time_hysteresis(
+        mut self,
+        hold_time: Duration,
+        decrease_delay: Duration,
+    ) -> HysteresisCurve<T> {
+        self.hold_time = hold_time;
+        self.decrease_delay = decrease_delay;
+
+        self
     }
 
-    fn update(&self, temp: i32) -> u8 {
-        let speed = self.curve.get_speed(temp);
+    fn update(
+        &self,
+        state: &mut CurveState,
+        input: &CurveInput,
+        now: Instant,
+    ) -> u8 {
+        let speed = self.curve.evaluate(state, input, now);
+        let state = &mut state.hysteresis;
+
+        if let Some((_, last_speed)) = state.last_update {
+            // Keep the last fan speed until the hold time and
+            // the decrease delay have elapsed
+            if speed < last_speed {
+                let decrease_since = *state.decrease_since.get_or_insert(now);
+
+                let held = state.increased_at.is_some_and(|increased_at| {
+                    now.saturating_duration_since(increased_at)
+                        < self.hold_time
+                });
+                let delayed = now.saturating_duration_since(decrease_since)
+                    < self.decrease_delay;
+
+                if held || delayed {
+                    return last_speed;
+                }
+            }
 
-        let mut last_update = self.last_update.borrow_mut();
-        *last_update = Some((temp, speed));
+            if speed > last_speed {
+                state.increased_at = Some(now);
+            }
+        }
+
+        state.decrease_since = None;
+        state.last_update = Some((input.temp, speed));
 
         speed
     }
 }
 
-impl<T: FanCurve> FanCurve for HysteresisCurve<T> {
-    fn get_speed(&self, temp: i32) -> u8 {
+impl<T: FanCurve + Clone + 'static> FanCurve for HysteresisCurve<T> {
+    fn evaluate(
+        &self,
+        state: &mut CurveState,
+        input: &CurveInput,
+        now: Instant,
+    ) -> u8 {
         // If last update is None update immediately and return the result
-        let last_update = self.last_update.borrow().clone();
-
-        if let Some(last) = last_update {
+        if let Some(last) = state.hysteresis.last_update {
             let last_temp = last.0;
-            let delta: i32 = (temp as i32) - (last_temp as i32);
+            let delta: i32 = input.temp - last_temp;
 
             let threshold = match delta.cmp(&0) {
                 Ordering::Less => self.lower_threshold,
@@ -89,13 +129,15 @@ impl<T: FanCurve> FanCurve for HysteresisCurve<T> {
 
             // If the Delta-T is greater or equal to the
             // threshold trigger an update
-            if (delta.abs() as u32) >= threshold {
-                self.update(temp)
+            if delta.unsigned_abs() >= threshold {
+                self.update(state, input, now)
             } else {
+                // The decrease is no longer requested
+                state.hysteresis.decrease_since = None;
                 last.1
             }
         } else {
-            self.update(temp)
+            self.update(state, input, now)
         }
     }
 
@@ -104,7 +146,7 @@ impl<T: FanCurve> FanCurve for HysteresisCurve<T> {
     }
 
     fn update_point(&mut self, point: (i32, u8)) {
-        self.curve.add_point(point);
+        self.curve.update_point(point);
     }
 
     fn remove_point(&mut self, temp: i32) {
@@ -114,4 +156,69 @@ impl<T: FanCurve> FanCurve for HysteresisCurve<T> {
     fn points_num(&self) -> usize {
         self.curve.points_num()
     }
+
+    fn points(&self) -> Vec<(i32, u8)> {
+        self.curve.points()
+    }
+
+    fn to_info(&self) -> FanCurveInfo {
+        // Unset parameters are built as zero
+        let threshold = |threshold: u32| (threshold > 0).then_some(threshold);
+        let time = |time: Duration| (!time.is_zero()).then_some(time);
+
+        FanCurveInfo {
+            lower_threshold: threshold(self.lower_threshold),
+            upper_threshold: threshold(self.upper_threshold),
+            hold_time: time(self.hold_time),
+            decrease_delay: time(self.decrease_delay),
+            ..self.curve.to_info()
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn FanCurve> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fan_curve::test_utils::curve_speeds_at;
+
+    #[test]
+    fn fan_speed_is_held_after_an_increase() {
+        let info = FanCurveInfo {
+            points: vec![(40, 20), (80, 60)],
+            hold_time: Some(Duration::from_secs(3)),
+            ..Default::default()
+        };
+        let curve = info.build_curve();
+        let mut state = CurveState::default();
+
+        let speeds = curve_speeds_at(
+            curve.as_ref(),
+            &mut state,
+            &[(0, 60), (1, 70), (2, 50), (3, 50), (4, 50)],
+        );
+        assert_eq!(speeds, [40, 50, 50, 50, 30]);
+    }
+
+    #[test]
+    fn fan_speed_decrease_must_persist_for_the_delay() {
+        let info = FanCurveInfo {
+            points: vec![(40, 20), (80, 60)],
+            decrease_delay: Some(Duration::from_secs(2)),
+            ..Default::default()
+        };
+        let curve = info.build_curve();
+        let mut state = CurveState::default();
+
+        // A short dip doesn't change the fan speed
+        let speeds = curve_speeds_at(
+            curve.as_ref(),
+            &mut state,
+            &[(0, 70), (1, 50), (2, 70), (3, 50), (4, 50), (5, 50)],
+        );
+        assert_eq!(speeds, [50, 50, 50, 50, 50, 30]);
+    }
 }
@@ -1,29 +1,25 @@
-use std::collections::BTreeMap;
+use std::time::Instant;
 
-use crate::fan_curve::FanCurve;
+use crate::fan_curve::{
+    CurveInput, CurveState, FanCurve,
+    curve_points::CurvePoints,
+    fan_curve_info::{FanCurveInfo, FanCurveType},
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct LinearCurve {
-    points: BTreeMap<i32, u8>,
+    points: CurvePoints,
 }
 
 impl LinearCurve {
     pub fn new(points: &[(i32, u8)]) -> LinearCurve {
-        let mut curve = Self {
-            points: BTreeMap::new(),
-        };
-
-        // Add the provided points to the curve
-        for p in points {
-            curve.add_point(p.clone());
+        Self {
+            points: CurvePoints::new(points),
         }
-
-        curve
     }
-}
 
-impl FanCurve for LinearCurve {
-    fn get_speed(&self, temp: i32) -> u8 {
+    // Return the fan speed at the given temperature
+    pub fn get_speed(&self, temp: i32) -> u8 {
         // Check if temperature is the map, in that case return
         // the corresponding fan speed
         if let Some(speed) = self.points.get(&temp) {
@@ -54,23 +50,49 @@ impl FanCurve for LinearCurve {
         // If no element was in the map return 100 for safety
         100
     }
+}
 
+impl FanCurve for LinearCurve {
     fn add_point(&mut self, point: (i32, u8)) {
-        // Clamp the fan speed
-        self.points.insert(point.0, point.1.clamp(0, 100));
+        self.points.insert(point);
     }
 
     fn update_point(&mut self, point: (i32, u8)) {
-        self.points.insert(point.0, point.1.clamp(0, 100));
+        self.points.insert(point);
     }
 
     fn remove_point(&mut self, temp: i32) {
-        self.points.remove(&temp);
+        self.points.remove(temp);
     }
 
     fn points_num(&self) -> usize {
         self.points.len()
     }
+
+    fn points(&self) -> Vec<(i32, u8)> {
+        self.points.to_vec()
+    }
+
+    fn evaluate(
+        &self,
+        _state: &mut CurveState,
+        input: &CurveInput,
+        _now: Instant,
+    ) -> u8 {
+        self.get_speed(input.temp)
+    }
+
+    fn to_info(&self) -> FanCurveInfo {
+        FanCurveInfo {
+            curve_type: FanCurveType::Linear,
+            points: self.points(),
+            ..Default::default()
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn FanCurve> {
+        Box::new(self.clone())
+    }
 }
 
 // Perform the linear interpolation between 
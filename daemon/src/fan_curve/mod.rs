@@ -1,11 +1,30 @@
-use std::fmt::Debug;
+use std::{
+    fmt::Debug,
+    time::{Duration, Instant},
+};
+
+use crate::fan_curve::{
+    fan_curve_info::FanCurveInfo, filter_curve::FilterState,
+    hysteresis_curve::HysteresisState, pid_curve::PidState,
+    slew_rate_curve::SlewRateState, zero_rpm_curve::ZeroRpmState,
+};
 
 pub mod fan_mode;
+pub mod curve_points;
 pub mod linear_curve;
+pub mod step_curve;
+pub mod monotone_curve;
+pub mod pid_curve;
+pub mod slew_rate_curve;
+pub mod filter_curve;
+pub mod zero_rpm_curve;
 pub mod hysteresis_curve;
 pub mod fan_curve_info;
 
-pub trait FanCurve: Debug {
+// Fan curves only describe how the fan speed is computed, the state
+// of the controlled fans is kept by the devices and given to every
+// evaluation, a curve can then be shared and cloned freely
+pub trait FanCurve: Debug + Send + Sync {
     // Add a point to the fan curve
     // Points are always specified as (temp, fan_speed)
     fn add_point(&mut self, point: (i32, u8));
@@ -18,8 +37,97 @@ pub trait FanCurve: Debug {
 
     // Return the number of points in the curve
     fn points_num(&self) -> usize;
+    // Return the points of the curve sorted by temperature
+    fn points(&self) -> Vec<(i32, u8)>;
+
+    // Return the fan speed for the given input at the given
+    // instant and update the state of the controlled fan
+    fn evaluate(
+        &self,
+        state: &mut CurveState,
+        input: &CurveInput,
+        now: Instant,
+    ) -> u8;
+
+    // Return the info describing the curve, building
+    // the info gives back an identical curve
+    fn to_info(&self) -> FanCurveInfo;
+
+    // Return a boxed copy of the curve
+    fn clone_box(&self) -> Box<dyn FanCurve>;
+}
+
+impl Clone for Box<dyn FanCurve> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+// Input of a fan curve evaluation
+#[derive(Debug, Clone, Copy)]
+pub struct CurveInput {
+    // Temperature read from the sensor
+    pub temp: i32,
+    // Lowest fan speed accepted by the device,
+    // the fans can only be stopped if it is zero
+    pub min_speed: u8,
+    // Time between two fan updates, used as the time
+    // elapsed before the first evaluation
+    pub update_interval: Duration,
+}
+
+impl CurveInput {
+    // Return the time elapsed since the given evaluation instant
+    pub fn elapsed(
+        &self,
+        last_update: Option<Instant>,
+        now: Instant,
+    ) -> Duration {
+        match last_update {
+            Some(last_update) => now.saturating_duration_since(last_update),
+            None => self.update_interval,
+        }
+    }
+}
 
-    // Return the fan speed for the given temperature
-    fn get_speed(&self, temp: i32) -> u8;
+// State of a fan controlled by a fan curve between two evaluations,
+// every curve wrapping another one keeps its own part of the state
+#[derive(Debug, Clone, Default)]
+pub struct CurveState {
+    pub hysteresis: HysteresisState,
+    pub slew_rate: SlewRateState,
+    pub filter: FilterState,
+    pub zero_rpm: ZeroRpmState,
+    pub pid: PidState,
 }
 
+#[cfg(test)]
+mod test_utils {
+    use std::time::{Duration, Instant};
+
+    use crate::fan_curve::{CurveInput, CurveState, FanCurve};
+
+    // Evaluate the curve at the given (seconds, temperature) updates with
+    // a one second update interval and return the fan speed of each update
+    pub fn curve_speeds_at(
+        curve: &dyn FanCurve,
+        state: &mut CurveState,
+        updates: &[(u64, i32)],
+    ) -> Vec<u8> {
+        let start = Instant::now();
+
+        updates
+            .iter()
+            .map(|(secs, temp)| {
+                let input = CurveInput {
+                    temp: *temp,
+                    min_speed: 0,
+                    update_interval: Duration::from_secs(1),
+                };
+                let now = start + Duration::from_secs(*secs);
+
+                curve.evaluate(state, &input, now)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,216 @@
+use std::time::Instant;
+
+use crate::fan_curve::{
+    CurveInput, CurveState, FanCurve,
+    curve_points::CurvePoints,
+    fan_curve_info::{FanCurveInfo, FanCurveType},
+};
+
+// Smooth fan curve using a monotone cubic (PCHIP) interpolation,
+// the fan speed never overshoot the points of an interval and
+// never decrease when the fan speeds of the points don't
+#[derive(Debug, Clone)]
+pub struct MonotoneCurve {
+    points: CurvePoints,
+}
+
+impl MonotoneCurve {
+    pub fn new(points: &[(i32, u8)]) -> MonotoneCurve {
+        Self {
+            points: CurvePoints::new(points),
+        }
+    }
+
+    // Return the fan speed at the given temperature
+    pub fn get_speed(&self, temp: i32) -> u8 {
+        let points: Vec<(f32, f32)> = self
+            .points
+            .iter()
+            .map(|(temp, speed)| (*temp as f32, *speed as f32))
+            .collect();
+
+        // Outside of the curve the closest point fan speed is used
+        let (Some(first), Some(last)) = (points.first(), points.last()) else {
+            // If no element was in the map return 100 for safety
+            return 100;
+        };
+
+        let t = temp as f32;
+
+        if t <= first.0 {
+            return first.1 as u8;
+        }
+        if t >= last.0 {
+            return last.1 as u8;
+        }
+
+        // Index of the interval containing the temperature
+        let k = points.partition_point(|(x, _)| *x <= t) - 1;
+        let slopes = pchip_slopes(&points);
+
+        let (x0, y0) = points[k];
+        let (x1, y1) = points[k + 1];
+        let h = x1 - x0;
+        let s = (t - x0) / h;
+
+        // Cubic Hermite basis functions
+        let h00 = (1.0 + 2.0 * s) * (1.0 - s) * (1.0 - s);
+        let h10 = s * (1.0 - s) * (1.0 - s);
+        let h01 = s * s * (3.0 - 2.0 * s);
+        let h11 = s * s * (s - 1.0);
+
+        let speed =
+            h00 * y0 + h10 * h * slopes[k] + h01 * y1 + h11 * h * slopes[k + 1];
+
+        // Guard against rounding errors overshooting the interval
+        speed.clamp(y0.min(y1), y0.max(y1)).round() as u8
+    }
+}
+
+impl FanCurve for MonotoneCurve {
+    fn add_point(&mut self, point: (i32, u8)) {
+        self.points.insert(point);
+    }
+
+    fn update_point(&mut self, point: (i32, u8)) {
+        self.points.insert(point);
+    }
+
+    fn remove_point(&mut self, temp: i32) {
+        self.points.remove(temp);
+    }
+
+    fn points_num(&self) -> usize {
+        self.points.len()
+    }
+
+    fn points(&self) -> Vec<(i32, u8)> {
+        self.points.to_vec()
+    }
+
+    fn evaluate(
+        &self,
+        _state: &mut CurveState,
+        input: &CurveInput,
+        _now: Instant,
+    ) -> u8 {
+        self.get_speed(input.temp)
+    }
+
+    fn to_info(&self) -> FanCurveInfo {
+        FanCurveInfo {
+            curve_type: FanCurveType::Monotone,
+            points: self.points(),
+            ..Default::default()
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn FanCurve> {
+        Box::new(self.clone())
+    }
+}
+
+// Compute the tangent of the curve at every point with the
+// Fritsch-Carlson method, the tangent is zero at local extrema
+fn pchip_slopes(points: &[(f32, f32)]) -> Vec<f32> {
+    let n = points.len();
+
+    // Width and slope of every interval
+    let h: Vec<f32> = points.windows(2).map(|p| p[1].0 - p[0].0).collect();
+    let delta: Vec<f32> = points
+        .windows(2)
+        .zip(h.iter())
+        .map(|(p, h)| (p[1].1 - p[0].1) / h)
+        .collect();
+
+    let mut slopes = vec![0.0; n];
+
+    // Two points are joined by a straight line
+    if n == 2 {
+        slopes[0] = delta[0];
+        slopes[1] = delta[0];
+        return slopes;
+    }
+
+    // Weighted harmonic mean of the adjacent interval slopes
+    for k in 1..n - 1 {
+        let (d0, d1) = (delta[k - 1], delta[k]);
+
+        if d0 == 0.0 || d1 == 0.0 || d0.signum() != d1.signum() {
+            continue;
+        }
+
+        let w0 = 2.0 * h[k] + h[k - 1];
+        let w1 = h[k] + 2.0 * h[k - 1];
+
+        slopes[k] = (w0 + w1) / (w0 / d0 + w1 / d1);
+    }
+
+    slopes[0] = end_slope(h[0], h[1], delta[0], delta[1]);
+    slopes[n - 1] = end_slope(h[n - 2], h[n - 3], delta[n - 2], delta[n - 3]);
+
+    slopes
+}
+
+// Compute the tangent at an end point with a three points
+// estimate, limited to keep the curve shape preserving
+fn end_slope(h0: f32, h1: f32, d0: f32, d1: f32) -> f32 {
+    let slope = ((2.0 * h0 + h1) * d0 - h0 * d1) / (h0 + h1);
+
+    if slope.signum() != d0.signum() || d0 == 0.0 {
+        0.0
+    } else if d0.signum() != d1.signum() && slope.abs() > (3.0 * d0).abs() {
+        3.0 * d0
+    } else {
+        slope
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fan_curve::test_utils::curve_speeds_at;
+
+    #[test]
+    fn monotone_curve_never_overshoots_or_decreases() {
+        let points = [(30, 20), (50, 60), (70, 60), (80, 65), (90, 100)];
+        let curve = MonotoneCurve::new(&points);
+
+        // The curve goes through every point
+        for (temp, speed) in points {
+            assert_eq!(curve.get_speed(temp), speed);
+        }
+
+        let mut last_speed = 0;
+        for temp in 20..=100 {
+            let speed = curve.get_speed(temp);
+            assert!(speed >= last_speed, "speed decreased at {temp}");
+
+            last_speed = speed;
+        }
+
+        // Flat between points with the same fan speed
+        for temp in 50..=70 {
+            assert_eq!(curve.get_speed(temp), 60);
+        }
+    }
+
+    #[test]
+    fn monotone_curve_type_is_wrapped_by_the_hysteresis() {
+        let info = FanCurveInfo {
+            curve_type: FanCurveType::Monotone,
+            points: vec![(40, 20), (80, 60)],
+            lower_threshold: Some(5),
+            upper_threshold: Some(0),
+            ..Default::default()
+        };
+        let curve = info.build_curve();
+        let mut state = CurveState::default();
+
+        // Two points are joined by a straight line, the drop
+        // in temperature is within the hysteresis
+        let speeds =
+            curve_speeds_at(curve.as_ref(), &mut state, &[(0, 60), (1, 57)]);
+        assert_eq!(speeds, [40, 40]);
+    }
+}
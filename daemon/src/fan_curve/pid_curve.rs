@@ -0,0 +1,213 @@
+use std::time::Instant;
+
+use crate::fan_curve::{
+    CurveInput, CurveState, FanCurve,
+    fan_curve_info::{FanCurveInfo, FanCurveType},
+};
+
+// Settings of a PID fan controller
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PidSettings {
+    // Temperature the controller try to hold the GPU at
+    pub target: i32,
+
+    // Proportional, integral and derivative gains, in
+    // percent of fan speed per degree of temperature error
+    pub kp: f32,
+    pub ki: f32,
+    pub kd: f32,
+
+    // Fan speed range of the controller in percent
+    pub min_duty: u8,
+    pub max_duty: u8,
+    // Maximum fan speed change in percent per second
+    pub max_rate: Option<f32>,
+}
+
+impl Default for PidSettings {
+    fn default() -> Self {
+        Self {
+            target: 70,
+
+            kp: 2.0,
+            ki: 0.1,
+            kd: 0.0,
+
+            min_duty: 0,
+            max_duty: 100,
+            max_rate: None,
+        }
+    }
+}
+
+// State of the controller between two updates
+#[derive(Debug, Clone, Default)]
+pub struct PidState {
+    pub integral: f32,
+    pub last_error: Option<f32>,
+    pub last_speed: Option<f32>,
+    pub last_update: Option<Instant>,
+}
+
+// Fan controller holding the GPU at the target temperature with
+// the lowest fan speed possible
+#[derive(Debug, Clone)]
+pub struct PidCurve {
+    settings: PidSettings,
+}
+
+impl PidCurve {
+    pub fn new(settings: PidSettings) -> PidCurve {
+        Self { settings }
+    }
+}
+
+impl FanCurve for PidCurve {
+    fn evaluate(
+        &self,
+        state: &mut CurveState,
+        input: &CurveInput,
+        now: Instant,
+    ) -> u8 {
+        let settings = &self.settings;
+        let state = &mut state.pid;
+        let temp = input.temp;
+
+        let dt = input.elapsed(state.last_update, now).as_secs_f32();
+        state.last_update = Some(now);
+        let min = settings.min_duty.min(100) as f32;
+        let max = settings.max_duty.clamp(settings.min_duty, 100) as f32;
+
+        // A positive error means the GPU is too hot
+        let error = (temp - settings.target) as f32;
+
+        let derivative = match state.last_error {
+            Some(last_error) if dt > 0.0 => (error - last_error) / dt,
+            _ => 0.0,
+        };
+        state.last_error = Some(error);
+
+        let output = |integral: f32| {
+            settings.kp * error
+                + settings.ki * integral
+                + settings.kd * derivative
+        };
+
+        // Anti-windup, the error is only integrated while it
+        // doesn't push the fan speed further out of its range
+        let integral = state.integral + error * dt;
+        let saturated = (output(integral) > max && error > 0.0)
+            || (output(integral) < min && error < 0.0);
+
+        if !saturated {
+            state.integral = integral;
+        }
+
+        let mut speed = output(state.integral).clamp(min, max);
+
+        // Limit the fan speed change since the last update
+        if let (Some(max_rate), Some(last_speed)) =
+            (settings.max_rate, state.last_speed)
+        {
+            let max_change = max_rate.abs() * dt;
+            speed =
+                speed.clamp(last_speed - max_change, last_speed + max_change);
+        }
+
+        state.last_speed = Some(speed);
+
+        speed.round() as u8
+    }
+
+    // The controller doesn't use points
+    fn add_point(&mut self, _point: (i32, u8)) {}
+
+    fn update_point(&mut self, _point: (i32, u8)) {}
+
+    fn remove_point(&mut self, _temp: i32) {}
+
+    fn points_num(&self) -> usize {
+        0
+    }
+
+    fn points(&self) -> Vec<(i32, u8)> {
+        Vec::new()
+    }
+
+    fn to_info(&self) -> FanCurveInfo {
+        FanCurveInfo {
+            curve_type: FanCurveType::Pid,
+            pid: Some(self.settings),
+            ..Default::default()
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn FanCurve> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fan_curve::test_utils::curve_speeds_at;
+
+    #[test]
+    fn pid_curve_integrates_over_the_elapsed_time() {
+        let curve = PidCurve::new(PidSettings {
+            target: 70,
+            kp: 0.0,
+            ki: 1.0,
+            kd: 0.0,
+            ..Default::default()
+        });
+        let mut state = CurveState::default();
+
+        // The first update integrates over the one second update interval
+        let speeds =
+            curve_speeds_at(&curve, &mut state, &[(0, 80), (2, 80), (3, 80)]);
+        assert_eq!(speeds, [10, 30, 40]);
+    }
+
+    #[test]
+    fn pid_curve_integral_does_not_wind_up() {
+        let curve = PidCurve::new(PidSettings {
+            target: 70,
+            kp: 5.0,
+            ki: 1.0,
+            kd: 0.0,
+            min_duty: 20,
+            max_duty: 80,
+            max_rate: None,
+        });
+        let mut state = CurveState::default();
+
+        // Saturated at the maximum duty for a long time
+        let saturated: Vec<(u64, i32)> = (0..100).map(|t| (t, 90)).collect();
+        let speeds = curve_speeds_at(&curve, &mut state, &saturated);
+        assert!(speeds.iter().all(|speed| *speed == 80));
+
+        // The speed drops as soon as the GPU is below the target
+        let speeds = curve_speeds_at(&curve, &mut state, &[(100, 65)]);
+        assert!(speeds[0] < 80);
+    }
+
+    #[test]
+    fn pid_curve_output_rate_is_limited() {
+        let curve = PidCurve::new(PidSettings {
+            target: 70,
+            kp: 10.0,
+            ki: 0.0,
+            kd: 0.0,
+            min_duty: 0,
+            max_duty: 100,
+            max_rate: Some(5.0),
+        });
+        let mut state = CurveState::default();
+
+        // At most 5% per second for two seconds
+        let speeds =
+            curve_speeds_at(&curve, &mut state, &[(0, 72), (2, 80), (4, 70)]);
+        assert_eq!(speeds, [20, 30, 20]);
+    }
+}
@@ -0,0 +1,132 @@
+use std::time::Instant;
+
+use crate::fan_curve::{
+    CurveInput, CurveState, FanCurve, fan_curve_info::FanCurveInfo,
+};
+
+// Limit how fast the fan speed given by a curve can change, the
+// increases and decreases have their own maximum rate so the fans
+// can ramp up quickly and slow down gradually
+#[derive(Debug, Clone)]
+pub struct SlewRateCurve {
+    curve: Box<dyn FanCurve>,
+
+    // Maximum fan speed change in percent per second,
+    // None to let the fan speed change immediately
+    ramp_up: Option<f32>,
+    ramp_down: Option<f32>,
+}
+
+// State of the rate limit between two updates
+#[derive(Debug, Clone, Default)]
+pub struct SlewRateState {
+    // Fan speed of the last update, not rounded so
+    // slow rates still add up over several updates
+    pub last_speed: Option<f32>,
+    pub last_update: Option<Instant>,
+}
+
+impl SlewRateCurve {
+    pub fn new(
+        curve: Box<dyn FanCurve>,
+        ramp_up: Option<f32>,
+        ramp_down: Option<f32>,
+    ) -> SlewRateCurve {
+        Self {
+            curve,
+
+            ramp_up,
+            ramp_down,
+        }
+    }
+}
+
+impl FanCurve for SlewRateCurve {
+    fn evaluate(
+        &self,
+        state: &mut CurveState,
+        input: &CurveInput,
+        now: Instant,
+    ) -> u8 {
+        let target = self.curve.evaluate(state, input, now) as f32;
+
+        let state = &mut state.slew_rate;
+        let dt = input.elapsed(state.last_update, now).as_secs_f32();
+        state.last_update = Some(now);
+
+        // The first update isn't limited
+        let speed = match state.last_speed {
+            Some(last) => {
+                let max_up = self.ramp_up.map_or(f32::MAX, |rate| rate * dt);
+                let max_down =
+                    self.ramp_down.map_or(f32::MAX, |rate| rate * dt);
+
+                target.clamp(last - max_down, last + max_up)
+            }
+            None => target,
+        };
+
+        state.last_speed = Some(speed);
+
+        speed.round() as u8
+    }
+
+    fn add_point(&mut self, point: (i32, u8)) {
+        self.curve.add_point(point);
+    }
+
+    fn update_point(&mut self, point: (i32, u8)) {
+        self.curve.update_point(point);
+    }
+
+    fn remove_point(&mut self, temp: i32) {
+        self.curve.remove_point(temp);
+    }
+
+    fn points_num(&self) -> usize {
+        self.curve.points_num()
+    }
+
+    fn points(&self) -> Vec<(i32, u8)> {
+        self.curve.points()
+    }
+
+    fn to_info(&self) -> FanCurveInfo {
+        FanCurveInfo {
+            ramp_up: self.ramp_up,
+            ramp_down: self.ramp_down,
+            ..self.curve.to_info()
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn FanCurve> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fan_curve::test_utils::curve_speeds_at;
+
+    #[test]
+    fn fan_speed_changes_are_limited_by_the_ramp_rates() {
+        let info = FanCurveInfo {
+            points: vec![(40, 20), (80, 100)],
+            ramp_up: Some(10.0),
+            ramp_down: Some(2.0),
+            ..Default::default()
+        };
+        let curve = info.build_curve();
+        let mut state = CurveState::default();
+
+        // Up to 10% per second towards 100%,
+        // then only 2% per second back down
+        let speeds = curve_speeds_at(
+            curve.as_ref(),
+            &mut state,
+            &[(0, 40), (2, 80), (4, 80), (6, 40)],
+        );
+        assert_eq!(speeds, [20, 40, 60, 56]);
+    }
+}
@@ -0,0 +1,122 @@
+use std::time::Instant;
+
+use crate::fan_curve::{
+    CurveInput, CurveState, FanCurve,
+    curve_points::CurvePoints,
+    fan_curve_info::{FanCurveInfo, FanCurveType},
+};
+
+// Fan curve without interpolation, the fan speed of a point
+// is held until the temperature reach the next point
+#[derive(Debug, Clone)]
+pub struct StepCurve {
+    points: CurvePoints,
+}
+
+impl StepCurve {
+    pub fn new(points: &[(i32, u8)]) -> StepCurve {
+        Self {
+            points: CurvePoints::new(points),
+        }
+    }
+
+    // Return the fan speed at the given temperature
+    pub fn get_speed(&self, temp: i32) -> u8 {
+        // Use the last point at or below the temperature,
+        // below the first point its fan speed is used
+        if let Some((_, speed)) = self.points.range(..=temp).next_back() {
+            return *speed;
+        }
+
+        if let Some((_, speed)) = self.points.first_key_value() {
+            return *speed;
+        }
+
+        // If no element was in the map return 100 for safety
+        100
+    }
+}
+
+impl FanCurve for StepCurve {
+    fn add_point(&mut self, point: (i32, u8)) {
+        self.points.insert(point);
+    }
+
+    fn update_point(&mut self, point: (i32, u8)) {
+        self.points.insert(point);
+    }
+
+    fn remove_point(&mut self, temp: i32) {
+        self.points.remove(temp);
+    }
+
+    fn points_num(&self) -> usize {
+        self.points.len()
+    }
+
+    fn points(&self) -> Vec<(i32, u8)> {
+        self.points.to_vec()
+    }
+
+    fn evaluate(
+        &self,
+        _state: &mut CurveState,
+        input: &CurveInput,
+        _now: Instant,
+    ) -> u8 {
+        self.get_speed(input.temp)
+    }
+
+    fn to_info(&self) -> FanCurveInfo {
+        FanCurveInfo {
+            curve_type: FanCurveType::Step,
+            points: self.points(),
+            ..Default::default()
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn FanCurve> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fan_curve::test_utils::curve_speeds_at;
+
+    #[test]
+    fn speed_is_held_until_the_next_point() {
+        let curve = StepCurve::new(&[(40, 20), (60, 50), (80, 100)]);
+
+        assert_eq!(curve.get_speed(30), 20);
+        assert_eq!(curve.get_speed(40), 20);
+        assert_eq!(curve.get_speed(59), 20);
+        assert_eq!(curve.get_speed(60), 50);
+        assert_eq!(curve.get_speed(79), 50);
+        assert_eq!(curve.get_speed(90), 100);
+    }
+
+    #[test]
+    fn empty_curve_runs_the_fans_at_full_speed() {
+        assert_eq!(StepCurve::new(&[]).get_speed(50), 100);
+    }
+
+    #[test]
+    fn step_curve_type_is_wrapped_by_the_hysteresis() {
+        let info = FanCurveInfo {
+            curve_type: FanCurveType::Step,
+            points: vec![(40, 20), (60, 50), (80, 100)],
+            lower_threshold: Some(10),
+            upper_threshold: Some(0),
+            ..Default::default()
+        };
+        let curve = info.build_curve();
+        let mut state = CurveState::default();
+
+        // The drop below the step is within the hysteresis
+        let speeds =
+            curve_speeds_at(curve.as_ref(), &mut state, &[(0, 65), (1, 58)]);
+        assert_eq!(speeds, [50, 50]);
+    }
+}
@@ -0,0 +1,182 @@
+use std::time::{Duration, Instant};
+
+use tracing::warn;
+
+use crate::fan_curve::{
+    CurveInput, CurveState, FanCurve, fan_curve_info::FanCurveInfo,
+};
+
+// Settings of the zero RPM mode of a fan curve
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZeroRpmSettings {
+    // The fans stop below the stop temperature and
+    // only restart above the start temperature
+    pub stop_temp: i32,
+    pub start_temp: i32,
+
+    // Fan speed used for the given time when the fans restart,
+    // to overcome the static friction of the stopped fans
+    pub kick_duty: u8,
+    pub kick_time: Duration,
+
+    // Lowest fan speed used while the fans run
+    pub min_duty: u8,
+}
+
+// State of the fans between two updates
+#[derive(Debug, Clone, Default)]
+pub struct ZeroRpmState {
+    // None before the first update
+    pub stopped: Option<bool>,
+    // End of the kick-start
+    pub kick_until: Option<Instant>,
+    // The device minimum speed was already reported
+    pub min_speed_warned: bool,
+}
+
+// Stop the fans while the temperature is low, the fans given by
+// the wrapped curve are only used while the fans are running
+// The fans are never stopped below the device minimum speed
+#[derive(Debug, Clone)]
+pub struct ZeroRpmCurve {
+    curve: Box<dyn FanCurve>,
+    settings: ZeroRpmSettings,
+}
+
+impl ZeroRpmCurve {
+    pub fn new(
+        curve: Box<dyn FanCurve>,
+        settings: ZeroRpmSettings,
+    ) -> ZeroRpmCurve {
+        Self { curve, settings }
+    }
+}
+
+impl FanCurve for ZeroRpmCurve {
+    fn evaluate(
+        &self,
+        state: &mut CurveState,
+        input: &CurveInput,
+        now: Instant,
+    ) -> u8 {
+        // The wrapped curve keeps running so its
+        // state is up to date when the fans restart
+        let speed = self.curve.evaluate(state, input, now);
+
+        let settings = &self.settings;
+        let state = &mut state.zero_rpm;
+        let temp = input.temp;
+
+        let stopped = match state.stopped {
+            Some(true) if temp >= settings.start_temp => {
+                state.kick_until = Some(now + settings.kick_time);
+                false
+            }
+            Some(false) => temp < settings.stop_temp,
+            Some(stopped) => stopped,
+            // Only stop fans that may already be running
+            // once the temperature is low enough
+            None => temp < settings.stop_temp,
+        };
+        state.stopped = Some(stopped);
+
+        if stopped {
+            if input.min_speed > 0 && !state.min_speed_warned {
+                warn!(
+                    "The device doesn't accept a 0% fan speed, \
+                    the fans are kept at {}% instead of stopping",
+                    input.min_speed
+                );
+                state.min_speed_warned = true;
+            }
+
+            return input.min_speed;
+        }
+
+        let mut speed =
+            speed.max(settings.min_duty).max(input.min_speed).min(100);
+
+        if state.kick_until.is_some_and(|kick_until| now < kick_until) {
+            speed = speed.max(settings.kick_duty.min(100));
+        }
+
+        speed
+    }
+
+    fn add_point(&mut self, point: (i32, u8)) {
+        self.curve.add_point(point);
+    }
+
+    fn update_point(&mut self, point: (i32, u8)) {
+        self.curve.update_point(point);
+    }
+
+    fn remove_point(&mut self, temp: i32) {
+        self.curve.remove_point(temp);
+    }
+
+    fn points_num(&self) -> usize {
+        self.curve.points_num()
+    }
+
+    fn points(&self) -> Vec<(i32, u8)> {
+        self.curve.points()
+    }
+
+    fn to_info(&self) -> FanCurveInfo {
+        FanCurveInfo {
+            zero_rpm: Some(self.settings),
+            ..self.curve.to_info()
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn FanCurve> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fan_curve::test_utils::curve_speeds_at;
+
+    // Zero RPM curve stopping the fans below 45°C and restarting them
+    // above 55°C, with a two seconds kick-start at 60%
+    fn zero_rpm_curve_info() -> FanCurveInfo {
+        FanCurveInfo {
+            points: vec![(40, 20), (80, 60)],
+            zero_rpm: Some(ZeroRpmSettings {
+                stop_temp: 45,
+                start_temp: 55,
+                kick_duty: 60,
+                kick_time: Duration::from_secs(2),
+                min_duty: 30,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn zero_rpm_stops_and_kick_starts_the_fans() {
+        let curve = zero_rpm_curve_info().build_curve();
+        let mut state = CurveState::default();
+
+        // Stopped until the start threshold, kick-start for two
+        // seconds, then the fan curve at the minimum duty until
+        // the temperature is below the stop threshold
+        let speeds = curve_speeds_at(
+            curve.as_ref(),
+            &mut state,
+            &[
+                (0, 40),
+                (1, 50),
+                (2, 56),
+                (3, 56),
+                (4, 56),
+                (5, 46),
+                (6, 44),
+            ],
+        );
+        assert_eq!(speeds, [0, 0, 60, 60, 36, 30, 0]);
+    }
+}
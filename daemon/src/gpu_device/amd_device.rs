@@ -0,0 +1,913 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use tracing::{debug, warn};
+
+use crate::{
+    fan_curve::{
+        CurveState, FanCurve,
+        fan_curve_info::FanCurveInfo,
+        fan_mode::{FanMode, FanSelection},
+        linear_curve::LinearCurve,
+    },
+    gpu_device::{
+        DEFAULT_DATA_UPDATE_INTERVAL, DEFAULT_FAN_UPDATE_INTERVAL, DeviceError,
+        GpuDevice, GpuVendor, Result, check_sensor_fault, check_unsupported,
+        clamp_fan_speed, curve_input,
+        gpu_config::{AmdConfig, AmdStockClocks, GpuConfig, OriginalSettings},
+        gpu_data::{FanData, GpuData, GpuVendorData},
+        gpu_info::{GpuCapabilities, GpuInfo, GpuVendorInfo},
+        select_fans,
+        sensor_fault::{SensorFaultHandler, SensorFaultPolicy, SensorReading},
+        sysfs,
+    },
+};
+
+// PCI vendor ID of AMD devices
+pub const AMD_PCI_VENDOR_ID: &str = "0x1002";
+
+// Value of the hwmon pwm1_enable attribute for each fan control mode
+const PWM_ENABLE_MANUAL: u32 = 1;
+const PWM_ENABLE_AUTO: u32 = 2;
+
+// Maximum value of the hwmon pwm1 attribute
+const PWM_MAX: u32 = 255;
+
+pub struct AmdDevice {
+    // Store the sysfs device and hwmon directories
+    device_path: PathBuf,
+    hwmon_path: PathBuf,
+
+    // Store the GPU unique identifier
+    uuid: String,
+
+    // Store the device generic and vendor specific informations
+    gpu_info: GpuInfo,
+    gpu_vendor_info: GpuVendorInfo,
+
+    // Store the device generic and vendor specific data
+    gpu_data: GpuData,
+    gpu_vendor_data: GpuVendorData,
+
+    // Data update interval
+    gpu_data_update_interval: Duration,
+    // Instant of the last data update
+    gpu_data_last_update: Instant,
+
+    // Stock maximum core and memory clock read from pp_od_clk_voltage or
+    // the runtime state, None if the device doesn't support overdrive
+    od_core_clock_max: Option<u32>,
+    od_mem_clock_max: Option<u32>,
+    // Set if pp_od_clk_voltage expose a voltage offset
+    od_voltage_offset: bool,
+
+    // Currently applied overdrive offsets
+    core_clock_offset: i32,
+    mem_clock_offset: i32,
+
+    // Store the current fan mode
+    fan_mode: FanMode,
+    // Fan curve to apply in curve mode
+    fan_curve: Box<dyn FanCurve>,
+    // State of the fan controlled by the fan curve
+    fan_curve_state: CurveState,
+    // Time between two fan updates, passed to the fan curve
+    fan_update_interval: Duration,
+    // Set once a fan speed outside the supported range was reported
+    fan_speed_warned: bool,
+    // Set while the thermal failsafe force the fan to full speed
+    fan_failsafe: bool,
+    // Apply the sensor fault policy in curve mode,
+    // set while the policy gave the fan to the driver
+    sensor_fault: SensorFaultHandler,
+    sensor_auto: bool,
+    // Filtered temperature of the last fan update
+    temp_filtered: Option<f32>,
+
+    // Fan mode and settings of the device before the daemon changed them
+    original_fan_mode: FanMode,
+    original_config: GpuConfig,
+}
+
+impl AmdDevice {
+    // Create an AMD device from its sysfs device directory,
+    // usually "/sys/class/drm/cardN/device"
+    pub fn new(device_path: &Path) -> Result<Self> {
+        let hwmon_path = sysfs::find_hwmon(device_path).ok_or_else(|| {
+            DeviceError::Initialization {
+                reason: format!(
+                    "Failed to find hwmon directory for \"{}\"",
+                    device_path.display()
+                ),
+                error: anyhow!("No hwmon directory"),
+            }
+        })?;
+
+        let uuid = Self::get_uuid(device_path);
+
+        // Read the stock overdrive clocks, used as base for the offsets
+        let od_table =
+            sysfs::read_string(&device_path.join("pp_od_clk_voltage")).ok();
+        let od_core_clock_max = od_table
+            .as_deref()
+            .and_then(|table| parse_od_clock(table, "OD_SCLK:"));
+        let od_mem_clock_max = od_table
+            .as_deref()
+            .and_then(|table| parse_od_clock(table, "OD_MCLK:"));
+        let voltage_offset =
+            od_table.as_deref().and_then(parse_od_voltage_offset);
+
+        // Obtain the device informations
+        let overdrive =
+            od_core_clock_max.is_some() && od_mem_clock_max.is_some();
+        let gpu_info =
+            Self::get_gpu_info(&uuid, device_path, &hwmon_path, overdrive)
+                .map_err(|e| DeviceError::Initialization {
+                    reason: format!(
+                        "Failed to retrive GPU info for \"{}\"",
+                        uuid
+                    ),
+                    error: e.into(),
+                })?;
+
+        let gpu_vendor_info =
+            Self::get_gpu_vendor_info(device_path, &hwmon_path).map_err(
+                |e| DeviceError::Initialization {
+                    reason: format!(
+                        "Failed to retrive GPU vendor info for \"{}\"",
+                        uuid
+                    ),
+                    error: e.into(),
+                },
+            )?;
+
+        // Obtain the initialization general and vendor specific data
+        let gpu_data = Self::get_gpu_data(
+            device_path,
+            &hwmon_path,
+            gpu_info.fan_count,
+            od_core_clock_max.map(|_| 0),
+            od_mem_clock_max.map(|_| 0),
+        )
+        .map_err(|e| DeviceError::Initialization {
+            reason: format!("Failed to retrive GPU data for \"{}\"", uuid),
+            error: e.into(),
+        })?;
+
+        let gpu_vendor_data = Self::get_gpu_vendor_data(&hwmon_path);
+
+        // Determine the current fan mode
+        // We can't just assume it is automatic, if an old instance of
+        // the program changed it and crashed if could still be manual
+        // Fans that can't be controlled are always automatic
+        let fan_control = gpu_info.capabilities.fan_control;
+        let (fan_mode, original_fan_mode) = if !fan_control {
+            (FanMode::Auto, FanMode::Auto)
+        } else {
+            let pwm_enable: u32 = sysfs::read_value(
+                &hwmon_path.join("pwm1_enable"),
+            )
+            .map_err(|e| DeviceError::Initialization {
+                reason: format!(
+                    "Failed to retrive fan control mode for \"{}\"",
+                    uuid
+                ),
+                error: e.into(),
+            })?;
+
+            if pwm_enable == PWM_ENABLE_AUTO {
+                (FanMode::Auto, FanMode::Auto)
+            } else {
+                // The fan is given back to the driver on restore, a
+                // fixed speed without a controller isn't safe
+                (FanMode::Curve, FanMode::Auto)
+            }
+        };
+
+        // Snapshot the supported settings, restored on reset,
+        // the offsets are relative to the current clocks
+        let original_config = GpuConfig {
+            amd_config: AmdConfig {
+                core_clock_offset: od_core_clock_max.map(|_| 0),
+                mem_clock_offset: od_mem_clock_max.map(|_| 0),
+                voltage_offset,
+            },
+            power_limit: gpu_data
+                .power_limit
+                .filter(|_| gpu_info.capabilities.power_limit),
+            ..Default::default()
+        };
+
+        // Generate a default fan curve always at 100% fan speed
+        let fan_curve = Box::new(LinearCurve::new(&[(0, 100)]));
+
+        Ok(Self {
+            device_path: device_path.to_path_buf(),
+            hwmon_path,
+
+            uuid,
+
+            gpu_info,
+            gpu_vendor_info,
+
+            gpu_data,
+            gpu_vendor_data,
+
+            gpu_data_update_interval: DEFAULT_DATA_UPDATE_INTERVAL,
+            gpu_data_last_update: Instant::now(),
+
+            od_core_clock_max,
+            od_mem_clock_max,
+            od_voltage_offset: voltage_offset.is_some(),
+
+            core_clock_offset: 0,
+            mem_clock_offset: 0,
+
+            fan_mode,
+            fan_curve,
+            fan_curve_state: CurveState::default(),
+            fan_update_interval: DEFAULT_FAN_UPDATE_INTERVAL,
+            fan_speed_warned: false,
+            fan_failsafe: false,
+            sensor_fault: SensorFaultHandler::default(),
+            sensor_auto: false,
+            temp_filtered: None,
+
+            original_fan_mode,
+            original_config,
+        })
+    }
+
+    // Return true if the given sysfs device directory belong to an AMD GPU
+    pub fn is_amd_device(device_path: &Path) -> bool {
+        sysfs::read_string(&device_path.join("vendor"))
+            .is_ok_and(|vendor| vendor == AMD_PCI_VENDOR_ID)
+    }
+
+    // AMD GPUs don't have an UUID, use the unique ID when the
+    // device expose one and fall back to the PCI slot otherwise
+    pub fn get_uuid(device_path: &Path) -> String {
+        if let Ok(unique_id) =
+            sysfs::read_string(&device_path.join("unique_id"))
+        {
+            return format!("AMD-{}", unique_id);
+        }
+
+        let slot = sysfs::pci_slot_name(device_path).unwrap_or_else(|| {
+            device_path
+                .parent()
+                .and_then(|card| card.file_name())
+                .map(|card| card.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+
+        format!("AMD-{}", slot)
+    }
+
+    fn get_gpu_info(
+        uuid: &str,
+        device_path: &Path,
+        hwmon_path: &Path,
+        overdrive: bool,
+    ) -> Result<GpuInfo> {
+        let name = sysfs::read_string(&device_path.join("product_name"))
+            .or_else(|_| {
+                sysfs::read_string(&device_path.join("device"))
+                    .map(|id| format!("AMD Radeon ({})", id))
+            })?;
+
+        let pcie_gen =
+            sysfs::read_string(&device_path.join("current_link_speed"))
+                .map(|speed| pcie_gen_from_speed(&speed))
+                .unwrap_or(0);
+
+        // Convert the supported pwm range to percent, the minimum is
+        // rounded up so it is never converted back bellow the hardware one
+        let fan_speed_min =
+            sysfs::read_optional::<u32>(&hwmon_path.join("pwm1_min"))
+                .map(|pwm| (pwm * 100).div_ceil(PWM_MAX))
+                .unwrap_or(0);
+        let fan_speed_max =
+            sysfs::read_optional::<u32>(&hwmon_path.join("pwm1_max"))
+                .map(|pwm| pwm * 100 / PWM_MAX)
+                .unwrap_or(100);
+
+        // The amdgpu hwmon interface expose at most a single fan channel,
+        // passively cooled boards don't expose any
+        let capabilities = Self::get_capabilities(hwmon_path, overdrive);
+        let fan_count = u32::from(
+            capabilities.fan_control
+                || capabilities.fan_rpm
+                || hwmon_path.join("pwm1").exists(),
+        );
+
+        Ok(GpuInfo {
+            uuid: uuid.to_string(),
+            name,
+            pcie_width: sysfs::read_optional(
+                &device_path.join("current_link_width"),
+            )
+            .unwrap_or(0),
+            pcie_gen,
+            power_limit_max: sysfs::read_milliwatts(
+                &hwmon_path.join("power1_cap_max"),
+            )
+            .unwrap_or(0),
+            power_limit_min: sysfs::read_milliwatts(
+                &hwmon_path.join("power1_cap_min"),
+            )
+            .unwrap_or(0),
+            power_limit_default: sysfs::read_milliwatts(
+                &hwmon_path.join("power1_cap_default"),
+            )
+            .unwrap_or(0),
+
+            fan_count,
+            fan_speed_min,
+            fan_speed_max,
+
+            capabilities,
+        })
+    }
+
+    // Detect the features supported by the device from
+    // the hwmon attributes it expose
+    fn get_capabilities(hwmon_path: &Path, overdrive: bool) -> GpuCapabilities {
+        GpuCapabilities {
+            fan_control: hwmon_path.join("pwm1_enable").exists(),
+            fan_rpm: hwmon_path.join("fan1_input").exists(),
+
+            clock_offsets: overdrive,
+
+            power_limit: hwmon_path.join("power1_cap").exists(),
+            power_usage: hwmon_path.join("power1_average").exists()
+                || hwmon_path.join("power1_input").exists(),
+
+            mem_temp: hwmon_path.join("temp3_input").exists(),
+        }
+    }
+
+    fn get_gpu_vendor_info(
+        device_path: &Path,
+        hwmon_path: &Path,
+    ) -> Result<GpuVendorInfo> {
+        Ok(GpuVendorInfo::AMD {
+            vbios: sysfs::read_string(&device_path.join("vbios_version"))
+                .unwrap_or_default(),
+            device_id: sysfs::read_string(&device_path.join("device"))?,
+
+            edge_max_temp: read_celsius(&hwmon_path.join("temp1_crit")),
+            junction_max_temp: read_celsius(&hwmon_path.join("temp2_crit")),
+            mem_max_temp: read_celsius(&hwmon_path.join("temp3_crit")),
+            shutdown_temp: read_celsius(&hwmon_path.join("temp1_emergency")),
+        })
+    }
+
+    fn get_gpu_data(
+        device_path: &Path,
+        hwmon_path: &Path,
+        fan_count: u32,
+        core_clock_offset: Option<i32>,
+        mem_clock_offset: Option<i32>,
+    ) -> Result<GpuData> {
+        // Prefer the averaged power usage when available
+        let power_usage = sysfs::read_milliwatts(
+            &hwmon_path.join("power1_average"),
+        )
+        .or_else(|| sysfs::read_milliwatts(&hwmon_path.join("power1_input")));
+
+        // Convert the pwm value to a fan speed percentage
+        let fans = (0..fan_count)
+            .map(|_| FanData {
+                speed: sysfs::read_optional::<u32>(&hwmon_path.join("pwm1"))
+                    .map(|pwm| pwm * 100 / PWM_MAX),
+                speed_rpm: sysfs::read_optional(&hwmon_path.join("fan1_input")),
+            })
+            .collect();
+
+        // Get the memory usage data
+        let total_memory: Option<u64> =
+            sysfs::read_optional(&device_path.join("mem_info_vram_total"));
+        let used_memory: Option<u64> =
+            sysfs::read_optional(&device_path.join("mem_info_vram_used"));
+        let free_memory = total_memory
+            .zip(used_memory)
+            .map(|(total, used)| total.saturating_sub(used));
+
+        Ok(GpuData {
+            temp_gpu: read_celsius(&hwmon_path.join("temp1_input")),
+            temp_sensor_failures: 0,
+            temp_gpu_filtered: None,
+
+            graphics_freq: read_megahertz(&hwmon_path.join("freq1_input")),
+            mem_freq: read_megahertz(&hwmon_path.join("freq2_input")),
+
+            core_clock_offset,
+            mem_clock_offset,
+
+            power_usage,
+            power_limit: sysfs::read_milliwatts(&hwmon_path.join("power1_cap")),
+
+            fans,
+
+            core_usage: sysfs::read_optional(
+                &device_path.join("gpu_busy_percent"),
+            ),
+            mem_usage: sysfs::read_optional(
+                &device_path.join("mem_busy_percent"),
+            ),
+
+            total_memory,
+            used_memory,
+            free_memory,
+        })
+    }
+
+    fn get_gpu_vendor_data(hwmon_path: &Path) -> GpuVendorData {
+        GpuVendorData::AMD {
+            junction_temp: read_celsius(&hwmon_path.join("temp2_input")),
+            mem_temp: read_celsius(&hwmon_path.join("temp3_input")),
+            core_voltage: sysfs::read_optional(&hwmon_path.join("in0_input")),
+        }
+    }
+
+    // Update the device data only if GPU update interval has elapsed
+    fn udpate_data(&mut self) -> Result<()> {
+        let time_elapsed = self.gpu_data_last_update.elapsed();
+
+        if time_elapsed >= self.gpu_data_update_interval {
+            self.gpu_data = Self::get_gpu_data(
+                &self.device_path,
+                &self.hwmon_path,
+                self.gpu_info.fan_count,
+                self.od_core_clock_max.map(|_| self.core_clock_offset),
+                self.od_mem_clock_max.map(|_| self.mem_clock_offset),
+            )?;
+            self.gpu_vendor_data = Self::get_gpu_vendor_data(&self.hwmon_path);
+
+            self.gpu_data.temp_sensor_failures = self.sensor_fault.failures();
+            self.gpu_data.temp_gpu_filtered = self.temp_filtered;
+            self.gpu_data_last_update = Instant::now();
+        }
+
+        Ok(())
+    }
+
+    // Set the fan control to automatic while the sensor
+    // fault policy require it, and back to manual
+    fn set_sensor_auto(&mut self, auto: bool) -> Result<()> {
+        if auto == self.sensor_auto {
+            return Ok(());
+        }
+
+        let (pwm_enable, mode_name) = if auto {
+            (PWM_ENABLE_AUTO, "automatic")
+        } else {
+            (PWM_ENABLE_MANUAL, "manual")
+        };
+
+        sysfs::write_value(&self.hwmon_path.join("pwm1_enable"), pwm_enable)
+            .map_err(|e| DeviceError::DeviceFanError {
+                reason: format!(
+                    "Failed to set fan mode to {} for: \"{}\"",
+                    mode_name, self.uuid
+                ),
+                error: e.into(),
+            })?;
+
+        self.sensor_auto = auto;
+
+        Ok(())
+    }
+
+    // Write the given fan speed percentage to the pwm attribute,
+    // the speed is clamped to the range supported by the hardware
+    fn set_fan_speed(&mut self, speed: u8) -> Result<()> {
+        let speed =
+            clamp_fan_speed(&self.gpu_info, speed, &mut self.fan_speed_warned);
+        let pwm = speed as u32 * PWM_MAX / 100;
+
+        sysfs::write_value(&self.hwmon_path.join("pwm1"), pwm).map_err(|e| {
+            DeviceError::DeviceFanError {
+                reason: format!(
+                    "Failed to set fan speed for device \"{}\"",
+                    self.uuid
+                ),
+                error: e.into(),
+            }
+        })
+    }
+
+    // Write the overdrive commands to pp_od_clk_voltage and commit them,
+    // the settings not supported by the device are added to unsupported
+    fn apply_overdrive(
+        &mut self,
+        gpu_config: &GpuConfig,
+        unsupported: &mut Vec<&str>,
+    ) -> Result<()> {
+        let od_path = self.device_path.join("pp_od_clk_voltage");
+        let amd_config = gpu_config.amd_config;
+        let mut commit = false;
+
+        if let Some(offset) = amd_config.core_clock_offset {
+            if let Some(base) = self.od_core_clock_max {
+                let clock = base.saturating_add_signed(offset);
+                sysfs::write_value(&od_path, format!("s 1 {}", clock))?;
+
+                self.core_clock_offset = offset;
+                commit = true;
+            } else {
+                unsupported.push("Core clock offset");
+            }
+        }
+        if let Some(offset) = amd_config.mem_clock_offset {
+            if let Some(base) = self.od_mem_clock_max {
+                let clock = base.saturating_add_signed(offset);
+                sysfs::write_value(&od_path, format!("m 1 {}", clock))?;
+
+                self.mem_clock_offset = offset;
+                commit = true;
+            } else {
+                unsupported.push("Memory clock offset");
+            }
+        }
+        if let Some(offset) = amd_config.voltage_offset {
+            if self.od_voltage_offset {
+                sysfs::write_value(&od_path, format!("vo {}", offset))?;
+                commit = true;
+            } else {
+                unsupported.push("Voltage offset");
+            }
+        }
+
+        // Commit the new overdrive table
+        if commit {
+            sysfs::write_value(&od_path, "c")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl GpuDevice for AmdDevice {
+    // Return the device vendor
+    fn get_vendor(&self) -> GpuVendor {
+        GpuVendor::AMD
+    }
+
+    // Set the fan curve of the selected fans, this does not
+    // automatically set the fan mode to curve
+    fn set_fan_curve(
+        &mut self,
+        fans: FanSelection,
+        fan_curve: Box<dyn FanCurve>,
+    ) -> Result<()> {
+        select_fans(&self.uuid, fans, self.gpu_info.fan_count)?;
+
+        self.fan_curve = fan_curve;
+        self.fan_curve_state = CurveState::default();
+        self.fan_speed_warned = false;
+
+        Ok(())
+    }
+    // Return the info of the fan curve of every fan
+    fn get_fan_curves(&self) -> Vec<FanCurveInfo> {
+        vec![self.fan_curve.to_info(); self.gpu_info.fan_count as usize]
+    }
+    // Set the fan mode of the selected fans, if no fan curve was
+    // previously set default to a 100% fan speed curve
+    fn set_fan_mode(
+        &mut self,
+        fans: FanSelection,
+        fan_mode: FanMode,
+    ) -> Result<()> {
+        select_fans(&self.uuid, fans, self.gpu_info.fan_count)?;
+
+        // Without fan control the fan is always automatic
+        if !self.gpu_info.capabilities.fan_control {
+            return match fan_mode {
+                FanMode::Auto => Ok(()),
+                _ => check_unsupported(&self.uuid, &["Fan control"]),
+            };
+        }
+
+        // The mode is applied once the failsafe is disengaged
+        if self.fan_failsafe {
+            self.fan_mode = fan_mode;
+            return Ok(());
+        }
+
+        let pwm_enable_path = self.hwmon_path.join("pwm1_enable");
+        self.fan_speed_warned = false;
+        // The fan control is written again below, the sensor
+        // fault policy is applied again on the next update
+        self.sensor_auto = false;
+
+        match fan_mode {
+            FanMode::Auto => {
+                sysfs::write_value(&pwm_enable_path, PWM_ENABLE_AUTO).map_err(
+                    |e| DeviceError::DeviceFanError {
+                        reason: format!(
+                            "Failed to set fan mode to automatic for: \"{}\"",
+                            self.uuid
+                        ),
+                        error: e.into(),
+                    },
+                )?
+            }
+            _ => {
+                sysfs::write_value(&pwm_enable_path, PWM_ENABLE_MANUAL)
+                    .map_err(|e| DeviceError::DeviceFanError {
+                        reason: format!(
+                            "Failed to set fan mode to manual for: \"{}\"",
+                            self.uuid
+                        ),
+                        error: e.into(),
+                    })?;
+
+                self.fan_mode = fan_mode;
+                self.update_fan()?;
+            }
+        }
+
+        self.fan_mode = fan_mode;
+
+        Ok(())
+    }
+    // Update the fan speed according to the mode and the fan curve
+    fn update_fan(&mut self) -> Result<()> {
+        // A failed temperature read is handled
+        // according to the sensor fault policy
+        let reading = match self.fan_mode {
+            FanMode::Curve if !self.fan_failsafe => {
+                let temp = read_celsius(&self.hwmon_path.join("temp1_input"));
+                Some(self.sensor_fault.read(temp.map(|temp| temp as i32)))
+            }
+            _ => None,
+        };
+
+        // Give the fan to the driver while the policy requires it
+        self.set_sensor_auto(matches!(reading, Some(SensorReading::Auto)))?;
+        self.temp_filtered = None;
+
+        match (self.fan_mode, reading) {
+            _ if self.fan_failsafe => {
+                debug!("Updating fan: Failsafe - Speed: 100%");
+
+                self.set_fan_speed(100)?;
+            }
+            (FanMode::Curve, Some(SensorReading::Temp(temp))) => {
+                let input =
+                    curve_input(&self.gpu_info, temp, self.fan_update_interval);
+
+                let fan_speed = self.fan_curve.evaluate(
+                    &mut self.fan_curve_state,
+                    &input,
+                    Instant::now(),
+                );
+                self.temp_filtered = self.fan_curve_state.filter.filtered;
+
+                debug!("Updating fan: Mode Curve - Speed: {:?}%", fan_speed);
+
+                self.set_fan_speed(fan_speed)?;
+            }
+            (FanMode::Curve, Some(SensorReading::Duty(duty))) => {
+                debug!("Updating fan: Sensor fault - Speed: {:?}%", duty);
+
+                self.set_fan_speed(duty)?;
+            }
+            (FanMode::Manual(speed), _) => {
+                debug!("Updating fan: Mode Manual - Speed: {:?}%", speed);
+
+                self.set_fan_speed(speed)?;
+            }
+            _ => {
+                debug!("Updating fan: Mode Auto")
+            }
+        }
+
+        check_sensor_fault(&self.uuid, &self.sensor_fault)
+    }
+    // Change the time between two fan updates, used by the fan
+    // curve if it depends on time before its first update
+    fn set_fan_update_interval(&mut self, update_interval: Duration) {
+        self.fan_update_interval = update_interval;
+    }
+
+    // Change the policy applied when the temperature can't be read
+    fn set_sensor_fault_policy(&mut self, policy: SensorFaultPolicy) {
+        self.sensor_fault.set_policy(policy);
+    }
+
+    // Force the fan to full speed regardless of its mode,
+    // the fan mode is applied again once disengaged
+    fn set_fan_failsafe(&mut self, engaged: bool) -> Result<()> {
+        if !self.gpu_info.capabilities.fan_control
+            || engaged == self.fan_failsafe
+        {
+            return Ok(());
+        }
+
+        if !engaged {
+            self.fan_failsafe = false;
+            return self.set_fan_mode(FanSelection::All, self.fan_mode);
+        }
+
+        sysfs::write_value(
+            &self.hwmon_path.join("pwm1_enable"),
+            PWM_ENABLE_MANUAL,
+        )
+        .map_err(|e| DeviceError::DeviceFanError {
+            reason: format!(
+                "Failed to set fan mode to manual for: \"{}\"",
+                self.uuid
+            ),
+            error: e.into(),
+        })?;
+
+        self.fan_failsafe = true;
+        self.sensor_auto = false;
+        self.update_fan()
+    }
+
+    // Return the device vendor specific information
+    fn get_vendor_info(&self) -> GpuVendorInfo {
+        self.gpu_vendor_info.clone()
+    }
+    // Return the device general information
+    fn get_info(&self) -> GpuInfo {
+        self.gpu_info.clone()
+    }
+
+    // Return the device vendor specific real time data,
+    // the update frequency is controlled by the set_update_freq function,
+    // the default update frequency is 1 hertz
+    fn get_vendor_data(&mut self) -> Result<GpuVendorData> {
+        self.udpate_data()?;
+        Ok(self.gpu_vendor_data.clone())
+    }
+    // Return the device general real time data
+    // the update frequency is controlled by the set_update_freq function,
+    // the default update frequency is 1 hertz
+    fn get_data(&mut self) -> Result<GpuData> {
+        self.udpate_data()?;
+        Ok(self.gpu_data.clone())
+    }
+    // Change the vendor and general data update frequency
+    fn set_data_update_interval(&mut self, update_interval: Duration) {
+        self.gpu_data_update_interval = update_interval;
+    }
+
+    // Apply the given GPU configuration to the device
+    // The configuration vendor must match the
+    fn apply_gpu_config(&mut self, gpu_config: GpuConfig) -> Result<()> {
+        let mut unsupported = Vec::new();
+
+        // The settings missing from the configuration
+        // are restored to their original value
+        let gpu_config = gpu_config.or(&self.original_config);
+
+        // Set the power limit, the hwmon power cap is in microwatts
+        if let Some(power_limit) = gpu_config.power_limit {
+            if !self.gpu_info.capabilities.power_limit {
+                unsupported.push("Power limit");
+            } else if power_limit > self.gpu_info.power_limit_max {
+                warn!(
+                    "requested power limit is beyond max ({}), ingoring it",
+                    self.gpu_info.power_limit_max
+                );
+            } else if power_limit < self.gpu_info.power_limit_min {
+                warn!(
+                    "requested power limit is bellow min ({}), ingoring it",
+                    self.gpu_info.power_limit_min
+                );
+            } else {
+                sysfs::write_value(
+                    &self.hwmon_path.join("power1_cap"),
+                    power_limit as u64 * 1000,
+                )?;
+            }
+        }
+
+        // Set vendor specific config
+        self.apply_overdrive(&gpu_config, &mut unsupported)?;
+
+        check_unsupported(&self.uuid, &unsupported)
+    }
+
+    // Return the fan mode and settings the device
+    // had before the daemon changed them
+    fn get_original_settings(&self) -> OriginalSettings {
+        OriginalSettings {
+            fan_modes: vec![
+                self.original_fan_mode;
+                self.gpu_info.fan_count as usize
+            ],
+            config: self.original_config.clone(),
+            amd_stock_clocks: AmdStockClocks {
+                core_clock_max: self.od_core_clock_max,
+                mem_clock_max: self.od_mem_clock_max,
+            },
+        }
+    }
+    // Replace the settings restored by reset, all
+    // the fans share the mode of the first one
+    fn set_original_settings(&mut self, settings: OriginalSettings) {
+        if let Some(fan_mode) = settings.fan_modes.first() {
+            self.original_fan_mode = *fan_mode;
+        }
+
+        // The table read at startup still hold the offsets
+        // applied by the instance that recorded the settings
+        let stock_clocks = settings.amd_stock_clocks;
+        if self.od_core_clock_max.is_some()
+            && stock_clocks.core_clock_max.is_some()
+        {
+            self.od_core_clock_max = stock_clocks.core_clock_max;
+        }
+        if self.od_mem_clock_max.is_some()
+            && stock_clocks.mem_clock_max.is_some()
+        {
+            self.od_mem_clock_max = stock_clocks.mem_clock_max;
+        }
+
+        self.original_config = settings.config;
+    }
+
+    // Restore the fan mode and the settings the
+    // device had before the daemon started
+    fn reset(&mut self) -> Result<()> {
+        self.fan_failsafe = false;
+        self.set_fan_mode(FanSelection::All, self.original_fan_mode)?;
+        self.apply_gpu_config(GpuConfig::default())
+    }
+
+    // The sysfs device directory disappear when the GPU is unplugged
+    fn is_available(&self) -> bool {
+        self.device_path.join("vendor").exists()
+    }
+}
+
+// Read a hwmon temperature attribute in degrees Celsius,
+// hwmon temperatures are stored in millidegrees
+fn read_celsius(path: &Path) -> Option<u32> {
+    sysfs::read_optional::<i64>(path).map(|temp| (temp / 1000).max(0) as u32)
+}
+
+// Read a hwmon frequency attribute in megahertz,
+// hwmon frequencies are stored in hertz
+fn read_megahertz(path: &Path) -> Option<u32> {
+    sysfs::read_optional::<u64>(path).map(|freq| (freq / 1_000_000) as u32)
+}
+
+// Convert a PCIe link speed (e.g. "16.0 GT/s PCIe") to its generation
+fn pcie_gen_from_speed(speed: &str) -> u32 {
+    let rate = speed
+        .split_whitespace()
+        .next()
+        .and_then(|rate| rate.parse::<f32>().ok())
+        .unwrap_or(0.0);
+
+    match rate {
+        r if r >= 64.0 => 6,
+        r if r >= 32.0 => 5,
+        r if r >= 16.0 => 4,
+        r if r >= 8.0 => 3,
+        r if r >= 5.0 => 2,
+        r if r > 0.0 => 1,
+        _ => 0,
+    }
+}
+
+// Return the clock of the highest state in the given section of the
+// pp_od_clk_voltage table, lines have the form "1: 2615Mhz"
+fn parse_od_clock(table: &str, section: &str) -> Option<u32> {
+    table
+        .lines()
+        .skip_while(|line| line.trim() != section)
+        .skip(1)
+        .take_while(|line| !line.trim().ends_with(':'))
+        .filter_map(|line| {
+            let (_, clock) = line.split_once(':')?;
+            let clock = clock.trim().to_lowercase();
+
+            clock.strip_suffix("mhz")?.trim().parse::<u32>().ok()
+        })
+        .max()
+}
+
+// Return the voltage offset of the pp_od_clk_voltage
+// table, the section has the form "OD_VDDGFX_OFFSET:\n0mV"
+fn parse_od_voltage_offset(table: &str) -> Option<i32> {
+    let offset = table
+        .lines()
+        .skip_while(|line| line.trim() != "OD_VDDGFX_OFFSET:")
+        .nth(1)?
+        .trim()
+        .to_lowercase();
+
+    offset.strip_suffix("mv")?.trim().parse::<i32>().ok()
+}
@@ -0,0 +1,376 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use nvml_wrapper::{
+    enum_wrappers::device::{
+        Clock, ClockId, TemperatureSensor, TemperatureThreshold,
+    },
+    enums::device::FanControlPolicy,
+    error::NvmlError,
+    struct_wrappers::device::{MemoryInfo, Utilization},
+    structs::device::PowerManagementConstraints,
+};
+
+use crate::gpu_device::nvml_backend::{NvmlBackend, NvmlResult};
+
+// NVML backend operations, one for each trait method
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NvmlOperation {
+    Uuid,
+    Name,
+    SysDriverVersion,
+    VbiosVersion,
+    NumCores,
+    CurrentPcieLinkWidth,
+    CurrentPcieLinkGen,
+    TemperatureThreshold,
+    PowerManagementLimitConstraints,
+    PowerManagementLimitDefault,
+    PowerManagementLimit,
+    PowerUsage,
+    SetPowerManagementLimit,
+    Temperature,
+    Clock,
+    UtilizationRates,
+    MemoryInfo,
+    GpcClockVfOffset,
+    MemClockVfOffset,
+    SetGpcClockVfOffset,
+    SetMemClockVfOffset,
+    NumFans,
+    MinMaxFanSpeed,
+    FanSpeed,
+    FanSpeedRpm,
+    FanControlPolicy,
+    SetFanControlPolicy,
+    SetFanSpeed,
+}
+
+// Injected errors, indexed by operation
+type Failures = HashMap<NvmlOperation, fn() -> NvmlError>;
+
+// State of the fake NVML driver, every field can be
+// freely modified to simulate the hardware behaviour
+#[derive(Debug, Clone)]
+pub struct FakeNvmlState {
+    pub uuid: String,
+    pub name: String,
+    pub driver_version: String,
+    pub vbios_version: String,
+    pub num_cores: u32,
+    pub pcie_width: u32,
+    pub pcie_gen: u32,
+
+    // Missing thresholds and clocks are reported as not supported
+    pub temperature_thresholds: HashMap<TemperatureThreshold, u32>,
+    pub clocks: HashMap<(Clock, ClockId), u32>,
+
+    // Power values in milliwatts
+    pub power_limit_min: u32,
+    pub power_limit_max: u32,
+    pub power_limit_default: u32,
+    pub power_limit: u32,
+    pub power_usage: u32,
+
+    pub temperature: u32,
+    pub utilization: Utilization,
+    pub memory_info: MemoryInfo,
+
+    pub gpc_clock_offset: i32,
+    pub mem_clock_offset: i32,
+
+    // Minimum and maximum fan speed accepted by the driver
+    pub fan_speed_min: u32,
+    pub fan_speed_max: u32,
+
+    // Per fan values, the fan count is the length of the vectors
+    pub fan_speeds: Vec<u32>,
+    pub fan_speeds_rpm: Vec<u32>,
+    pub fan_policies: Vec<FanControlPolicy>,
+}
+
+impl Default for FakeNvmlState {
+    fn default() -> Self {
+        let temperature_thresholds = HashMap::from([
+            (TemperatureThreshold::GpuMax, 93),
+            (TemperatureThreshold::Slowdown, 90),
+            (TemperatureThreshold::Shutdown, 98),
+        ]);
+
+        let clocks = HashMap::from([
+            ((Clock::Graphics, ClockId::Current), 1800),
+            ((Clock::Memory, ClockId::Current), 9500),
+            ((Clock::SM, ClockId::Current), 1800),
+            ((Clock::Video, ClockId::Current), 1600),
+        ]);
+
+        Self {
+            uuid: "GPU-00000000-0000-0000-0000-000000000000".to_string(),
+            name: "Fake Nvidia GPU".to_string(),
+            driver_version: "000.00".to_string(),
+            vbios_version: "00.00.00.00.00".to_string(),
+            num_cores: 4096,
+            pcie_width: 16,
+            pcie_gen: 4,
+
+            temperature_thresholds,
+            clocks,
+
+            power_limit_min: 100_000,
+            power_limit_max: 300_000,
+            power_limit_default: 250_000,
+            power_limit: 250_000,
+            power_usage: 50_000,
+
+            temperature: 50,
+            utilization: Utilization { gpu: 0, memory: 0 },
+            memory_info: MemoryInfo {
+                free: 8 << 30,
+                reserved: 0,
+                total: 8 << 30,
+                used: 0,
+                version: 0,
+            },
+
+            gpc_clock_offset: 0,
+            mem_clock_offset: 0,
+
+            fan_speed_min: 0,
+            fan_speed_max: 100,
+
+            fan_speeds: vec![30],
+            fan_speeds_rpm: vec![1000],
+            fan_policies: vec![FanControlPolicy::TemperatureContinousSw],
+        }
+    }
+}
+
+// In memory NVML backend used to test the Nvidia device without
+// hardware, clones share the same state so a test can keep a handle
+// on the driver after moving it into the device
+#[derive(Clone, Default)]
+pub struct FakeNvmlBackend {
+    state: Arc<Mutex<FakeNvmlState>>,
+
+    // Errors to return instead of the state values
+    failures: Arc<Mutex<Failures>>,
+}
+
+impl FakeNvmlBackend {
+    pub fn new(state: FakeNvmlState) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(state)),
+            failures: Arc::default(),
+        }
+    }
+
+    // Access the driver state
+    pub fn state(&self) -> MutexGuard<'_, FakeNvmlState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Make every call of the given operation fail with the given error,
+    // e.g. fail(NvmlOperation::FanControlPolicy, || NvmlError::NotSupported)
+    pub fn fail(&self, operation: NvmlOperation, error: fn() -> NvmlError) {
+        self.failures().insert(operation, error);
+    }
+
+    // Stop injecting errors for the given operation
+    pub fn clear_failure(&self, operation: NvmlOperation) {
+        self.failures().remove(&operation);
+    }
+
+    // Stop injecting errors for all operations
+    pub fn clear_failures(&self) {
+        self.failures().clear();
+    }
+
+    fn failures(&self) -> MutexGuard<'_, Failures> {
+        self.failures.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // Return the injected error for the given operation if any
+    fn check(&self, operation: NvmlOperation) -> NvmlResult<()> {
+        match self.failures().get(&operation) {
+            Some(error) => Err(error()),
+            None => Ok(()),
+        }
+    }
+
+    // Check the fan index is valid and return it as a vector index
+    fn fan_index(&self, fan_idx: u32) -> NvmlResult<usize> {
+        if (fan_idx as usize) < self.state().fan_speeds.len() {
+            Ok(fan_idx as usize)
+        } else {
+            Err(NvmlError::InvalidArg)
+        }
+    }
+}
+
+impl NvmlBackend for FakeNvmlBackend {
+    fn uuid(&self) -> NvmlResult<String> {
+        self.check(NvmlOperation::Uuid)?;
+        Ok(self.state().uuid.clone())
+    }
+    fn name(&self) -> NvmlResult<String> {
+        self.check(NvmlOperation::Name)?;
+        Ok(self.state().name.clone())
+    }
+    fn sys_driver_version(&self) -> NvmlResult<String> {
+        self.check(NvmlOperation::SysDriverVersion)?;
+        Ok(self.state().driver_version.clone())
+    }
+    fn vbios_version(&self) -> NvmlResult<String> {
+        self.check(NvmlOperation::VbiosVersion)?;
+        Ok(self.state().vbios_version.clone())
+    }
+    fn num_cores(&self) -> NvmlResult<u32> {
+        self.check(NvmlOperation::NumCores)?;
+        Ok(self.state().num_cores)
+    }
+    fn current_pcie_link_width(&self) -> NvmlResult<u32> {
+        self.check(NvmlOperation::CurrentPcieLinkWidth)?;
+        Ok(self.state().pcie_width)
+    }
+    fn current_pcie_link_gen(&self) -> NvmlResult<u32> {
+        self.check(NvmlOperation::CurrentPcieLinkGen)?;
+        Ok(self.state().pcie_gen)
+    }
+    fn temperature_threshold(
+        &self,
+        threshold: TemperatureThreshold,
+    ) -> NvmlResult<u32> {
+        self.check(NvmlOperation::TemperatureThreshold)?;
+        self.state()
+            .temperature_thresholds
+            .get(&threshold)
+            .copied()
+            .ok_or(NvmlError::NotSupported)
+    }
+
+    fn power_management_limit_constraints(
+        &self,
+    ) -> NvmlResult<PowerManagementConstraints> {
+        self.check(NvmlOperation::PowerManagementLimitConstraints)?;
+
+        let state = self.state();
+        Ok(PowerManagementConstraints {
+            min_limit: state.power_limit_min,
+            max_limit: state.power_limit_max,
+        })
+    }
+    fn power_management_limit_default(&self) -> NvmlResult<u32> {
+        self.check(NvmlOperation::PowerManagementLimitDefault)?;
+        Ok(self.state().power_limit_default)
+    }
+    fn power_management_limit(&self) -> NvmlResult<u32> {
+        self.check(NvmlOperation::PowerManagementLimit)?;
+        Ok(self.state().power_limit)
+    }
+    fn power_usage(&self) -> NvmlResult<u32> {
+        self.check(NvmlOperation::PowerUsage)?;
+        Ok(self.state().power_usage)
+    }
+    fn set_power_management_limit(&mut self, limit: u32) -> NvmlResult<()> {
+        self.check(NvmlOperation::SetPowerManagementLimit)?;
+
+        let mut state = self.state();
+        if limit < state.power_limit_min || limit > state.power_limit_max {
+            return Err(NvmlError::InvalidArg);
+        }
+
+        state.power_limit = limit;
+        Ok(())
+    }
+
+    fn temperature(&self, _sensor: TemperatureSensor) -> NvmlResult<u32> {
+        self.check(NvmlOperation::Temperature)?;
+        Ok(self.state().temperature)
+    }
+    fn clock(&self, clock: Clock, clock_id: ClockId) -> NvmlResult<u32> {
+        self.check(NvmlOperation::Clock)?;
+        self.state()
+            .clocks
+            .get(&(clock, clock_id))
+            .copied()
+            .ok_or(NvmlError::NotSupported)
+    }
+    fn utilization_rates(&self) -> NvmlResult<Utilization> {
+        self.check(NvmlOperation::UtilizationRates)?;
+        Ok(self.state().utilization.clone())
+    }
+    fn memory_info(&self) -> NvmlResult<MemoryInfo> {
+        self.check(NvmlOperation::MemoryInfo)?;
+        Ok(self.state().memory_info.clone())
+    }
+
+    fn gpc_clock_vf_offset(&self) -> NvmlResult<i32> {
+        self.check(NvmlOperation::GpcClockVfOffset)?;
+        Ok(self.state().gpc_clock_offset)
+    }
+    fn mem_clock_vf_offset(&self) -> NvmlResult<i32> {
+        self.check(NvmlOperation::MemClockVfOffset)?;
+        Ok(self.state().mem_clock_offset)
+    }
+    fn set_gpc_clock_vf_offset(&mut self, offset: i32) -> NvmlResult<()> {
+        self.check(NvmlOperation::SetGpcClockVfOffset)?;
+        self.state().gpc_clock_offset = offset;
+        Ok(())
+    }
+    fn set_mem_clock_vf_offset(&mut self, offset: i32) -> NvmlResult<()> {
+        self.check(NvmlOperation::SetMemClockVfOffset)?;
+        self.state().mem_clock_offset = offset;
+        Ok(())
+    }
+
+    fn num_fans(&self) -> NvmlResult<u32> {
+        self.check(NvmlOperation::NumFans)?;
+        Ok(self.state().fan_speeds.len() as u32)
+    }
+    fn min_max_fan_speed(&self) -> NvmlResult<(u32, u32)> {
+        self.check(NvmlOperation::MinMaxFanSpeed)?;
+
+        let state = self.state();
+        Ok((state.fan_speed_min, state.fan_speed_max))
+    }
+    fn fan_speed(&self, fan_idx: u32) -> NvmlResult<u32> {
+        self.check(NvmlOperation::FanSpeed)?;
+        let i = self.fan_index(fan_idx)?;
+        Ok(self.state().fan_speeds[i])
+    }
+    fn fan_speed_rpm(&self, fan_idx: u32) -> NvmlResult<u32> {
+        self.check(NvmlOperation::FanSpeedRpm)?;
+        let i = self.fan_index(fan_idx)?;
+        Ok(self.state().fan_speeds_rpm[i])
+    }
+    fn fan_control_policy(&self, fan_idx: u32) -> NvmlResult<FanControlPolicy> {
+        self.check(NvmlOperation::FanControlPolicy)?;
+        let i = self.fan_index(fan_idx)?;
+        Ok(self.state().fan_policies[i])
+    }
+    fn set_fan_control_policy(
+        &mut self,
+        fan_idx: u32,
+        policy: FanControlPolicy,
+    ) -> NvmlResult<()> {
+        self.check(NvmlOperation::SetFanControlPolicy)?;
+        let i = self.fan_index(fan_idx)?;
+        self.state().fan_policies[i] = policy;
+        Ok(())
+    }
+    fn set_fan_speed(&mut self, fan_idx: u32, speed: u32) -> NvmlResult<()> {
+        self.check(NvmlOperation::SetFanSpeed)?;
+        let i = self.fan_index(fan_idx)?;
+
+        // Like the real driver reject speeds outside the supported range
+        let mut state = self.state();
+        if speed < state.fan_speed_min || speed > state.fan_speed_max {
+            return Err(NvmlError::InvalidArg);
+        }
+
+        state.fan_speeds[i] = speed;
+        Ok(())
+    }
+}
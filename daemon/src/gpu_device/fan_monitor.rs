@@ -0,0 +1,122 @@
+use crate::gpu_device::{gpu_data::FanData, gpu_info::GpuInfo};
+
+// Number of consecutive updates with a fan speed far below the
+// expected one before the fan is considered stalled
+pub const STALL_UPDATES: u32 = 3;
+// Fraction of the expected fan speed below which a fan is too slow
+const STALL_RPM_RATIO: f32 = 0.25;
+// Fans are allowed to stop below this duty, e.g. in zero RPM mode
+const STALL_MIN_DUTY: u32 = 20;
+
+// Health of a single fan
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FanHealth {
+    Ok,
+    // The fan doesn't spin, or too slowly, for the commanded duty
+    Stalled,
+    // The device doesn't report the fan speed in RPM
+    Unknown,
+}
+
+impl FanHealth {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FanHealth::Ok => "ok",
+            FanHealth::Stalled => "stalled",
+            FanHealth::Unknown => "unknown",
+        }
+    }
+}
+
+// Monitoring state of a single fan
+#[derive(Debug, Clone)]
+struct FanMonitorState {
+    health: FanHealth,
+
+    // Number of consecutive updates with a fan speed too low
+    slow_updates: u32,
+    // Highest RPM per percent of duty observed, used
+    // to estimate the expected speed of the fan
+    rpm_per_duty: f32,
+}
+
+// Detect the stalled fans of a device from the commanded
+// duty and the measured speed of its fans
+#[derive(Debug, Clone)]
+pub struct FanMonitor {
+    fans: Vec<FanMonitorState>,
+}
+
+impl FanMonitor {
+    // Create the monitor of a device, the health of the fans
+    // is unknown if the device doesn't report their RPM
+    pub fn new(info: &GpuInfo) -> Self {
+        let health = if info.capabilities.fan_rpm {
+            FanHealth::Ok
+        } else {
+            FanHealth::Unknown
+        };
+
+        let fan = FanMonitorState {
+            health,
+
+            slow_updates: 0,
+            rpm_per_duty: 0.0,
+        };
+
+        Self {
+            fans: vec![fan; info.fan_count as usize],
+        }
+    }
+
+    // Return the health of every fan
+    pub fn health(&self) -> Vec<FanHealth> {
+        self.fans.iter().map(|fan| fan.health).collect()
+    }
+
+    // Update the fans health with the latest fan data,
+    // return the index and new health of the fans that changed
+    pub fn update(&mut self, fans_data: &[FanData]) -> Vec<(u32, FanHealth)> {
+        let mut changed = Vec::new();
+
+        for (i, (fan, data)) in
+            self.fans.iter_mut().zip(fans_data.iter()).enumerate()
+        {
+            let (Some(duty), Some(rpm)) = (data.speed, data.speed_rpm) else {
+                continue;
+            };
+
+            // A low duty doesn't prove anything about the fan
+            if duty < STALL_MIN_DUTY {
+                fan.slow_updates = 0;
+                continue;
+            }
+
+            let expected = fan.rpm_per_duty * duty as f32;
+            let slow = rpm == 0 || (rpm as f32) < expected * STALL_RPM_RATIO;
+
+            let health = if slow {
+                fan.slow_updates += 1;
+
+                if fan.slow_updates >= STALL_UPDATES {
+                    FanHealth::Stalled
+                } else {
+                    fan.health
+                }
+            } else {
+                fan.slow_updates = 0;
+                fan.rpm_per_duty =
+                    fan.rpm_per_duty.max(rpm as f32 / duty as f32);
+
+                FanHealth::Ok
+            };
+
+            if health != fan.health {
+                fan.health = health;
+                changed.push((i as u32, health));
+            }
+        }
+
+        changed
+    }
+}
@@ -1,15 +1,109 @@
+use serde::{Deserialize, Serialize};
+
+use crate::fan_curve::fan_mode::FanMode;
+
 // Vendor specific configuration
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
 pub struct NvidiaConfig {
     pub core_clock_offset: Option<i32>,
     pub mem_clock_offset: Option<i32>,
 }
 
+// Vendor specific configuration, the clock offsets are applied
+// relative to the stock maximum clocks of pp_od_clk_voltage
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct AmdConfig {
+    pub core_clock_offset: Option<i32>,
+    pub mem_clock_offset: Option<i32>,
+    pub voltage_offset: Option<i32>,
+}
+
 // General configuration
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct GpuConfig {
     pub nvidia_config: NvidiaConfig,
+    pub amd_config: AmdConfig,
 
     // GPU power limit
     pub power_limit: Option<u32>,
 }
+
+impl GpuConfig {
+    // Return the configuration with every unset setting
+    // replaced by the one of the given fallback configuration
+    pub fn or(&self, fallback: &GpuConfig) -> GpuConfig {
+        let nvidia = self.nvidia_config;
+        let nvidia_fallback = fallback.nvidia_config;
+        let amd = self.amd_config;
+        let amd_fallback = fallback.amd_config;
+
+        GpuConfig {
+            nvidia_config: NvidiaConfig {
+                core_clock_offset: nvidia
+                    .core_clock_offset
+                    .or(nvidia_fallback.core_clock_offset),
+                mem_clock_offset: nvidia
+                    .mem_clock_offset
+                    .or(nvidia_fallback.mem_clock_offset),
+            },
+            amd_config: AmdConfig {
+                core_clock_offset: amd
+                    .core_clock_offset
+                    .or(amd_fallback.core_clock_offset),
+                mem_clock_offset: amd
+                    .mem_clock_offset
+                    .or(amd_fallback.mem_clock_offset),
+                voltage_offset: amd
+                    .voltage_offset
+                    .or(amd_fallback.voltage_offset),
+            },
+
+            power_limit: self.power_limit.or(fallback.power_limit),
+        }
+    }
+
+    // Return true if the configuration set a non zero
+    // clock or voltage offset, for any vendor
+    pub fn has_overclock(&self) -> bool {
+        [
+            self.nvidia_config.core_clock_offset,
+            self.nvidia_config.mem_clock_offset,
+            self.amd_config.core_clock_offset,
+            self.amd_config.mem_clock_offset,
+            self.amd_config.voltage_offset,
+        ]
+        .iter()
+        .any(|offset| offset.is_some_and(|offset| offset != 0))
+    }
+
+    // Return the configuration without its clock and voltage
+    // offsets, the devices then restore their original offsets
+    pub fn without_overclock(&self) -> GpuConfig {
+        GpuConfig {
+            power_limit: self.power_limit,
+            ..Default::default()
+        }
+    }
+}
+
+// Stock maximum clocks of an AMD overdrive table, the clock
+// offsets are applied on top of them
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct AmdStockClocks {
+    pub core_clock_max: Option<u32>,
+    pub mem_clock_max: Option<u32>,
+}
+
+// Fan modes and configuration of a device before the daemon
+// changed them, restored when the device is reset
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct OriginalSettings {
+    // Original mode of every fan, by fan index
+    pub fan_modes: Vec<FanMode>,
+    pub config: GpuConfig,
+
+    // Recorded so an instance started after a crash doesn't
+    // read the overclocked table as the stock one
+    #[serde(default)]
+    pub amd_stock_clocks: AmdStockClocks,
+}
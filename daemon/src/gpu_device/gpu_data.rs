@@ -14,37 +14,69 @@ pub enum GpuVendorData {
         video_boost_freq: Option<u32>,
     },
     AMD {
-        // TODO: AMD vendor data
+        // Junction (hotspot) and memory temperature
+        junction_temp: Option<u32>,
+        mem_temp: Option<u32>,
+
+        // Core voltage in millivolts
+        core_voltage: Option<u32>,
+    },
+    Intel {
+        // Energy counter in microjoules
+        energy: Option<u64>,
+
+        // Core voltage in millivolts
+        core_voltage: Option<u32>,
     },
+    Simulated {
+        // Synthetic load from 0.0 to 1.0
+        load: f32,
+    },
+}
+
+// Store the real time data of a single fan
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FanData {
+    // Fan speed in percent and in rotation per minute,
+    // None if the device doesn't report it
+    pub speed: Option<u32>,
+    pub speed_rpm: Option<u32>,
 }
 
-// Store the general GPU data
+// Store the general GPU data, the optional values are
+// None when the device doesn't support reading them
 #[derive(Debug, Clone)]
 pub struct GpuData {
-    pub temp_gpu: u32,
+    // None if the temperature sensor can't be read
+    pub temp_gpu: Option<u32>,
+    // Consecutive failed temperature reads of the fan control,
+    // the sensor fault policy is applied while it is not zero
+    pub temp_sensor_failures: u32,
+    // Temperature given to the fan curves after filtering,
+    // None if the fan curve in use doesn't filter it
+    pub temp_gpu_filtered: Option<f32>,
 
     // Core and memory current frequency
-    pub graphics_freq: u32,
-    pub mem_freq: u32,
+    pub graphics_freq: Option<u32>,
+    pub mem_freq: Option<u32>,
 
     // Overclocking frequency offsets
-    pub core_clock_offset: i32,
-    pub mem_clock_offset: i32,
+    pub core_clock_offset: Option<i32>,
+    pub mem_clock_offset: Option<i32>,
 
     // Power usage and power limit
-    pub power_usage: u32,
-    pub power_limit: u32,
+    pub power_usage: Option<u32>,
+    pub power_limit: Option<u32>,
 
-    // Fan information
-    pub fan_speed: u32,
-    pub fan_speed_rpm: u32,
+    // Fan information, one entry for each fan of the device
+    pub fans: Vec<FanData>,
 
     // Utilization information
-    pub core_usage: u32,
-    pub mem_usage: u32,
+    pub core_usage: Option<u32>,
+    pub mem_usage: Option<u32>,
 
     // Memory utilization information, all values in bytes
-    pub total_memory: u64,
-    pub used_memory: u64,
-    pub free_memory: u64,
+    pub total_memory: Option<u64>,
+    pub used_memory: Option<u64>,
+    pub free_memory: Option<u64>,
 }
@@ -16,10 +16,46 @@ pub enum GpuVendorInfo {
         shutdown_temp: Option<u32>,
     },
     AMD {
-        // TODO: AMD vendor info
+        vbios: String,
+        device_id: String,
+
+        // GPU temperature threshold
+        edge_max_temp: Option<u32>,
+        junction_max_temp: Option<u32>,
+        mem_max_temp: Option<u32>,
+        shutdown_temp: Option<u32>,
+    },
+    Intel {
+        // Kernel driver in use, either "i915" or "xe"
+        driver: String,
+        device_id: String,
+    },
+    Simulated {
+        // Thermal model parameters
+        ambient_temp: f32,
+        heat_capacity: f32,
     },
 }
 
+// Features supported by the device, detected at initialization
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GpuCapabilities {
+    // The fan speed can be controlled by the daemon
+    pub fan_control: bool,
+    // The fan speed in RPM can be read
+    pub fan_rpm: bool,
+
+    // The core and memory clock offsets can be set
+    pub clock_offsets: bool,
+
+    // The power limit can be set and the power usage read
+    pub power_limit: bool,
+    pub power_usage: bool,
+
+    // The memory temperature can be read
+    pub mem_temp: bool,
+}
+
 // Store GPU general information
 #[derive(Debug, Clone)]
 pub struct GpuInfo {
@@ -32,5 +68,20 @@ pub struct GpuInfo {
     pub power_limit_max: u32,
     pub power_limit_min: u32,
     pub power_limit_default: u32,
+
+    // Number of fans and fan speed range supported by the hardware,
+    // speeds are in percent
+    pub fan_count: u32,
+    pub fan_speed_min: u32,
+    pub fan_speed_max: u32,
+
+    // Features supported by the device
+    pub capabilities: GpuCapabilities,
 }
 
+impl GpuInfo {
+    // Return true if the fans can be stopped by the zero RPM mode
+    pub fn supports_zero_rpm(&self) -> bool {
+        self.capabilities.fan_control && self.fan_speed_min == 0
+    }
+}
@@ -0,0 +1,546 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use tracing::{debug, warn};
+
+use crate::{
+    fan_curve::{
+        FanCurve,
+        fan_curve_info::FanCurveInfo,
+        fan_mode::{FanMode, FanSelection},
+    },
+    gpu_device::{
+        DEFAULT_DATA_UPDATE_INTERVAL, DeviceError, GpuDevice, GpuVendor,
+        Result, check_unsupported,
+        gpu_config::{GpuConfig, OriginalSettings},
+        gpu_data::{FanData, GpuData, GpuVendorData},
+        gpu_info::{GpuCapabilities, GpuInfo, GpuVendorInfo},
+        sensor_fault::SensorFaultPolicy,
+        sysfs,
+    },
+};
+
+// PCI vendor ID of Intel devices
+pub const INTEL_PCI_VENDOR_ID: &str = "0x8086";
+
+// Name of the hwmon interfaces exposed by the Intel discrete GPU drivers
+const HWMON_NAMES: [&str; 2] = ["i915", "xe"];
+
+// Lowest power limit accepted when the hwmon doesn't report one, in mW
+const DEFAULT_MIN_POWER_LIMIT: u32 = 10_000;
+
+pub struct IntelDevice {
+    // Store the sysfs device and hwmon directories
+    device_path: PathBuf,
+    hwmon_path: PathBuf,
+
+    // Hwmon channel used for the power and energy attributes
+    power_channel: u32,
+
+    // Store the GPU unique identifier
+    uuid: String,
+
+    // Store the device generic and vendor specific informations
+    gpu_info: GpuInfo,
+    gpu_vendor_info: GpuVendorInfo,
+
+    // Store the device generic and vendor specific data
+    gpu_data: GpuData,
+    gpu_vendor_data: GpuVendorData,
+
+    // Data update interval
+    gpu_data_update_interval: Duration,
+    // Instant of the last data update
+    gpu_data_last_update: Instant,
+
+    // Energy counter value and read instant of the last data update,
+    // the power usage is computed from the energy consumed since then
+    last_energy: Option<(u64, Instant)>,
+
+    // Settings of the device before the daemon changed them
+    original_config: GpuConfig,
+}
+
+impl IntelDevice {
+    // Create an Intel device from its sysfs device directory,
+    // usually "/sys/class/drm/cardN/device"
+    pub fn new(device_path: &Path) -> Result<Self> {
+        let hwmon_path = sysfs::find_hwmon(device_path).ok_or_else(|| {
+            DeviceError::Initialization {
+                reason: format!(
+                    "Failed to find hwmon directory for \"{}\"",
+                    device_path.display()
+                ),
+                error: anyhow!("No hwmon directory"),
+            }
+        })?;
+
+        let driver =
+            sysfs::read_string(&hwmon_path.join("name")).map_err(|e| {
+                DeviceError::Initialization {
+                    reason: format!(
+                        "Failed to retrive hwmon name for \"{}\"",
+                        device_path.display()
+                    ),
+                    error: e.into(),
+                }
+            })?;
+
+        let uuid = Self::get_uuid(device_path);
+
+        // The xe driver expose the card power on channel 1 and the
+        // package power on channel 2, use whatever is available
+        let power_channel = if hwmon_path.join("power1_max").exists() {
+            1
+        } else {
+            2
+        };
+
+        // Obtain the device informations
+        let gpu_info =
+            Self::get_gpu_info(&uuid, device_path, &hwmon_path, power_channel)
+                .map_err(|e| DeviceError::Initialization {
+                    reason: format!(
+                        "Failed to retrive GPU info for \"{}\"",
+                        uuid
+                    ),
+                    error: e.into(),
+                })?;
+
+        let gpu_vendor_info = Self::get_gpu_vendor_info(driver, device_path)
+            .map_err(|e| DeviceError::Initialization {
+                reason: format!(
+                    "Failed to retrive GPU vendor info for \"{}\"",
+                    uuid
+                ),
+                error: e.into(),
+            })?;
+
+        // Obtain the initialization general and vendor specific data
+        let gpu_data =
+            Self::get_gpu_data(device_path, &hwmon_path, power_channel, None);
+        let gpu_vendor_data =
+            Self::get_gpu_vendor_data(&hwmon_path, power_channel);
+
+        let last_energy = read_energy(&hwmon_path, power_channel)
+            .map(|energy| (energy, Instant::now()));
+
+        // Snapshot the power limit, restored on reset
+        let original_config = GpuConfig {
+            power_limit: gpu_data
+                .power_limit
+                .filter(|_| gpu_info.capabilities.power_limit),
+            ..Default::default()
+        };
+
+        Ok(Self {
+            device_path: device_path.to_path_buf(),
+            hwmon_path,
+
+            power_channel,
+
+            uuid,
+
+            gpu_info,
+            gpu_vendor_info,
+
+            gpu_data,
+            gpu_vendor_data,
+
+            gpu_data_update_interval: DEFAULT_DATA_UPDATE_INTERVAL,
+            gpu_data_last_update: Instant::now(),
+
+            last_energy,
+
+            original_config,
+        })
+    }
+
+    // Return true if the given sysfs device directory belong to an
+    // Intel GPU driven by the i915 or xe driver with a hwmon interface
+    pub fn is_intel_device(device_path: &Path) -> bool {
+        let is_intel = sysfs::read_string(&device_path.join("vendor"))
+            .is_ok_and(|vendor| vendor == INTEL_PCI_VENDOR_ID);
+
+        is_intel
+            && sysfs::find_hwmon(device_path)
+                .and_then(|hwmon| sysfs::read_string(&hwmon.join("name")).ok())
+                .is_some_and(|name| HWMON_NAMES.contains(&name.as_str()))
+    }
+
+    // Intel GPUs don't have an UUID, use the PCI slot instead
+    // and fall back to the card name when it isn't known
+    pub fn get_uuid(device_path: &Path) -> String {
+        let slot = sysfs::pci_slot_name(device_path).unwrap_or_else(|| {
+            device_path
+                .parent()
+                .and_then(|card| card.file_name())
+                .map(|card| card.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+
+        format!("INTEL-{}", slot)
+    }
+
+    fn get_gpu_info(
+        uuid: &str,
+        device_path: &Path,
+        hwmon_path: &Path,
+        power_channel: u32,
+    ) -> Result<GpuInfo> {
+        let device_id = sysfs::read_string(&device_path.join("device"))?;
+
+        // The rated power is the TDP of the card, fall back to the
+        // current power limit when the card doesn't expose it
+        let power_rated = sysfs::read_milliwatts(
+            &hwmon_path.join(format!("power{}_rated_max", power_channel)),
+        )
+        .or_else(|| {
+            sysfs::read_milliwatts(
+                &hwmon_path.join(format!("power{}_max", power_channel)),
+            )
+        })
+        .unwrap_or(0);
+        let power_min = sysfs::read_milliwatts(
+            &hwmon_path.join(format!("power{}_min", power_channel)),
+        )
+        .unwrap_or(DEFAULT_MIN_POWER_LIMIT);
+
+        Ok(GpuInfo {
+            uuid: uuid.to_string(),
+            name: format!("Intel Graphics ({})", device_id),
+            pcie_width: sysfs::read_optional(
+                &device_path.join("current_link_width"),
+            )
+            .unwrap_or(0),
+            pcie_gen: 0,
+            power_limit_max: power_rated,
+            power_limit_min: power_min.min(power_rated),
+            power_limit_default: power_rated,
+
+            // The fans are read only, the firmware decide their speed
+            fan_count: read_fans(hwmon_path).len() as u32,
+            fan_speed_min: 0,
+            fan_speed_max: 100,
+
+            capabilities: Self::get_capabilities(hwmon_path, power_channel),
+        })
+    }
+
+    // Detect the features supported by the device from
+    // the hwmon attributes it expose
+    fn get_capabilities(
+        hwmon_path: &Path,
+        power_channel: u32,
+    ) -> GpuCapabilities {
+        GpuCapabilities {
+            // The hwmon interface doesn't allow fan control
+            fan_control: false,
+            fan_rpm: !read_fans(hwmon_path).is_empty(),
+
+            clock_offsets: false,
+
+            power_limit: hwmon_path
+                .join(format!("power{}_max", power_channel))
+                .exists(),
+            power_usage: read_energy(hwmon_path, power_channel).is_some(),
+
+            mem_temp: false,
+        }
+    }
+
+    fn get_gpu_vendor_info(
+        driver: String,
+        device_path: &Path,
+    ) -> Result<GpuVendorInfo> {
+        Ok(GpuVendorInfo::Intel {
+            driver,
+            device_id: sysfs::read_string(&device_path.join("device"))?,
+        })
+    }
+
+    fn get_gpu_data(
+        device_path: &Path,
+        hwmon_path: &Path,
+        power_channel: u32,
+        power_usage: Option<u32>,
+    ) -> GpuData {
+        GpuData {
+            temp_gpu: find_package_temp(hwmon_path),
+            temp_sensor_failures: 0,
+            temp_gpu_filtered: None,
+
+            graphics_freq: read_graphics_freq(device_path),
+            mem_freq: None,
+
+            core_clock_offset: None,
+            mem_clock_offset: None,
+
+            power_usage,
+            power_limit: sysfs::read_milliwatts(
+                &hwmon_path.join(format!("power{}_max", power_channel)),
+            ),
+
+            fans: read_fans(hwmon_path),
+
+            core_usage: None,
+            mem_usage: None,
+
+            total_memory: None,
+            used_memory: None,
+            free_memory: None,
+        }
+    }
+
+    fn get_gpu_vendor_data(
+        hwmon_path: &Path,
+        power_channel: u32,
+    ) -> GpuVendorData {
+        GpuVendorData::Intel {
+            energy: read_energy(hwmon_path, power_channel),
+            core_voltage: sysfs::read_optional(&hwmon_path.join("in0_input")),
+        }
+    }
+
+    // Update the device data only if GPU update interval has elapsed
+    fn udpate_data(&mut self) -> Result<()> {
+        let time_elapsed = self.gpu_data_last_update.elapsed();
+
+        if time_elapsed >= self.gpu_data_update_interval {
+            let power_usage = self.get_power_usage();
+
+            self.gpu_data = Self::get_gpu_data(
+                &self.device_path,
+                &self.hwmon_path,
+                self.power_channel,
+                power_usage,
+            );
+            self.gpu_vendor_data =
+                Self::get_gpu_vendor_data(&self.hwmon_path, self.power_channel);
+
+            self.gpu_data_last_update = Instant::now();
+        }
+
+        Ok(())
+    }
+
+    // Compute the power usage in milliwatts from the energy
+    // consumed since the last call, None if no energy counter is exposed
+    fn get_power_usage(&mut self) -> Option<u32> {
+        let energy = read_energy(&self.hwmon_path, self.power_channel)?;
+        let now = Instant::now();
+
+        let power_usage = match self.last_energy {
+            Some((last_energy, last_read)) if energy >= last_energy => {
+                let elapsed = now.duration_since(last_read).as_secs_f64();
+
+                if elapsed > 0.0 {
+                    // Microjoules per second to milliwatts
+                    Some(
+                        ((energy - last_energy) as f64 / elapsed / 1000.0)
+                            as u32,
+                    )
+                } else {
+                    self.gpu_data.power_usage
+                }
+            }
+            // The counter wrapped around or was never read
+            _ => self.gpu_data.power_usage,
+        };
+
+        self.last_energy = Some((energy, now));
+
+        power_usage
+    }
+}
+
+impl GpuDevice for IntelDevice {
+    // Return the device vendor
+    fn get_vendor(&self) -> GpuVendor {
+        GpuVendor::Intel
+    }
+
+    // The Intel hwmon interface doesn't allow fan control,
+    // the fan curve is ignored
+    fn set_fan_curve(
+        &mut self,
+        _fans: FanSelection,
+        _fan_curve: Box<dyn FanCurve>,
+    ) -> Result<()> {
+        debug!("Ignoring fan curve for device \"{}\"", self.uuid);
+
+        Ok(())
+    }
+    // The fans are always controlled by the driver
+    fn get_fan_curves(&self) -> Vec<FanCurveInfo> {
+        Vec::new()
+    }
+    // Only the automatic fan mode is supported
+    fn set_fan_mode(
+        &mut self,
+        _fans: FanSelection,
+        fan_mode: FanMode,
+    ) -> Result<()> {
+        match fan_mode {
+            FanMode::Auto => Ok(()),
+            _ => check_unsupported(&self.uuid, &["Fan control"]),
+        }
+    }
+    // The fans are always controlled by the firmware
+    fn update_fan(&mut self) -> Result<()> {
+        debug!("Updating fan: Mode Auto");
+
+        Ok(())
+    }
+    // The fans are always controlled by the firmware
+    fn set_fan_update_interval(&mut self, _update_interval: Duration) {}
+
+    // The fans don't follow a curve,
+    // the sensor fault policy is never applied
+    fn set_sensor_fault_policy(&mut self, _policy: SensorFaultPolicy) {}
+
+    // The fans can't be controlled, the firmware
+    // is left in charge of them
+    fn set_fan_failsafe(&mut self, _engaged: bool) -> Result<()> {
+        Ok(())
+    }
+
+    // Return the device vendor specific information
+    fn get_vendor_info(&self) -> GpuVendorInfo {
+        self.gpu_vendor_info.clone()
+    }
+    // Return the device general information
+    fn get_info(&self) -> GpuInfo {
+        self.gpu_info.clone()
+    }
+
+    // Return the device vendor specific real time data,
+    // the update frequency is controlled by the set_update_freq function,
+    // the default update frequency is 1 hertz
+    fn get_vendor_data(&mut self) -> Result<GpuVendorData> {
+        self.udpate_data()?;
+        Ok(self.gpu_vendor_data.clone())
+    }
+    // Return the device general real time data
+    // the update frequency is controlled by the set_update_freq function,
+    // the default update frequency is 1 hertz
+    fn get_data(&mut self) -> Result<GpuData> {
+        self.udpate_data()?;
+        Ok(self.gpu_data.clone())
+    }
+    // Change the vendor and general data update frequency
+    fn set_data_update_interval(&mut self, update_interval: Duration) {
+        self.gpu_data_update_interval = update_interval;
+    }
+
+    // Apply the given GPU configuration to the device
+    // The configuration vendor must match the
+    fn apply_gpu_config(&mut self, gpu_config: GpuConfig) -> Result<()> {
+        let mut unsupported = Vec::new();
+
+        // The settings missing from the configuration
+        // are restored to their original value
+        let gpu_config = gpu_config.or(&self.original_config);
+
+        // Set the power limit, the hwmon power limit is in microwatts
+        if let Some(power_limit) = gpu_config.power_limit {
+            if !self.gpu_info.capabilities.power_limit {
+                unsupported.push("Power limit");
+            } else if power_limit > self.gpu_info.power_limit_max {
+                warn!(
+                    "requested power limit is beyond max ({}), ingoring it",
+                    self.gpu_info.power_limit_max
+                );
+            } else if power_limit < self.gpu_info.power_limit_min {
+                warn!(
+                    "requested power limit is bellow min ({}), ingoring it",
+                    self.gpu_info.power_limit_min
+                );
+            } else {
+                sysfs::write_value(
+                    &self
+                        .hwmon_path
+                        .join(format!("power{}_max", self.power_channel)),
+                    power_limit as u64 * 1000,
+                )?;
+            }
+        }
+
+        check_unsupported(&self.uuid, &unsupported)
+    }
+
+    // Return the settings the device had before
+    // the daemon changed them
+    fn get_original_settings(&self) -> OriginalSettings {
+        OriginalSettings {
+            fan_modes: vec![FanMode::Auto; self.gpu_info.fan_count as usize],
+            config: self.original_config.clone(),
+            ..Default::default()
+        }
+    }
+    // Replace the settings restored by reset,
+    // the fans are always automatic
+    fn set_original_settings(&mut self, settings: OriginalSettings) {
+        self.original_config = settings.config;
+    }
+
+    // Restore the settings the device had before the daemon
+    // started, the fans are always automatic
+    fn reset(&mut self) -> Result<()> {
+        self.apply_gpu_config(GpuConfig::default())
+    }
+
+    // The sysfs device directory disappear when the GPU is unplugged
+    fn is_available(&self) -> bool {
+        self.device_path.join("vendor").exists()
+    }
+}
+
+// Return the package temperature in degrees Celsius, the xe driver
+// label it "pkg", fall back to the first temperature channel
+fn find_package_temp(hwmon_path: &Path) -> Option<u32> {
+    let channel = (1..=4)
+        .find(|i| {
+            sysfs::read_string(&hwmon_path.join(format!("temp{}_label", i)))
+                .is_ok_and(|label| label == "pkg")
+        })
+        .unwrap_or(1);
+
+    sysfs::read_optional::<i64>(
+        &hwmon_path.join(format!("temp{}_input", channel)),
+    )
+    .map(|temp| (temp / 1000).max(0) as u32)
+}
+
+// Read the current graphics frequency in megahertz, the i915 driver
+// expose it on the card directory and the xe driver on the GT directory
+fn read_graphics_freq(device_path: &Path) -> Option<u32> {
+    device_path
+        .parent()
+        .and_then(|card| sysfs::read_optional(&card.join("gt_cur_freq_mhz")))
+        .or_else(|| {
+            sysfs::read_optional(&device_path.join("tile0/gt0/freq0/cur_freq"))
+        })
+}
+
+// Read the energy counter of the given channel in microjoules
+fn read_energy(hwmon_path: &Path, channel: u32) -> Option<u64> {
+    sysfs::read_optional(&hwmon_path.join(format!("energy{}_input", channel)))
+}
+
+// Read the speed of every fan reported by the hwmon interface,
+// the fans are read only so only the RPM speed is known
+fn read_fans(hwmon_path: &Path) -> Vec<FanData> {
+    (1..)
+        .map_while(|i| {
+            sysfs::read_optional(&hwmon_path.join(format!("fan{}_input", i)))
+        })
+        .map(|speed_rpm| FanData {
+            speed: None,
+            speed_rpm: Some(speed_rpm),
+        })
+        .collect()
+}
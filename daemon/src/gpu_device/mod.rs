@@ -2,18 +2,37 @@ pub mod gpu_config;
 pub mod gpu_data;
 pub mod gpu_info;
 
+pub mod fan_monitor;
+pub mod sensor_fault;
+pub mod sysfs;
+pub mod thermal_failsafe;
+
+pub mod nvml_backend;
+#[cfg(any(test, feature = "fake-nvml"))]
+pub mod fake_nvml_backend;
+
+pub mod amd_device;
+pub mod intel_device;
 pub mod nvidia_device;
+pub mod simulated_device;
 
 use std::time::Duration;
 
+use anyhow::anyhow;
 use thiserror::Error;
+use tracing::warn;
 
 use crate::{
-    fan_curve::{FanCurve, fan_mode::FanMode},
+    fan_curve::{
+        CurveInput, FanCurve,
+        fan_curve_info::FanCurveInfo,
+        fan_mode::{FanMode, FanSelection},
+    },
     gpu_device::{
-        gpu_config::GpuConfig,
+        gpu_config::{GpuConfig, OriginalSettings},
         gpu_data::{GpuData, GpuVendorData},
         gpu_info::{GpuInfo, GpuVendorInfo},
+        sensor_fault::{SensorFaultHandler, SensorFaultPolicy},
     },
 };
 
@@ -45,12 +64,18 @@ pub enum DeviceError {
     DeviceFanError {
         reason: String,
         error: anyhow::Error,
-    }
+    },
+    #[error("Device unsupported feature error: {reason}")]
+    Unsupported { reason: String },
+    #[error("Device sensor fault error: {reason}")]
+    SensorFault { reason: String },
 }
 
 pub enum GpuVendor {
     Nvidia,
     AMD,
+    Intel,
+    Simulated,
 }
 
 // GPU device trait
@@ -58,14 +83,41 @@ pub trait GpuDevice {
     // Return the device vendor
     fn get_vendor(&self) -> GpuVendor;
 
-    // Set the device fan curve, this does not automatically
-    // set the fan mode to curve
-    fn set_fan_curve(&mut self, fan_curve: Box<dyn FanCurve + Send>);
-    // Set the device fan mode, if no fan curve was previously set
-    // default to a 100% fan speed curve
-    fn set_fan_mode(&mut self, fan_mode: FanMode) -> Result<()>;
-    // Update the fan speed according to the mode and the fan curve
+    // Set the fan curve of the selected fans, this does not
+    // automatically set the fan mode to curve
+    // A curve assigned to all the fans replace the per fan curves
+    // The state of the fans controlled by the new curve is reset
+    fn set_fan_curve(
+        &mut self,
+        fans: FanSelection,
+        fan_curve: Box<dyn FanCurve>,
+    ) -> Result<()>;
+    // Return the info of the fan curve of every fan, whatever its
+    // fan mode, empty if the device doesn't allow fan control
+    fn get_fan_curves(&self) -> Vec<FanCurveInfo>;
+    // Set the fan mode of the selected fans, if no fan curve was
+    // previously set default to a 100% fan speed curve
+    // Return an unsupported error if the device doesn't
+    // allow fan control and the mode is not automatic
+    fn set_fan_mode(
+        &mut self,
+        fans: FanSelection,
+        fan_mode: FanMode,
+    ) -> Result<()>;
+    // Update the fans speed according to their mode and fan curve
+    // Return a sensor fault error once the temperature failed to be
+    // read several times in a row, the fan speed is still updated
     fn update_fan(&mut self) -> Result<()>;
+    // Change the time between two fan updates, used by the fan
+    // curves depending on time before their first update
+    fn set_fan_update_interval(&mut self, update_interval: Duration);
+    // Change the policy applied to the fans in curve mode
+    // when the temperature sensor can't be read
+    fn set_sensor_fault_policy(&mut self, policy: SensorFaultPolicy);
+    // Force every fan to full speed regardless of its mode,
+    // the fan modes are applied again once disengaged
+    // Devices without fan control ignore the failsafe
+    fn set_fan_failsafe(&mut self, engaged: bool) -> Result<()>;
 
     // Return the device vendor specific information
     fn get_vendor_info(&self) -> GpuVendorInfo;
@@ -85,5 +137,108 @@ pub trait GpuDevice {
 
     // Apply the given GPU configuration to the device
     // The configuration vendor must match the
+    // The supported settings are applied even if some are not,
+    // in that case an unsupported error is returned
+    // The settings missing from the configuration are
+    // restored to their value before the daemon started
     fn apply_gpu_config(&mut self, gpu_config: GpuConfig) -> Result<()>;
+    // Return the fan modes and settings the device
+    // had before the daemon changed them
+    fn get_original_settings(&self) -> OriginalSettings;
+    // Replace the settings restored by reset, used to restore the
+    // settings recorded by a daemon instance that didn't exit cleanly
+    fn set_original_settings(&mut self, settings: OriginalSettings);
+    // Restore the fan mode and the settings the
+    // device had before the daemon started
+    fn reset(&mut self) -> Result<()>;
+
+    // Return false if the device is no longer reachable,
+    // e.g. it was unplugged or fell off the bus
+    fn is_available(&self) -> bool;
+}
+
+// Return the fan indices of the given selection, fail with a
+// fan error if the selection is out of the device fan range
+pub fn select_fans(
+    uuid: &str,
+    fans: FanSelection,
+    fan_count: u32,
+) -> Result<Vec<u32>> {
+    fans.indices(fan_count).ok_or_else(|| DeviceError::DeviceFanError {
+        reason: format!(
+            "Invalid fan selection {:?} for device \"{}\" ({} fans)",
+            fans, uuid, fan_count
+        ),
+        error: anyhow!("Fan index out of range"),
+    })
+}
+
+// Clamp the given fan speed to the range supported by the hardware,
+// the warning is only logged once until the warned flag is reset
+pub fn clamp_fan_speed(info: &GpuInfo, speed: u8, warned: &mut bool) -> u8 {
+    let min = info.fan_speed_min.min(100) as u8;
+    let max = (info.fan_speed_max.min(100) as u8).max(min);
+
+    let clamped = speed.clamp(min, max);
+
+    if clamped != speed && !*warned {
+        warn!(
+            "Fan speed {}% is outside the range supported by \"{}\" \
+            ({}% - {}%), using {}% instead",
+            speed, info.uuid, min, max, clamped
+        );
+
+        *warned = true;
+    }
+
+    clamped
+}
+
+// Return the fan curves input for the given temperature
+pub fn curve_input(
+    info: &GpuInfo,
+    temp: i32,
+    update_interval: Duration,
+) -> CurveInput {
+    CurveInput {
+        temp,
+        min_speed: info.fan_speed_min.min(100) as u8,
+        update_interval,
+    }
+}
+
+// Return an unsupported error listing the given features,
+// or Ok if the list is empty
+pub fn check_unsupported(uuid: &str, features: &[&str]) -> Result<()> {
+    if features.is_empty() {
+        return Ok(());
+    }
+
+    Err(DeviceError::Unsupported {
+        reason: format!(
+            "{} not supported by device \"{}\"",
+            features.join(", "),
+            uuid
+        ),
+    })
+}
+
+// Return a sensor fault error if the consecutive failed
+// temperature reads just reached the report threshold
+pub fn check_sensor_fault(
+    uuid: &str,
+    sensor_fault: &SensorFaultHandler,
+) -> Result<()> {
+    if !sensor_fault.should_report() {
+        return Ok(());
+    }
+
+    Err(DeviceError::SensorFault {
+        reason: format!(
+            "Failed to read the temperature of device \"{}\" {} times \
+            in a row, applying the sensor fault policy",
+            uuid,
+            sensor_fault.failures()
+        ),
+    })
 }
@@ -1,10 +1,6 @@
-use std::{
-    sync::Arc,
-    time::{Duration, Instant},
-};
+use std::time::{Duration, Instant};
 
 use nvml_wrapper::{
-    Device, Nvml,
     enum_wrappers::device::{
         Clock, ClockId, TemperatureSensor, TemperatureThreshold,
     },
@@ -14,19 +10,41 @@ use nvml_wrapper::{
 use tracing::{debug, warn};
 
 use crate::{
-    fan_curve::{FanCurve, fan_mode::FanMode, linear_curve::LinearCurve},
+    fan_curve::{
+        CurveState, FanCurve,
+        fan_curve_info::FanCurveInfo,
+        fan_mode::{FanMode, FanSelection},
+        linear_curve::LinearCurve,
+    },
     gpu_device::{
-        DEFAULT_DATA_UPDATE_INTERVAL, DeviceError, GpuDevice, GpuVendor,
-        Result,
-        gpu_config::GpuConfig,
-        gpu_data::{GpuData, GpuVendorData},
-        gpu_info::{GpuInfo, GpuVendorInfo},
+        DEFAULT_DATA_UPDATE_INTERVAL, DEFAULT_FAN_UPDATE_INTERVAL, DeviceError,
+        GpuDevice, GpuVendor, Result, check_sensor_fault, check_unsupported,
+        clamp_fan_speed, curve_input,
+        gpu_config::{GpuConfig, OriginalSettings},
+        gpu_data::{FanData, GpuData, GpuVendorData},
+        gpu_info::{GpuCapabilities, GpuInfo, GpuVendorInfo},
+        nvml_backend::NvmlBackend,
+        select_fans,
+        sensor_fault::{SensorFaultHandler, SensorFaultPolicy, SensorReading},
     },
 };
 
+// Fan control state of a single fan
+struct FanState {
+    // Store the current fan mode
+    mode: FanMode,
+    // Fan mode before the daemon took control of the fan
+    original_mode: FanMode,
+    // Fan curve assigned to this fan only, if None
+    // the device fan curve is used in curve mode
+    curve: Option<Box<dyn FanCurve>>,
+    // State of the fan controlled by its own curve
+    curve_state: CurveState,
+}
+
 pub struct NvidiaDevice {
-    // Store a reference to the NVML context
-    nvml: Arc<Nvml>,
+    // Store the NVML access for this device
+    nvml: Box<dyn NvmlBackend + Send>,
 
     // Store the GPU unique identifier
     uuid: String,
@@ -44,23 +62,38 @@ pub struct NvidiaDevice {
     // Instant of the last data update
     gpu_data_last_update: Instant,
 
-    // Store the current fan mode
-    fan_mode: FanMode,
-    // Fan curve to apply in curve mode
-    fan_curve: Box<dyn FanCurve + Send>,
+    // Store the fan mode and fan curve of each fan
+    fans: Vec<FanState>,
+    // Fan curve to apply in curve mode to the fans without their own curve
+    fan_curve: Box<dyn FanCurve>,
+    // State of the fans controlled by the device fan curve
+    fan_curve_state: CurveState,
+    // Time between two fan updates, passed to the fan curves
+    fan_update_interval: Duration,
+    // Set once a fan speed outside the supported range was reported
+    fan_speed_warned: bool,
+    // Set while the thermal failsafe force the fans to full speed
+    fan_failsafe: bool,
+    // Apply the sensor fault policy to the fans in curve mode,
+    // set while the policy gave the fans to the driver
+    sensor_fault: SensorFaultHandler,
+    sensor_auto: bool,
+    // Filtered temperature of the last fan update
+    temp_filtered: Option<f32>,
+
+    // Settings of the device before the daemon changed them
+    original_config: GpuConfig,
 }
 
 impl NvidiaDevice {
-    pub fn new(nvml: Arc<Nvml>, uuid: &str) -> Result<Self> {
-        let device = nvml.device_by_uuid(uuid).map_err(|e| {
-            DeviceError::Initialization {
-                reason: format!("Failed to retrive GPU device \"{}\"", uuid),
-                error: e.into(),
-            }
+    pub fn new(nvml: Box<dyn NvmlBackend + Send>) -> Result<Self> {
+        let uuid = nvml.uuid().map_err(|e| DeviceError::Initialization {
+            reason: "Failed to retrive GPU device uuid".to_string(),
+            error: e.into(),
         })?;
 
         // Obtain the device informations
-        let gpu_info = Self::get_gpu_info(&device).map_err(|e| {
+        let gpu_info = Self::get_gpu_info(nvml.as_ref()).map_err(|e| {
             DeviceError::Initialization {
                 reason: format!("Failed to retrive GPU info for \"{}\"", uuid),
                 error: e.into(),
@@ -78,7 +111,7 @@ impl NvidiaDevice {
         })?;
 
         let gpu_vendor_info =
-            Self::get_gpu_vendor_info(driver_version, &device).map_err(
+            Self::get_gpu_vendor_info(driver_version, nvml.as_ref()).map_err(
                 |e| DeviceError::Initialization {
                     reason: format!(
                         "Failed to retrive GPU vendor info for \"{}\"",
@@ -89,52 +122,76 @@ impl NvidiaDevice {
             )?;
 
         // Obtain the initialization general and vendor specific data
-        let gpu_data = Self::get_gpu_data(&device).map_err(|e| {
+        let gpu_data = Self::get_gpu_data(nvml.as_ref()).map_err(|e| {
             DeviceError::Initialization {
                 reason: format!("Failed to retrive GPU data for \"{}\"", uuid),
                 error: e.into(),
             }
         })?;
 
-        let gpu_vendor_data =
-            Self::get_gpu_vendor_data(&device).map_err(|e| {
-                DeviceError::Initialization {
-                    reason: format!(
-                        "Failed to retrive GPU vendor data for \"{}\"",
-                        uuid
-                    ),
-                    error: e.into(),
-                }
-            })?;
-
-        // Determine the current fan mode
-        // We can't just assume it is automatic, if an old instance of
-        // the program changed it and crashed if could still be manual
-        // TODO: Handle multiple fan
-        let control_policy = device.fan_control_policy(0).map_err(|e| {
-            DeviceError::Initialization {
+        let gpu_vendor_data = Self::get_gpu_vendor_data(nvml.as_ref())
+            .map_err(|e| DeviceError::Initialization {
                 reason: format!(
-                    "Failed to retrive fan control policy for \"{}\"",
+                    "Failed to retrive GPU vendor data for \"{}\"",
                     uuid
                 ),
                 error: e.into(),
-            }
-        })?;
+            })?;
+
+        // Determine the current mode of each fan
+        // We can't just assume it is automatic, if an old instance of
+        // the program changed it and crashed if could still be manual
+        // Fans that can't be controlled are always automatic
+        let mut fans = Vec::new();
 
-        let fan_mode =
-            if control_policy == FanControlPolicy::TemperatureContinousSw {
-                FanMode::Auto
+        for i in 0..gpu_info.fan_count {
+            let (mode, original_mode) = if !gpu_info.capabilities.fan_control {
+                (FanMode::Auto, FanMode::Auto)
             } else {
-                FanMode::Curve
+                let control_policy =
+                    nvml.fan_control_policy(i).map_err(|e| {
+                        DeviceError::Initialization {
+                            reason: format!(
+                                "Failed to retrive fan {} control policy for \"{}\"",
+                                i, uuid
+                            ),
+                            error: e.into(),
+                        }
+                    })?;
+
+                if control_policy == FanControlPolicy::TemperatureContinousSw {
+                    (FanMode::Auto, FanMode::Auto)
+                } else {
+                    // The fan is given back to the driver on restore, a
+                    // fixed speed without a controller isn't safe
+                    (FanMode::Curve, FanMode::Auto)
+                }
             };
 
+            fans.push(FanState {
+                mode,
+                original_mode,
+                curve: None,
+                curve_state: CurveState::default(),
+            });
+        }
+
+        // Snapshot the supported settings, restored on reset
+        let original_config = Self::get_original_config(
+            nvml.as_ref(),
+            &gpu_info,
+        )
+        .map_err(|e| DeviceError::Initialization {
+            reason: format!("Failed to retrive GPU settings for \"{}\"", uuid),
+            error: e.into(),
+        })?;
+
         // Generate a default fan curve always at 100% fan speed
-        let mut fan_curve = Box::new(LinearCurve::new(&Vec::new()));
-        fan_curve.add_point((0, 100).into());
+        let fan_curve = Box::new(LinearCurve::new(&[(0, 100)]));
 
         Ok(Self {
-            nvml: nvml.clone(),
-            uuid: uuid.to_string(),
+            nvml,
+            uuid,
 
             gpu_info,
             gpu_vendor_info,
@@ -145,47 +202,125 @@ impl NvidiaDevice {
             gpu_data_update_interval: DEFAULT_DATA_UPDATE_INTERVAL,
             gpu_data_last_update: Instant::now(),
 
-            fan_mode,
+            fans,
             fan_curve,
+            fan_curve_state: CurveState::default(),
+            fan_update_interval: DEFAULT_FAN_UPDATE_INTERVAL,
+            fan_speed_warned: false,
+            fan_failsafe: false,
+            sensor_fault: SensorFaultHandler::default(),
+            sensor_auto: false,
+            temp_filtered: None,
+
+            original_config,
         })
     }
 
-    // Return a NVML device handle.
-    // This function can fail and return an error
-    fn get_device<'a>(&'a self) -> Result<Device<'a>> {
-        let uuid = self.uuid.as_str();
+    // Read the current value of the supported settings
+    fn get_original_config(
+        device: &dyn NvmlBackend,
+        gpu_info: &GpuInfo,
+    ) -> Result<GpuConfig> {
+        let mut config = GpuConfig::default();
 
-        self.nvml.device_by_uuid(uuid).map_err(|e| {
-            DeviceError::DeviceAcquisition {
-                reason: format!("Failed to retrive GPU device \"{}\"", uuid),
-                error: e.into(),
-            }
-        })
+        if gpu_info.capabilities.power_limit {
+            config.power_limit = Some(device.power_management_limit()?);
+        }
+        if gpu_info.capabilities.clock_offsets {
+            config.nvidia_config.core_clock_offset =
+                Some(device.gpc_clock_vf_offset()?);
+            config.nvidia_config.mem_clock_offset =
+                Some(device.mem_clock_vf_offset()?);
+        }
+
+        Ok(config)
     }
 
-    fn get_gpu_info<'a, 'b>(device: &'a Device<'b>) -> Result<GpuInfo> {
+    fn get_gpu_info(device: &dyn NvmlBackend) -> Result<GpuInfo> {
+        let capabilities = Self::get_capabilities(device)?;
+
+        // Report a zero power limit range if the power limit can't be set
         let power_limit_constraints =
-            device.power_management_limit_constraints()?;
+            Self::ok_support(device.power_management_limit_constraints())?;
+        let power_limit_default =
+            Self::ok_support(device.power_management_limit_default())?;
+
+        // Not every board report the supported fan speed range,
+        // in that case assume the full range is supported
+        let fan_count = Self::ok_support(device.num_fans())?.unwrap_or(0);
+        let (fan_speed_min, fan_speed_max) =
+            Self::ok_support(device.min_max_fan_speed())?.unwrap_or((0, 100));
 
         Ok(GpuInfo {
             uuid: device.uuid()?,
             name: device.name()?,
-            pcie_width: device.current_pcie_link_width()?,
-            pcie_gen: device.current_pcie_link_gen()?,
-            power_limit_max: power_limit_constraints.max_limit,
-            power_limit_min: power_limit_constraints.min_limit,
-            power_limit_default: device.power_management_limit_default()?,
+            pcie_width: Self::ok_support(device.current_pcie_link_width())?
+                .unwrap_or(0),
+            pcie_gen: Self::ok_support(device.current_pcie_link_gen())?
+                .unwrap_or(0),
+            power_limit_max: power_limit_constraints
+                .as_ref()
+                .map_or(0, |c| c.max_limit),
+            power_limit_min: power_limit_constraints
+                .as_ref()
+                .map_or(0, |c| c.min_limit),
+            power_limit_default: power_limit_default.unwrap_or(0),
+
+            fan_count,
+            fan_speed_min,
+            fan_speed_max,
+
+            capabilities,
+        })
+    }
+
+    // Detect the features supported by the device, the
+    // non supported queries are used to detect missing features
+    fn get_capabilities(device: &dyn NvmlBackend) -> Result<GpuCapabilities> {
+        let fan_count = Self::ok_support(device.num_fans())?.unwrap_or(0);
+
+        // The fans can only be controlled if the
+        // control policy of every fan is available
+        let mut fan_control = fan_count > 0;
+
+        for i in 0..fan_count {
+            if Self::ok_support(device.fan_control_policy(i))?.is_none() {
+                fan_control = false;
+            }
+        }
+
+        let fan_rpm = fan_count > 0
+            && Self::ok_support(device.fan_speed_rpm(0))?.is_some();
+
+        let clock_offsets = Self::ok_support(device.gpc_clock_vf_offset())?
+            .is_some()
+            && Self::ok_support(device.mem_clock_vf_offset())?.is_some();
+
+        Ok(GpuCapabilities {
+            fan_control,
+            fan_rpm,
+
+            clock_offsets,
+
+            power_limit: Self::ok_support(
+                device.power_management_limit_constraints(),
+            )?
+            .is_some(),
+            power_usage: Self::ok_support(device.power_usage())?.is_some(),
+
+            // NVML doesn't expose the memory temperature
+            mem_temp: false,
         })
     }
 
-    fn get_gpu_vendor_info<'a, 'b>(
+    fn get_gpu_vendor_info(
         driver_version: String,
-        device: &'a Device<'b>,
+        device: &dyn NvmlBackend,
     ) -> Result<GpuVendorInfo> {
         Ok(GpuVendorInfo::Nvidia {
             driver_version: driver_version,
             vbios: device.vbios_version()?,
-            cuda_core_count: device.num_cores()?,
+            cuda_core_count: Self::ok_support(device.num_cores())?.unwrap_or(0),
             max_temp: Self::ok_support(
                 device.temperature_threshold(TemperatureThreshold::GpuMax),
             )?,
@@ -201,55 +336,71 @@ impl NvidiaDevice {
         })
     }
 
-    fn get_gpu_data<'a, 'b>(device: &'a Device<'b>) -> Result<GpuData> {
-        // Get the fan speed data
-        // TODO: Handle multiples fans
-        let mut fan_speed = 0;
-        let mut fan_speed_rpm = 0;
+    fn get_gpu_data(device: &dyn NvmlBackend) -> Result<GpuData> {
+        // Get the fan speed data of every fan
+        let mut fans = Vec::new();
 
-        if device.num_fans()? > 0 {
-            fan_speed = device.fan_speed(0)?;
-            fan_speed_rpm = device.fan_speed_rpm(0)?;
+        for i in 0..Self::ok_support(device.num_fans())?.unwrap_or(0) {
+            fans.push(FanData {
+                speed: Self::ok_support(device.fan_speed(i))?,
+                speed_rpm: Self::ok_support(device.fan_speed_rpm(i))?,
+            });
         }
 
         // Get the core and memory usage data
-        let mut core_usage = 0;
-        let mut mem_usage = 0;
+        let mut core_usage = None;
+        let mut mem_usage = None;
 
-        if let Ok(utilization) = device.utilization_rates() {
-            core_usage = utilization.gpu;
-            mem_usage = utilization.memory;
-        } else {
-            warn!("Failed to fetch GPU utilization info");
+        match device.utilization_rates() {
+            Ok(utilization) => {
+                core_usage = Some(utilization.gpu);
+                mem_usage = Some(utilization.memory);
+            }
+            Err(NvmlError::NotSupported) => (),
+            Err(_) => warn!("Failed to fetch GPU utilization info"),
         }
 
         // Get the memory usage data
-        let mut total_memory = 0;
-        let mut used_memory = 0;
-        let mut free_memory = 0;
-
-        if let Ok(mem_info) = device.memory_info() {
-            total_memory = mem_info.total;
-            used_memory = mem_info.used;
-            free_memory = mem_info.free;
-        } else {
-            warn!("Failed to fetch GPU memory info");
+        let mut total_memory = None;
+        let mut used_memory = None;
+        let mut free_memory = None;
+
+        match device.memory_info() {
+            Ok(mem_info) => {
+                total_memory = Some(mem_info.total);
+                used_memory = Some(mem_info.used);
+                free_memory = Some(mem_info.free);
+            }
+            Err(NvmlError::NotSupported) => (),
+            Err(_) => warn!("Failed to fetch GPU memory info"),
         }
 
+        // A failed temperature read is a sensor fault unless the GPU is lost
+        let temp_gpu = match device.temperature(TemperatureSensor::Gpu) {
+            Ok(temp) => Some(temp),
+            Err(NvmlError::GpuLost) => return Err(NvmlError::GpuLost.into()),
+            Err(_) => None,
+        };
+
         Ok(GpuData {
-            temp_gpu: device.temperature(TemperatureSensor::Gpu)?,
+            temp_gpu,
+            temp_sensor_failures: 0,
+            temp_gpu_filtered: None,
 
-            graphics_freq: device.clock(Clock::Graphics, ClockId::Current)?,
-            mem_freq: device.clock(Clock::Memory, ClockId::Current)?,
+            graphics_freq: Self::ok_support(
+                device.clock(Clock::Graphics, ClockId::Current),
+            )?,
+            mem_freq: Self::ok_support(
+                device.clock(Clock::Memory, ClockId::Current),
+            )?,
 
-            core_clock_offset: device.gpc_clock_vf_offset()?,
-            mem_clock_offset: device.mem_clock_vf_offset()?,
+            core_clock_offset: Self::ok_support(device.gpc_clock_vf_offset())?,
+            mem_clock_offset: Self::ok_support(device.mem_clock_vf_offset())?,
 
-            power_usage: device.power_usage()?,
-            power_limit: device.power_management_limit()?,
+            power_usage: Self::ok_support(device.power_usage())?,
+            power_limit: Self::ok_support(device.power_management_limit())?,
 
-            fan_speed,
-            fan_speed_rpm,
+            fans,
 
             core_usage,
             mem_usage,
@@ -260,9 +411,7 @@ impl NvidiaDevice {
         })
     }
 
-    fn get_gpu_vendor_data<'a, 'b>(
-        device: &'a Device<'b>,
-    ) -> Result<GpuVendorData> {
+    fn get_gpu_vendor_data(device: &dyn NvmlBackend) -> Result<GpuVendorData> {
         Ok(GpuVendorData::Nvidia {
             sm_freq: Self::ok_support(
                 device.clock(Clock::SM, ClockId::Current),
@@ -291,16 +440,52 @@ impl NvidiaDevice {
         let time_elapsed = self.gpu_data_last_update.elapsed();
 
         if time_elapsed >= self.gpu_data_update_interval {
-            self.gpu_data = Self::get_gpu_data(&self.get_device()?)?;
+            self.gpu_data = Self::get_gpu_data(self.nvml.as_ref())?;
             self.gpu_vendor_data =
-                Self::get_gpu_vendor_data(&self.get_device()?)?;
+                Self::get_gpu_vendor_data(self.nvml.as_ref())?;
 
+            self.gpu_data.temp_sensor_failures = self.sensor_fault.failures();
+            self.gpu_data.temp_gpu_filtered = self.temp_filtered;
             self.gpu_data_last_update = Instant::now();
         }
 
         Ok(())
     }
 
+    // Set the control policy of the fans in curve mode to automatic
+    // while the sensor fault policy require it, and back to manual
+    fn set_sensor_auto(&mut self, auto: bool) -> Result<()> {
+        if auto == self.sensor_auto {
+            return Ok(());
+        }
+
+        let policy = if auto {
+            FanControlPolicy::TemperatureContinousSw
+        } else {
+            FanControlPolicy::Manual
+        };
+
+        for (i, fan) in self.fans.iter().enumerate() {
+            if !matches!(fan.mode, FanMode::Curve) {
+                continue;
+            }
+
+            self.nvml.set_fan_control_policy(i as u32, policy).map_err(
+                |e| DeviceError::DeviceFanError {
+                    reason: format!(
+                        "Failed to set fan {} control policy for: \"{}\"",
+                        i, self.uuid
+                    ),
+                    error: e.into(),
+                },
+            )?;
+        }
+
+        self.sensor_auto = auto;
+
+        Ok(())
+    }
+
     // If the given result is Ok(T) return Ok(Some(T))
     // If the given result is a non supported error return Ok(None)
     // If the given result is any other kind of error return Err(e)
@@ -321,100 +506,238 @@ impl GpuDevice for NvidiaDevice {
         GpuVendor::Nvidia
     }
 
-    // Set the device fan curve, this does not automatically
-    // set the fan mode to curve
-    fn set_fan_curve(&mut self, fan_curve: Box<dyn FanCurve + Send>) {
-        self.fan_curve = fan_curve;
+    // Set the fan curve of the selected fans, this does not
+    // automatically set the fan mode to curve
+    // A curve assigned to all the fans replace the per fan curves
+    fn set_fan_curve(
+        &mut self,
+        fans: FanSelection,
+        fan_curve: Box<dyn FanCurve>,
+    ) -> Result<()> {
+        self.fan_speed_warned = false;
+
+        match fans {
+            FanSelection::All => {
+                self.fan_curve = fan_curve;
+                self.fan_curve_state = CurveState::default();
+
+                for fan in self.fans.iter_mut() {
+                    fan.curve = None;
+                }
+            }
+            FanSelection::Index(index) => {
+                select_fans(&self.uuid, fans, self.gpu_info.fan_count)?;
+
+                let fan = &mut self.fans[index as usize];
+                fan.curve = Some(fan_curve);
+                fan.curve_state = CurveState::default();
+            }
+        }
+
+        Ok(())
     }
-    // Set the device fan mode, if no fan curve was previously set
-    // default to a 100% fan speed curve
-    fn set_fan_mode(&mut self, fan_mode: FanMode) -> Result<()> {
-        match fan_mode {
-            FanMode::Auto => self
-                .get_device()?
-                .set_fan_control_policy(
-                    0,
-                    FanControlPolicy::TemperatureContinousSw,
-                )
-                .map_err(|e| DeviceError::DeviceFanError {
+    // Return the info of the fan curve of every fan
+    fn get_fan_curves(&self) -> Vec<FanCurveInfo> {
+        self.fans
+            .iter()
+            .map(|fan| fan.curve.as_ref().unwrap_or(&self.fan_curve).to_info())
+            .collect()
+    }
+    // Set the fan mode of the selected fans, if no fan curve was
+    // previously set default to a 100% fan speed curve
+    fn set_fan_mode(
+        &mut self,
+        fans: FanSelection,
+        fan_mode: FanMode,
+    ) -> Result<()> {
+        // Without fan control the fans are always automatic
+        if !self.gpu_info.capabilities.fan_control {
+            return match fan_mode {
+                FanMode::Auto => Ok(()),
+                _ => check_unsupported(&self.uuid, &["Fan control"]),
+            };
+        }
+
+        let (policy, policy_name) = match fan_mode {
+            FanMode::Auto => {
+                (FanControlPolicy::TemperatureContinousSw, "automatic")
+            }
+            _ => (FanControlPolicy::Manual, "manual"),
+        };
+
+        self.fan_speed_warned = false;
+
+        // Take the fans back from the driver before changing their mode,
+        // the sensor fault policy is applied again on the next update
+        if !self.fan_failsafe {
+            self.set_sensor_auto(false)?;
+        }
+
+        for i in select_fans(&self.uuid, fans, self.gpu_info.fan_count)? {
+            // The mode is applied once the failsafe is disengaged
+            if self.fan_failsafe {
+                self.fans[i as usize].mode = fan_mode;
+                continue;
+            }
+
+            self.nvml.set_fan_control_policy(i, policy).map_err(|e| {
+                DeviceError::DeviceFanError {
                     reason: format!(
-                        "Failed to set fan mode to automatic for: \"{}\"",
-                        self.uuid
+                        "Failed to set fan {} mode to {} for: \"{}\"",
+                        i, policy_name, self.uuid
                     ),
                     error: e.into(),
-                })?,
-            _ => {
-                self.get_device()?
-                    .set_fan_control_policy(0, FanControlPolicy::Manual)
-                    .map_err(|e| DeviceError::DeviceFanError {
-                        reason: format!(
-                            "Failed to set fan mode to manual for: \"{}\"",
-                            self.uuid
-                        ),
-                        error: e.into(),
-                    })?;
+                }
+            })?;
 
-                self.update_fan()?;
-            }
+            self.fans[i as usize].mode = fan_mode;
         }
 
-        self.fan_mode = fan_mode;
+        // Apply the new fan speed right away
+        if !matches!(fan_mode, FanMode::Auto) && !self.fan_failsafe {
+            self.update_fan()?;
+        }
 
         Ok(())
     }
-    // Update the fan speed according to the mode and the fan curve
+    // Update the fans speed according to their mode and fan curve
     fn update_fan(&mut self) -> Result<()> {
-        // Get the NVML device
-        let mut device = self.get_device().map_err(|e| match e {
-            DeviceError::DeviceInternal { reason: _, error } => {
-                DeviceError::DeviceFanError {
-                    reason: format!("Device query error during fan update"),
-                    error,
+        // The temperature is only read if a fan needs it, a failed
+        // read is handled according to the sensor fault policy
+        let needs_temp = !self.fan_failsafe
+            && self
+                .fans
+                .iter()
+                .any(|fan| matches!(fan.mode, FanMode::Curve));
+
+        let reading = needs_temp.then(|| {
+            let temp = self.nvml.temperature(TemperatureSensor::Gpu).ok();
+            self.sensor_fault.read(temp.map(|temp| temp as i32))
+        });
+
+        // Give the fans in curve mode to the driver
+        // while the policy requires it
+        self.set_sensor_auto(matches!(reading, Some(SensorReading::Auto)))?;
+
+        // The device fan curve speed is only computed once
+        let mut curve_speed = None;
+        let now = Instant::now();
+
+        for (i, fan) in self.fans.iter_mut().enumerate() {
+            let fan_speed = match (fan.mode, reading) {
+                _ if self.fan_failsafe => 100,
+                (FanMode::Curve, Some(SensorReading::Temp(temp))) => {
+                    let input = curve_input(
+                        &self.gpu_info,
+                        temp,
+                        self.fan_update_interval,
+                    );
+
+                    match &fan.curve {
+                        Some(curve) => {
+                            curve.evaluate(&mut fan.curve_state, &input, now)
+                        }
+                        None => *curve_speed.get_or_insert_with(|| {
+                            self.fan_curve.evaluate(
+                                &mut self.fan_curve_state,
+                                &input,
+                                now,
+                            )
+                        }),
+                    }
                 }
-            }
-            _ => e,
-        })?;
+                (FanMode::Curve, Some(SensorReading::Duty(duty))) => duty,
+                (FanMode::Manual(speed), _) => speed,
+                _ => {
+                    debug!("Updating fan {}: Mode Auto", i);
+                    continue;
+                }
+            };
 
-        match self.fan_mode {
-            FanMode::Curve => {
-                // If the query for the temperature fail return
-                // 110 degrees for safety
-                let temp = device
-                    .temperature(TemperatureSensor::Gpu)
-                    .unwrap_or_else(|_| 110) as i32;
-                let fan_speed = self.fan_curve.get_speed(temp);
-
-                debug!("Updating fan: Mode Curve - Speed: {:?}%", fan_speed);
-
-                device.set_fan_speed(0, fan_speed as u32).map_err(|e| {
-                    DeviceError::DeviceFanError {
-                        reason: format!(
-                            "Failed to set fan speed for device \"{}\"",
-                            self.uuid
-                        ),
-                        error: e.into(),
-                    }
+            // Keep the speed in the range supported by the board
+            let fan_speed = clamp_fan_speed(
+                &self.gpu_info,
+                fan_speed,
+                &mut self.fan_speed_warned,
+            );
+
+            debug!(
+                "Updating fan {}: Mode {:?} - Speed: {:?}%",
+                i, fan.mode, fan_speed
+            );
+
+            self.nvml
+                .set_fan_speed(i as u32, fan_speed as u32)
+                .map_err(|e| DeviceError::DeviceFanError {
+                    reason: format!(
+                        "Failed to set fan {} speed for device \"{}\"",
+                        i, self.uuid
+                    ),
+                    error: e.into(),
                 })?;
+        }
+
+        // Report the temperature used by the first fan in curve mode
+        self.temp_filtered = match reading {
+            Some(SensorReading::Temp(_)) if !self.fan_failsafe => self
+                .fans
+                .iter()
+                .find(|fan| matches!(fan.mode, FanMode::Curve))
+                .and_then(|fan| match fan.curve {
+                    Some(_) => fan.curve_state.filter.filtered,
+                    None => self.fan_curve_state.filter.filtered,
+                }),
+            _ => None,
+        };
+
+        check_sensor_fault(&self.uuid, &self.sensor_fault)
+    }
+    // Change the time between two fan updates, used by the fan
+    // curves depending on time before their first update
+    fn set_fan_update_interval(&mut self, update_interval: Duration) {
+        self.fan_update_interval = update_interval;
+    }
+
+    // Change the policy applied when the temperature can't be read
+    fn set_sensor_fault_policy(&mut self, policy: SensorFaultPolicy) {
+        self.sensor_fault.set_policy(policy);
+    }
+
+    // Force every fan to full speed regardless of its mode,
+    // the fan modes are applied again once disengaged
+    fn set_fan_failsafe(&mut self, engaged: bool) -> Result<()> {
+        if !self.gpu_info.capabilities.fan_control
+            || engaged == self.fan_failsafe
+        {
+            return Ok(());
+        }
+
+        if !engaged {
+            self.fan_failsafe = false;
+
+            for i in 0..self.fans.len() {
+                let mode = self.fans[i].mode;
+                self.set_fan_mode(FanSelection::Index(i as u32), mode)?;
             }
-            FanMode::Manual(speed) => {
-                debug!("Updating fan: Mode Manual - Speed: {:?}%", speed);
-
-                device.set_fan_speed(0, speed as u32).map_err(|e| {
-                    DeviceError::DeviceFanError {
-                        reason: format!(
-                            "Failed to set fan speed for device \"{}\"",
-                            self.uuid
-                        ),
-                        error: e.into(),
-                    }
+
+            return Ok(());
+        }
+
+        for i in 0..self.gpu_info.fan_count {
+            self.nvml
+                .set_fan_control_policy(i, FanControlPolicy::Manual)
+                .map_err(|e| DeviceError::DeviceFanError {
+                    reason: format!(
+                        "Failed to set fan {} mode to manual for: \"{}\"",
+                        i, self.uuid
+                    ),
+                    error: e.into(),
                 })?;
-            }
-            _ => {
-                debug!("Updating fan: Mode Auto")
-            }
         }
 
-        Ok(())
+        self.fan_failsafe = true;
+        self.sensor_auto = false;
+        self.update_fan()
     }
 
     // Return the device vendor specific information
@@ -448,12 +771,18 @@ impl GpuDevice for NvidiaDevice {
     // Apply the given GPU configuration to the device
     // The configuration vendor must match the
     fn apply_gpu_config(&mut self, gpu_config: GpuConfig) -> Result<()> {
-        // Get the NVML device
-        let mut device = self.get_device()?;
+        let capabilities = self.gpu_info.capabilities;
+        let mut unsupported = Vec::new();
+
+        // The settings missing from the configuration
+        // are restored to their original value
+        let gpu_config = gpu_config.or(&self.original_config);
 
         // Set the power limit
         if let Some(power_limit) = gpu_config.power_limit {
-            if power_limit > self.gpu_info.power_limit_max {
+            if !capabilities.power_limit {
+                unsupported.push("Power limit");
+            } else if power_limit > self.gpu_info.power_limit_max {
                 warn!(
                     "requested power limit is beyond max ({}), ingoring it",
                     self.gpu_info.power_limit_max
@@ -464,19 +793,73 @@ impl GpuDevice for NvidiaDevice {
                     self.gpu_info.power_limit_min
                 );
             } else {
-                device.set_power_management_limit(power_limit)?;
+                self.nvml.set_power_management_limit(power_limit)?;
             }
         }
 
         // Set vendor specific config
-        if let Some(offset) = gpu_config.nvidia_config.core_clock_offset {
-            device.set_gpc_clock_vf_offset(offset)?;
+        let nvidia_config = gpu_config.nvidia_config;
+
+        if !capabilities.clock_offsets {
+            if nvidia_config.core_clock_offset.is_some()
+                || nvidia_config.mem_clock_offset.is_some()
+            {
+                unsupported.push("Clock offsets");
+            }
+        } else {
+            if let Some(offset) = nvidia_config.core_clock_offset {
+                self.nvml.set_gpc_clock_vf_offset(offset)?;
+            }
+            if let Some(offset) = nvidia_config.mem_clock_offset {
+                self.nvml.set_mem_clock_vf_offset(offset)?;
+            }
         }
-        if let Some(offset) = gpu_config.nvidia_config.mem_clock_offset {
-            device.set_mem_clock_vf_offset(offset)?;
+
+        check_unsupported(&self.uuid, &unsupported)
+    }
+
+    // Return the fan modes and settings the device
+    // had before the daemon changed them
+    fn get_original_settings(&self) -> OriginalSettings {
+        OriginalSettings {
+            fan_modes: self.fans.iter().map(|fan| fan.original_mode).collect(),
+            config: self.original_config.clone(),
+            ..Default::default()
+        }
+    }
+    // Replace the settings restored by reset, the modes
+    // of fans missing from the settings are kept
+    fn set_original_settings(&mut self, settings: OriginalSettings) {
+        for (fan, mode) in self.fans.iter_mut().zip(settings.fan_modes) {
+            fan.original_mode = mode;
         }
 
-        Ok(())
+        self.original_config = settings.config;
+    }
+
+    // Restore the fan mode and the settings the
+    // device had before the daemon started
+    fn reset(&mut self) -> Result<()> {
+        self.fan_failsafe = false;
+
+        for i in 0..self.fans.len() {
+            let original_mode = self.fans[i].original_mode;
+            self.set_fan_mode(FanSelection::Index(i as u32), original_mode)?;
+        }
+
+        self.apply_gpu_config(GpuConfig::default())
+    }
+
+    // The device is lost if the driver can't find it anymore,
+    // other errors might be transient and are ignored
+    fn is_available(&self) -> bool {
+        !matches!(
+            self.nvml.uuid(),
+            Err(NvmlError::GpuLost
+                | NvmlError::NotFound
+                | NvmlError::DriverNotLoaded
+                | NvmlError::Uninitialized)
+        )
     }
 }
 
@@ -0,0 +1,233 @@
+use std::sync::Arc;
+
+use nvml_wrapper::{
+    Device, Nvml,
+    enum_wrappers::device::{
+        Clock, ClockId, TemperatureSensor, TemperatureThreshold,
+    },
+    enums::device::FanControlPolicy,
+    error::NvmlError,
+    struct_wrappers::device::{MemoryInfo, Utilization},
+    structs::device::PowerManagementConstraints,
+};
+
+// Alias the NVML result type for this module
+pub type NvmlResult<T> = std::result::Result<T, NvmlError>;
+
+// Classification of the NVML errors, used to tell apart the
+// failures of a single call from the ones of the whole driver
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NvmlFailure {
+    // The NVML library is not installed on the system
+    LibraryNotFound,
+    // The Nvidia kernel module is not loaded
+    DriverNotLoaded,
+    // The daemon is not allowed to access the driver
+    NoPermission,
+    // The driver stopped working, e.g. it was reloaded or upgraded
+    DriverLost,
+    // Any other error, specific to a single call or device
+    Other,
+}
+
+impl NvmlFailure {
+    pub fn classify(error: &NvmlError) -> Self {
+        match error {
+            NvmlError::LibloadingError(_)
+            | NvmlError::FailedToLoadSymbol(_)
+            | NvmlError::LibraryNotFound => Self::LibraryNotFound,
+            NvmlError::DriverNotLoaded => Self::DriverNotLoaded,
+            NvmlError::NoPermission => Self::NoPermission,
+            NvmlError::Uninitialized | NvmlError::LibRmVersionMismatch => {
+                Self::DriverLost
+            }
+            _ => Self::Other,
+        }
+    }
+
+    // Return true if every NVML call will fail until
+    // NVML is initialized again
+    pub fn is_driver_level(&self) -> bool {
+        matches!(self, Self::DriverNotLoaded | Self::DriverLost)
+    }
+}
+
+// NVML access for a single GPU
+//
+// The Nvidia device only talk to the driver through this trait so
+// its logic can be exercised with a fake driver, the methods mirror
+// the nvml_wrapper device API
+pub trait NvmlBackend {
+    // Device informations
+    fn uuid(&self) -> NvmlResult<String>;
+    fn name(&self) -> NvmlResult<String>;
+    fn sys_driver_version(&self) -> NvmlResult<String>;
+    fn vbios_version(&self) -> NvmlResult<String>;
+    fn num_cores(&self) -> NvmlResult<u32>;
+    fn current_pcie_link_width(&self) -> NvmlResult<u32>;
+    fn current_pcie_link_gen(&self) -> NvmlResult<u32>;
+    fn temperature_threshold(
+        &self,
+        threshold: TemperatureThreshold,
+    ) -> NvmlResult<u32>;
+
+    // Power management
+    fn power_management_limit_constraints(
+        &self,
+    ) -> NvmlResult<PowerManagementConstraints>;
+    fn power_management_limit_default(&self) -> NvmlResult<u32>;
+    fn power_management_limit(&self) -> NvmlResult<u32>;
+    fn power_usage(&self) -> NvmlResult<u32>;
+    fn set_power_management_limit(&mut self, limit: u32) -> NvmlResult<()>;
+
+    // Sensors and utilization
+    fn temperature(&self, sensor: TemperatureSensor) -> NvmlResult<u32>;
+    fn clock(&self, clock: Clock, clock_id: ClockId) -> NvmlResult<u32>;
+    fn utilization_rates(&self) -> NvmlResult<Utilization>;
+    fn memory_info(&self) -> NvmlResult<MemoryInfo>;
+
+    // Clock offsets
+    fn gpc_clock_vf_offset(&self) -> NvmlResult<i32>;
+    fn mem_clock_vf_offset(&self) -> NvmlResult<i32>;
+    fn set_gpc_clock_vf_offset(&mut self, offset: i32) -> NvmlResult<()>;
+    fn set_mem_clock_vf_offset(&mut self, offset: i32) -> NvmlResult<()>;
+
+    // Fan control
+    fn num_fans(&self) -> NvmlResult<u32>;
+    fn min_max_fan_speed(&self) -> NvmlResult<(u32, u32)>;
+    fn fan_speed(&self, fan_idx: u32) -> NvmlResult<u32>;
+    fn fan_speed_rpm(&self, fan_idx: u32) -> NvmlResult<u32>;
+    fn fan_control_policy(&self, fan_idx: u32) -> NvmlResult<FanControlPolicy>;
+    fn set_fan_control_policy(
+        &mut self,
+        fan_idx: u32,
+        policy: FanControlPolicy,
+    ) -> NvmlResult<()>;
+    fn set_fan_speed(&mut self, fan_idx: u32, speed: u32) -> NvmlResult<()>;
+}
+
+// Production NVML backend built on top of nvml_wrapper
+//
+// The NVML device handle borrow the NVML context, so a new
+// handle is acquired from the UUID for every call
+pub struct NvmlWrapperBackend {
+    // Store a reference to the NVML context
+    nvml: Arc<Nvml>,
+
+    // Store the GPU unique identifier
+    uuid: String,
+}
+
+impl NvmlWrapperBackend {
+    pub fn new(nvml: Arc<Nvml>, uuid: &str) -> Self {
+        Self {
+            nvml,
+            uuid: uuid.to_string(),
+        }
+    }
+
+    // Return a NVML device handle
+    fn device(&self) -> NvmlResult<Device<'_>> {
+        self.nvml.device_by_uuid(self.uuid.as_str())
+    }
+}
+
+impl NvmlBackend for NvmlWrapperBackend {
+    fn uuid(&self) -> NvmlResult<String> {
+        self.device()?.uuid()
+    }
+    fn name(&self) -> NvmlResult<String> {
+        self.device()?.name()
+    }
+    fn sys_driver_version(&self) -> NvmlResult<String> {
+        self.nvml.sys_driver_version()
+    }
+    fn vbios_version(&self) -> NvmlResult<String> {
+        self.device()?.vbios_version()
+    }
+    fn num_cores(&self) -> NvmlResult<u32> {
+        self.device()?.num_cores()
+    }
+    fn current_pcie_link_width(&self) -> NvmlResult<u32> {
+        self.device()?.current_pcie_link_width()
+    }
+    fn current_pcie_link_gen(&self) -> NvmlResult<u32> {
+        self.device()?.current_pcie_link_gen()
+    }
+    fn temperature_threshold(
+        &self,
+        threshold: TemperatureThreshold,
+    ) -> NvmlResult<u32> {
+        self.device()?.temperature_threshold(threshold)
+    }
+
+    fn power_management_limit_constraints(
+        &self,
+    ) -> NvmlResult<PowerManagementConstraints> {
+        self.device()?.power_management_limit_constraints()
+    }
+    fn power_management_limit_default(&self) -> NvmlResult<u32> {
+        self.device()?.power_management_limit_default()
+    }
+    fn power_management_limit(&self) -> NvmlResult<u32> {
+        self.device()?.power_management_limit()
+    }
+    fn power_usage(&self) -> NvmlResult<u32> {
+        self.device()?.power_usage()
+    }
+    fn set_power_management_limit(&mut self, limit: u32) -> NvmlResult<()> {
+        self.device()?.set_power_management_limit(limit)
+    }
+
+    fn temperature(&self, sensor: TemperatureSensor) -> NvmlResult<u32> {
+        self.device()?.temperature(sensor)
+    }
+    fn clock(&self, clock: Clock, clock_id: ClockId) -> NvmlResult<u32> {
+        self.device()?.clock(clock, clock_id)
+    }
+    fn utilization_rates(&self) -> NvmlResult<Utilization> {
+        self.device()?.utilization_rates()
+    }
+    fn memory_info(&self) -> NvmlResult<MemoryInfo> {
+        self.device()?.memory_info()
+    }
+
+    fn gpc_clock_vf_offset(&self) -> NvmlResult<i32> {
+        self.device()?.gpc_clock_vf_offset()
+    }
+    fn mem_clock_vf_offset(&self) -> NvmlResult<i32> {
+        self.device()?.mem_clock_vf_offset()
+    }
+    fn set_gpc_clock_vf_offset(&mut self, offset: i32) -> NvmlResult<()> {
+        self.device()?.set_gpc_clock_vf_offset(offset)
+    }
+    fn set_mem_clock_vf_offset(&mut self, offset: i32) -> NvmlResult<()> {
+        self.device()?.set_mem_clock_vf_offset(offset)
+    }
+
+    fn num_fans(&self) -> NvmlResult<u32> {
+        self.device()?.num_fans()
+    }
+    fn min_max_fan_speed(&self) -> NvmlResult<(u32, u32)> {
+        self.device()?.min_max_fan_speed()
+    }
+    fn fan_speed(&self, fan_idx: u32) -> NvmlResult<u32> {
+        self.device()?.fan_speed(fan_idx)
+    }
+    fn fan_speed_rpm(&self, fan_idx: u32) -> NvmlResult<u32> {
+        self.device()?.fan_speed_rpm(fan_idx)
+    }
+    fn fan_control_policy(&self, fan_idx: u32) -> NvmlResult<FanControlPolicy> {
+        self.device()?.fan_control_policy(fan_idx)
+    }
+    fn set_fan_control_policy(
+        &mut self,
+        fan_idx: u32,
+        policy: FanControlPolicy,
+    ) -> NvmlResult<()> {
+        self.device()?.set_fan_control_policy(fan_idx, policy)
+    }
+    fn set_fan_speed(&mut self, fan_idx: u32, speed: u32) -> NvmlResult<()> {
+        self.device()?.set_fan_speed(fan_idx, speed)
+    }
+}
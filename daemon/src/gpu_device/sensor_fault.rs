@@ -0,0 +1,112 @@
+use std::time::{Duration, Instant};
+
+use tracing::info;
+
+// Number of consecutive failed temperature reads
+// after which the sensor fault is reported
+pub const SENSOR_FAULT_REPORT: u32 = 3;
+
+// Fan control used by the fans in curve mode
+// when the temperature sensor can't be read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensorFaultPolicy {
+    // Keep using the last good temperature for the given
+    // duration, then run the fans at full speed
+    LastGood(Duration),
+    // Run the fans at the given speed in percent
+    FixedDuty(u8),
+    // Give the fans back to the driver automatic mode
+    Auto,
+}
+
+// Run the fans at full speed, like before the policies were configurable
+impl Default for SensorFaultPolicy {
+    fn default() -> Self {
+        Self::FixedDuty(100)
+    }
+}
+
+// Fan control to apply after a temperature read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensorReading {
+    // Temperature to feed to the fan curves
+    Temp(i32),
+    // Fixed fan speed in percent
+    Duty(u8),
+    // The driver control the fans
+    Auto,
+}
+
+// Apply the sensor fault policy of a device and
+// count the consecutive failed temperature reads
+#[derive(Debug, Clone, Default)]
+pub struct SensorFaultHandler {
+    policy: SensorFaultPolicy,
+
+    // Number of consecutive failed reads
+    failures: u32,
+    // Last temperature read successfully and its read instant
+    last_good: Option<(i32, Instant)>,
+}
+
+impl SensorFaultHandler {
+    pub fn new(policy: SensorFaultPolicy) -> Self {
+        Self {
+            policy,
+
+            failures: 0,
+            last_good: None,
+        }
+    }
+
+    // Change the policy used on the next failed reads
+    pub fn set_policy(&mut self, policy: SensorFaultPolicy) {
+        self.policy = policy;
+    }
+
+    // Return the number of consecutive failed reads
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    // Return true if the consecutive failed reads just
+    // reached the threshold to report the sensor fault
+    pub fn should_report(&self) -> bool {
+        self.failures == SENSOR_FAULT_REPORT
+    }
+
+    // Return the fan control to apply given the temperature
+    // read from the sensor, None if the read failed
+    pub fn read(&mut self, temp: Option<i32>) -> SensorReading {
+        let now = Instant::now();
+
+        if let Some(temp) = temp {
+            if self.failures >= SENSOR_FAULT_REPORT {
+                info!(
+                    "Temperature sensor recovered after {} failed reads",
+                    self.failures
+                );
+            }
+
+            self.failures = 0;
+            self.last_good = Some((temp, now));
+
+            return SensorReading::Temp(temp);
+        }
+
+        self.failures = self.failures.saturating_add(1);
+
+        match self.policy {
+            SensorFaultPolicy::LastGood(hold) => match self.last_good {
+                Some((temp, instant)) if now - instant <= hold => {
+                    SensorReading::Temp(temp)
+                }
+                _ => SensorReading::Duty(100),
+            },
+            SensorFaultPolicy::FixedDuty(duty) => {
+                SensorReading::Duty(duty.min(100))
+            }
+            SensorFaultPolicy::Auto => SensorReading::Auto,
+        }
+    }
+}
@@ -0,0 +1,686 @@
+use std::{
+    f32::consts::PI,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use tracing::{debug, warn};
+
+use crate::{
+    fan_curve::{
+        CurveState, FanCurve,
+        fan_curve_info::FanCurveInfo,
+        fan_mode::{FanMode, FanSelection},
+        linear_curve::LinearCurve,
+    },
+    gpu_device::{
+        DEFAULT_DATA_UPDATE_INTERVAL, DEFAULT_FAN_UPDATE_INTERVAL, GpuDevice,
+        GpuVendor, Result, curve_input,
+        gpu_config::{GpuConfig, OriginalSettings},
+        gpu_data::{FanData, GpuData, GpuVendorData},
+        gpu_info::{GpuCapabilities, GpuInfo, GpuVendorInfo},
+        select_fans,
+        sensor_fault::SensorFaultPolicy,
+    },
+};
+
+// Longest integration step of the thermal model, longer
+// intervals are split to keep the simulation stable
+const MAX_SIMULATION_STEP: Duration = Duration::from_millis(100);
+
+// Fan speed in RPM at 100% duty
+const SIMULATED_MAX_FAN_RPM: u32 = 3000;
+
+// The simulated GPU has a single fan
+const FAN_COUNT: u32 = 1;
+
+// Synthetic GPU load over time, load values go from 0.0 to 1.0
+#[derive(Debug, Clone, Copy)]
+pub enum LoadPattern {
+    // Always the same load
+    Constant(f32),
+    // Alternate between the low and high load every half period
+    Square {
+        period: Duration,
+        low: f32,
+        high: f32,
+    },
+    // Oscillate smoothly between the low and high load
+    Sine {
+        period: Duration,
+        low: f32,
+        high: f32,
+    },
+}
+
+impl LoadPattern {
+    // Return the load at the given time since the simulation start
+    pub fn load_at(&self, time: Duration) -> f32 {
+        let load = match *self {
+            LoadPattern::Constant(load) => load,
+            LoadPattern::Square { period, low, high } => {
+                let phase = phase(time, period);
+
+                if phase < 0.5 { high } else { low }
+            }
+            LoadPattern::Sine { period, low, high } => {
+                let phase = phase(time, period);
+                let wave = (1.0 - (2.0 * PI * phase).cos()) / 2.0;
+
+                low + (high - low) * wave
+            }
+        };
+
+        load.clamp(0.0, 1.0)
+    }
+}
+
+// Parse a load pattern of the form "constant:LOAD",
+// "square:PERIOD:LOW:HIGH" or "sine:PERIOD:LOW:HIGH",
+// the period is in seconds
+impl FromStr for LoadPattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || format!("Invalid load pattern \"{}\"", s);
+
+        let mut parts = s.split(':');
+        let kind = parts.next().unwrap_or_default();
+        let values = parts
+            .map(|value| value.trim().parse::<f32>())
+            .collect::<std::result::Result<Vec<f32>, _>>()
+            .map_err(|_| invalid())?;
+
+        // Negative and non finite periods are rejected
+        let period = |secs: f32| {
+            Duration::try_from_secs_f32(secs).map_err(|_| invalid())
+        };
+
+        match (kind, values.as_slice()) {
+            ("constant", &[load]) => Ok(LoadPattern::Constant(load)),
+            ("square", &[secs, low, high]) => Ok(LoadPattern::Square {
+                period: period(secs)?,
+                low,
+                high,
+            }),
+            ("sine", &[secs, low, high]) => Ok(LoadPattern::Sine {
+                period: period(secs)?,
+                low,
+                high,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+// Parameters of the simulated GPU thermal model
+//
+// The temperature follows C * dT/dt = P - k * (T - T_ambient),
+// where the cooling coefficient k grows linearly with the fan speed
+#[derive(Debug, Clone, Copy)]
+pub struct ThermalModel {
+    // Ambient temperature in degrees Celsius
+    pub ambient_temp: f32,
+    // Heat capacity of the GPU and heatsink in J/°C
+    pub heat_capacity: f32,
+
+    // Power draw at no load and full load in watts
+    pub idle_power: f32,
+    pub max_power: f32,
+
+    // Cooling coefficient in W/°C with the fans stopped
+    pub passive_cooling: f32,
+    // Cooling coefficient in W/°C added by the fans at 100% speed
+    pub fan_cooling: f32,
+
+    // Synthetic load driving the power draw
+    pub load_pattern: LoadPattern,
+}
+
+impl Default for ThermalModel {
+    fn default() -> Self {
+        Self {
+            ambient_temp: 25.0,
+            heat_capacity: 400.0,
+
+            idle_power: 20.0,
+            max_power: 250.0,
+
+            passive_cooling: 1.0,
+            fan_cooling: 5.0,
+
+            load_pattern: LoadPattern::Square {
+                period: Duration::from_secs(120),
+                low: 0.05,
+                high: 0.95,
+            },
+        }
+    }
+}
+
+pub struct SimulatedDevice {
+    // Thermal model parameters
+    model: ThermalModel,
+
+    // Store the device generic and vendor specific informations
+    gpu_info: GpuInfo,
+    gpu_vendor_info: GpuVendorInfo,
+
+    // Simulation state
+    temp: f32,
+    load: f32,
+    power_usage: f32,
+    fan_speed: u8,
+    power_limit: u32,
+
+    // Simulated time since the device creation
+    simulation_time: Duration,
+    // Instant of the last simulation step
+    last_step: Instant,
+
+    // Store the device generic and vendor specific data
+    gpu_data: GpuData,
+    gpu_vendor_data: GpuVendorData,
+
+    // Data update interval
+    gpu_data_update_interval: Duration,
+    // Instant of the last data update
+    gpu_data_last_update: Instant,
+
+    // Store the current fan mode
+    fan_mode: FanMode,
+    // Fan curve to apply in curve mode
+    fan_curve: Box<dyn FanCurve>,
+    // State of the fan controlled by the fan curve
+    fan_curve_state: CurveState,
+    // Time between two fan updates, passed to the fan curve
+    fan_update_interval: Duration,
+    // Set while the thermal failsafe force the fan to full speed
+    fan_failsafe: bool,
+}
+
+impl SimulatedDevice {
+    // Create a simulated device, the index is used to generate the UUID
+    pub fn new(index: u32, model: ThermalModel) -> Self {
+        let power_limit_max = (model.max_power * 1000.0) as u32;
+
+        let gpu_info = GpuInfo {
+            uuid: format!("SIM-{:04}", index),
+            name: format!("Simulated GPU {}", index),
+            pcie_width: 16,
+            pcie_gen: 4,
+            power_limit_max,
+            power_limit_min: power_limit_max / 3,
+            power_limit_default: power_limit_max,
+
+            fan_count: FAN_COUNT,
+            fan_speed_min: 0,
+            fan_speed_max: 100,
+
+            // The thermal model doesn't simulate overclocking
+            // and the memory temperature
+            capabilities: GpuCapabilities {
+                fan_control: true,
+                fan_rpm: true,
+                clock_offsets: false,
+                power_limit: true,
+                power_usage: true,
+                mem_temp: false,
+            },
+        };
+
+        let gpu_vendor_info = GpuVendorInfo::Simulated {
+            ambient_temp: model.ambient_temp,
+            heat_capacity: model.heat_capacity,
+        };
+
+        // Generate a default fan curve always at 100% fan speed
+        let fan_curve = Box::new(LinearCurve::new(&[(0, 100)]));
+
+        let mut device = Self {
+            model,
+
+            gpu_info,
+            gpu_vendor_info,
+
+            temp: model.ambient_temp,
+            load: 0.0,
+            power_usage: 0.0,
+            fan_speed: 0,
+            power_limit: power_limit_max,
+
+            simulation_time: Duration::ZERO,
+            last_step: Instant::now(),
+
+            gpu_data: GpuData {
+                temp_gpu: Some(0),
+                temp_sensor_failures: 0,
+                temp_gpu_filtered: None,
+                graphics_freq: None,
+                mem_freq: None,
+                core_clock_offset: None,
+                mem_clock_offset: None,
+                power_usage: None,
+                power_limit: None,
+                fans: Vec::new(),
+                core_usage: None,
+                mem_usage: None,
+                total_memory: None,
+                used_memory: None,
+                free_memory: None,
+            },
+            gpu_vendor_data: GpuVendorData::Simulated { load: 0.0 },
+
+            gpu_data_update_interval: DEFAULT_DATA_UPDATE_INTERVAL,
+            gpu_data_last_update: Instant::now(),
+
+            fan_mode: FanMode::Auto,
+            fan_curve,
+            fan_curve_state: CurveState::default(),
+            fan_update_interval: DEFAULT_FAN_UPDATE_INTERVAL,
+            fan_failsafe: false,
+        };
+
+        device.fan_speed = device.auto_fan_speed();
+        device.refresh_data();
+
+        device
+    }
+
+    // Advance the thermal model by the given simulated time
+    fn step(&mut self, duration: Duration) {
+        let mut remaining = duration;
+
+        while !remaining.is_zero() {
+            let dt = remaining.min(MAX_SIMULATION_STEP);
+            remaining -= dt;
+
+            self.simulation_time += dt;
+            self.load = self.model.load_pattern.load_at(self.simulation_time);
+
+            // The power draw scale with the load and is capped
+            // by the current power limit
+            let power = self.model.idle_power
+                + (self.model.max_power - self.model.idle_power) * self.load;
+            self.power_usage = power.min(self.power_limit as f32 / 1000.0);
+
+            let cooling = self.model.passive_cooling
+                + self.model.fan_cooling * self.fan_speed as f32 / 100.0;
+            let heat_flow = self.power_usage
+                - cooling * (self.temp - self.model.ambient_temp);
+
+            self.temp +=
+                heat_flow * dt.as_secs_f32() / self.model.heat_capacity;
+
+            // Emulate the driver fan control in automatic mode
+            if matches!(self.fan_mode, FanMode::Auto) && !self.fan_failsafe {
+                self.fan_speed = self.auto_fan_speed();
+            }
+        }
+    }
+
+    // Advance the thermal model to the current instant
+    fn advance(&mut self) {
+        let now = Instant::now();
+        self.step(now.duration_since(self.last_step));
+        self.last_step = now;
+    }
+
+    // Fan speed chosen by the emulated driver in automatic mode
+    fn auto_fan_speed(&self) -> u8 {
+        ((self.temp - 40.0) * 2.0).clamp(30.0, 100.0) as u8
+    }
+
+    // Copy the simulation state to the device data
+    fn refresh_data(&mut self) {
+        let usage = (self.load * 100.0) as u32;
+
+        self.gpu_data = GpuData {
+            temp_gpu: Some(self.temp.max(0.0) as u32),
+            temp_sensor_failures: 0,
+            temp_gpu_filtered: matches!(self.fan_mode, FanMode::Curve)
+                .then_some(self.fan_curve_state.filter.filtered)
+                .flatten(),
+
+            graphics_freq: Some(300 + (self.load * 1700.0) as u32),
+            mem_freq: Some(5000),
+
+            core_clock_offset: None,
+            mem_clock_offset: None,
+
+            power_usage: Some((self.power_usage * 1000.0) as u32),
+            power_limit: Some(self.power_limit),
+
+            fans: vec![FanData {
+                speed: Some(self.fan_speed as u32),
+                speed_rpm: Some(
+                    self.fan_speed as u32 * SIMULATED_MAX_FAN_RPM / 100,
+                ),
+            }],
+
+            core_usage: Some(usage),
+            mem_usage: Some(usage / 2),
+
+            total_memory: Some(8 << 30),
+            used_memory: Some((usage as u64) << 26),
+            free_memory: Some((8 << 30) - ((usage as u64) << 26)),
+        };
+        self.gpu_vendor_data = GpuVendorData::Simulated { load: self.load };
+    }
+
+    // Update the device data only if GPU update interval has elapsed
+    fn udpate_data(&mut self) {
+        self.advance();
+
+        let time_elapsed = self.gpu_data_last_update.elapsed();
+
+        if time_elapsed >= self.gpu_data_update_interval {
+            self.refresh_data();
+
+            self.gpu_data_last_update = Instant::now();
+        }
+    }
+}
+
+impl GpuDevice for SimulatedDevice {
+    // Return the device vendor
+    fn get_vendor(&self) -> GpuVendor {
+        GpuVendor::Simulated
+    }
+
+    // Set the fan curve of the selected fans, this does not
+    // automatically set the fan mode to curve
+    fn set_fan_curve(
+        &mut self,
+        fans: FanSelection,
+        fan_curve: Box<dyn FanCurve>,
+    ) -> Result<()> {
+        select_fans(&self.gpu_info.uuid, fans, FAN_COUNT)?;
+
+        self.fan_curve = fan_curve;
+        self.fan_curve_state = CurveState::default();
+
+        Ok(())
+    }
+    // Return the info of the fan curve of every fan
+    fn get_fan_curves(&self) -> Vec<FanCurveInfo> {
+        vec![self.fan_curve.to_info(); FAN_COUNT as usize]
+    }
+    // Set the fan mode of the selected fans, if no fan curve was
+    // previously set default to a 100% fan speed curve
+    fn set_fan_mode(
+        &mut self,
+        fans: FanSelection,
+        fan_mode: FanMode,
+    ) -> Result<()> {
+        select_fans(&self.gpu_info.uuid, fans, FAN_COUNT)?;
+
+        self.advance();
+
+        self.fan_mode = fan_mode;
+        self.update_fan()
+    }
+    // Update the fan speed according to the mode and the fan curve
+    fn update_fan(&mut self) -> Result<()> {
+        self.advance();
+
+        match self.fan_mode {
+            _ if self.fan_failsafe => {
+                debug!("Updating fan: Failsafe - Speed: 100%");
+
+                self.fan_speed = 100;
+            }
+            FanMode::Curve => {
+                let input = curve_input(
+                    &self.gpu_info,
+                    self.temp as i32,
+                    self.fan_update_interval,
+                );
+
+                let fan_speed = self.fan_curve.evaluate(
+                    &mut self.fan_curve_state,
+                    &input,
+                    self.last_step,
+                );
+
+                debug!("Updating fan: Mode Curve - Speed: {:?}%", fan_speed);
+
+                self.fan_speed = fan_speed.min(100);
+            }
+            FanMode::Manual(speed) => {
+                debug!("Updating fan: Mode Manual - Speed: {:?}%", speed);
+
+                self.fan_speed = speed.min(100);
+            }
+            _ => {
+                debug!("Updating fan: Mode Auto")
+            }
+        }
+
+        Ok(())
+    }
+    // Change the time between two fan updates, used by the fan
+    // curve if it depends on time before its first update
+    fn set_fan_update_interval(&mut self, update_interval: Duration) {
+        self.fan_update_interval = update_interval;
+    }
+
+    // The simulated temperature can always be read,
+    // the sensor fault policy is never applied
+    fn set_sensor_fault_policy(&mut self, _policy: SensorFaultPolicy) {}
+
+    // Force the fan to full speed regardless of its mode,
+    // the fan mode is applied again once disengaged
+    fn set_fan_failsafe(&mut self, engaged: bool) -> Result<()> {
+        self.advance();
+
+        self.fan_failsafe = engaged;
+        self.update_fan()
+    }
+
+    // Return the device vendor specific information
+    fn get_vendor_info(&self) -> GpuVendorInfo {
+        self.gpu_vendor_info.clone()
+    }
+    // Return the device general information
+    fn get_info(&self) -> GpuInfo {
+        self.gpu_info.clone()
+    }
+
+    // Return the device vendor specific real time data,
+    // the update frequency is controlled by the set_update_freq function,
+    // the default update frequency is 1 hertz
+    fn get_vendor_data(&mut self) -> Result<GpuVendorData> {
+        self.udpate_data();
+        Ok(self.gpu_vendor_data.clone())
+    }
+    // Return the device general real time data
+    // the update frequency is controlled by the set_update_freq function,
+    // the default update frequency is 1 hertz
+    fn get_data(&mut self) -> Result<GpuData> {
+        self.udpate_data();
+        Ok(self.gpu_data.clone())
+    }
+    // Change the vendor and general data update frequency
+    fn set_data_update_interval(&mut self, update_interval: Duration) {
+        self.gpu_data_update_interval = update_interval;
+    }
+
+    // Apply the given GPU configuration to the device
+    // The configuration vendor must match the
+    fn apply_gpu_config(&mut self, gpu_config: GpuConfig) -> Result<()> {
+        self.advance();
+
+        // Set the power limit, the simulation
+        // starts at the default power limit
+        let power_limit = gpu_config
+            .power_limit
+            .unwrap_or(self.gpu_info.power_limit_default);
+
+        if power_limit > self.gpu_info.power_limit_max {
+            warn!(
+                "requested power limit is beyond max ({}), ingoring it",
+                self.gpu_info.power_limit_max
+            );
+        } else if power_limit < self.gpu_info.power_limit_min {
+            warn!(
+                "requested power limit is bellow min ({}), ingoring it",
+                self.gpu_info.power_limit_min
+            );
+        } else {
+            self.power_limit = power_limit;
+        }
+
+        Ok(())
+    }
+
+    // The simulation always start from the automatic
+    // fan mode and the default power limit, it's never
+    // recorded in the runtime state
+    fn get_original_settings(&self) -> OriginalSettings {
+        OriginalSettings {
+            fan_modes: vec![FanMode::Auto],
+            config: GpuConfig {
+                power_limit: Some(self.gpu_info.power_limit_default),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+    // The simulation state doesn't outlive the daemon,
+    // there is nothing to restore
+    fn set_original_settings(&mut self, _settings: OriginalSettings) {}
+
+    // Restore the fan mode and the settings the
+    // device had before the daemon started
+    fn reset(&mut self) -> Result<()> {
+        self.fan_failsafe = false;
+        self.set_fan_mode(FanSelection::All, FanMode::Auto)?;
+        self.apply_gpu_config(GpuConfig::default())
+    }
+
+    // The simulated GPU can't be unplugged
+    fn is_available(&self) -> bool {
+        true
+    }
+}
+
+// Return the position in the current period as a value from 0.0 to 1.0
+fn phase(time: Duration, period: Duration) -> f32 {
+    if period.is_zero() {
+        return 0.0;
+    }
+
+    (time.as_secs_f64() % period.as_secs_f64() / period.as_secs_f64()) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Run the model with the fan at a fixed speed until it settles
+    fn settled_temp(
+        model: ThermalModel,
+        fan_speed: u8,
+        power_limit: u32,
+    ) -> f32 {
+        let mut device = SimulatedDevice::new(0, model);
+        device.fan_mode = FanMode::Manual(fan_speed);
+        device.fan_speed = fan_speed;
+        device.power_limit = power_limit;
+
+        device.step(Duration::from_secs(3600));
+        device.temp
+    }
+
+    fn constant_load_model(load: f32) -> ThermalModel {
+        ThermalModel {
+            load_pattern: LoadPattern::Constant(load),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn load_patterns_follow_their_period() {
+        let period = Duration::from_secs(100);
+        let square = LoadPattern::Square {
+            period,
+            low: 0.1,
+            high: 0.9,
+        };
+        let sine = LoadPattern::Sine {
+            period,
+            low: 0.2,
+            high: 0.6,
+        };
+
+        assert_eq!(square.load_at(Duration::from_secs(10)), 0.9);
+        assert_eq!(square.load_at(Duration::from_secs(60)), 0.1);
+        assert_eq!(square.load_at(Duration::from_secs(110)), 0.9);
+
+        assert!((sine.load_at(Duration::ZERO) - 0.2).abs() < 1e-4);
+        assert!((sine.load_at(Duration::from_secs(50)) - 0.6).abs() < 1e-4);
+        assert!((sine.load_at(Duration::from_secs(25)) - 0.4).abs() < 1e-4);
+
+        // Loads are clamped to the valid range
+        assert_eq!(LoadPattern::Constant(1.5).load_at(Duration::ZERO), 1.0);
+    }
+
+    #[test]
+    fn load_patterns_are_parsed() {
+        assert!(matches!(
+            "constant:0.5".parse(),
+            Ok(LoadPattern::Constant(0.5))
+        ));
+        assert!(matches!(
+            "square:60:0.1:0.9".parse(),
+            Ok(LoadPattern::Square { period, low: 0.1, high: 0.9 })
+                if period == Duration::from_secs(60)
+        ));
+        assert!(matches!(
+            "sine:30:0:1".parse(),
+            Ok(LoadPattern::Sine { period, .. })
+                if period == Duration::from_secs(30)
+        ));
+
+        for invalid in ["", "constant", "square:-1:0:1", "sine:10:a:1"] {
+            assert!(invalid.parse::<LoadPattern>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn temperature_settles_where_cooling_match_the_power() {
+        // 250 W over 1 + 5 W/°C with the fan at full speed
+        let temp = settled_temp(constant_load_model(1.0), 100, 250_000);
+        assert!((temp - (25.0 + 250.0 / 6.0)).abs() < 0.1, "{}", temp);
+
+        // The idle power over the passive cooling with the fan stopped
+        let temp = settled_temp(constant_load_model(0.0), 0, 250_000);
+        assert!((temp - (25.0 + 20.0)).abs() < 0.1, "{}", temp);
+    }
+
+    #[test]
+    fn power_limit_caps_the_heat_input() {
+        let temp = settled_temp(constant_load_model(1.0), 100, 125_000);
+
+        assert!((temp - (25.0 + 125.0 / 6.0)).abs() < 0.1, "{}", temp);
+    }
+
+    #[test]
+    fn heat_capacity_slows_the_temperature_change() {
+        let mut light = SimulatedDevice::new(0, constant_load_model(1.0));
+        let mut heavy = SimulatedDevice::new(
+            1,
+            ThermalModel {
+                heat_capacity: 4000.0,
+                ..constant_load_model(1.0)
+            },
+        );
+
+        light.step(Duration::from_secs(10));
+        heavy.step(Duration::from_secs(10));
+
+        assert!(light.temp > heavy.temp);
+        assert!(heavy.temp > 25.0);
+    }
+}
@@ -0,0 +1,111 @@
+use std::{
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::anyhow;
+
+use crate::gpu_device::{DeviceError, Result};
+
+// Default mount point of the sysfs file system
+pub const DEFAULT_SYSFS_ROOT: &str = "/sys";
+
+// Return the device directory of every DRM card under the given sysfs
+// root, the paths have the form "<root>/class/drm/cardN/device"
+pub fn drm_card_devices(sysfs_root: &Path) -> Vec<PathBuf> {
+    let drm_path = sysfs_root.join("class/drm");
+
+    let Ok(entries) = fs::read_dir(&drm_path) else {
+        return Vec::new();
+    };
+
+    let mut devices: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            // Only keep the card entries, ignore the connectors
+            // (cardN-DP-1) and the render nodes (renderDN)
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+
+            name.strip_prefix("card").is_some_and(|index| {
+                !index.is_empty() && index.chars().all(|c| c.is_ascii_digit())
+            })
+        })
+        .map(|entry| entry.path().join("device"))
+        .filter(|path| path.is_dir())
+        .collect();
+
+    devices.sort();
+    devices
+}
+
+// Return the first hwmon directory of the given device
+pub fn find_hwmon(device_path: &Path) -> Option<PathBuf> {
+    let entries = fs::read_dir(device_path.join("hwmon")).ok()?;
+
+    let mut hwmons: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with("hwmon"))
+        })
+        .collect();
+
+    hwmons.sort();
+    hwmons.into_iter().next()
+}
+
+// Return the PCI slot name of the given device from its uevent file
+pub fn pci_slot_name(device_path: &Path) -> Option<String> {
+    let uevent = fs::read_to_string(device_path.join("uevent")).ok()?;
+
+    uevent
+        .lines()
+        .find_map(|line| line.strip_prefix("PCI_SLOT_NAME="))
+        .map(|slot| slot.trim().to_string())
+}
+
+// Read a sysfs attribute as a trimmed string
+pub fn read_string(path: &Path) -> Result<String> {
+    fs::read_to_string(path)
+        .map(|value| value.trim().to_string())
+        .map_err(|e| DeviceError::DeviceInternal {
+            reason: format!("Failed to read \"{}\"", path.display()),
+            error: e.into(),
+        })
+}
+
+// Read and parse a sysfs attribute
+pub fn read_value<T: FromStr>(path: &Path) -> Result<T> {
+    let value = read_string(path)?;
+
+    value.parse().map_err(|_| DeviceError::DeviceInternal {
+        reason: format!("Failed to parse \"{}\"", path.display()),
+        error: anyhow!("Invalid value \"{}\"", value),
+    })
+}
+
+// Read and parse a sysfs attribute, return None if the
+// attribute doesn't exist or can't be parsed
+pub fn read_optional<T: FromStr>(path: &Path) -> Option<T> {
+    read_value(path).ok()
+}
+
+// Read a hwmon power attribute in milliwatts,
+// hwmon power values are stored in microwatts
+pub fn read_milliwatts(path: &Path) -> Option<u32> {
+    read_optional::<u64>(path).map(|power| (power / 1000) as u32)
+}
+
+// Write a value to a sysfs attribute
+pub fn write_value(path: &Path, value: impl Display) -> Result<()> {
+    fs::write(path, value.to_string()).map_err(|e| {
+        DeviceError::DeviceInternal {
+            reason: format!("Failed to write \"{}\"", path.display()),
+            error: e.into(),
+        }
+    })
+}
@@ -0,0 +1,101 @@
+use crate::gpu_device::gpu_info::GpuVendorInfo;
+
+// Default number of degrees the temperature must fall
+// below the threshold before the failsafe is released
+pub const DEFAULT_FAILSAFE_HYSTERESIS: u32 = 5;
+
+// Failsafe settings shared by all the devices
+#[derive(Debug, Clone, Copy)]
+pub struct FailsafeSettings {
+    // Temperature forcing the fans to full speed, if None the
+    // slowdown or maximum temperature of the device is used
+    pub temp: Option<u32>,
+    // Degrees below the threshold required to release the failsafe
+    pub hysteresis: u32,
+    // Drop the power limit to its minimum while the failsafe is engaged
+    pub drop_power_limit: bool,
+
+    // Give the fans of a device back to the driver when one of them stall
+    pub auto_on_fan_stall: bool,
+}
+
+impl Default for FailsafeSettings {
+    fn default() -> Self {
+        Self {
+            temp: None,
+            hysteresis: DEFAULT_FAILSAFE_HYSTERESIS,
+            drop_power_limit: false,
+
+            auto_on_fan_stall: false,
+        }
+    }
+}
+
+// Track the failsafe state of a single device, the failsafe engage
+// when the temperature reach the threshold and is released once the
+// temperature fall below the threshold minus the hysteresis
+#[derive(Debug, Clone)]
+pub struct ThermalFailsafe {
+    // None if the device doesn't report a threshold
+    // and none was configured, the failsafe is then disabled
+    threshold: Option<u32>,
+    hysteresis: u32,
+
+    engaged: bool,
+}
+
+impl ThermalFailsafe {
+    // Create the failsafe of a device, the configured temperature
+    // take precedence over the device thresholds
+    pub fn new(
+        settings: &FailsafeSettings,
+        vendor_info: &GpuVendorInfo,
+    ) -> Self {
+        let device_threshold = match vendor_info {
+            GpuVendorInfo::Nvidia {
+                max_temp,
+                slowdown_temp,
+                ..
+            } => slowdown_temp.or(*max_temp),
+            // The GPU temperature of AMD devices is the edge temperature
+            GpuVendorInfo::AMD { edge_max_temp, .. } => *edge_max_temp,
+            _ => None,
+        };
+
+        Self {
+            threshold: settings.temp.or(device_threshold),
+            hysteresis: settings.hysteresis,
+
+            engaged: false,
+        }
+    }
+
+    // Return the temperature engaging the failsafe
+    pub fn threshold(&self) -> Option<u32> {
+        self.threshold
+    }
+
+    // Return true if the failsafe is currently engaged
+    pub fn is_engaged(&self) -> bool {
+        self.engaged
+    }
+
+    // Update the failsafe state with the current temperature,
+    // return the new state if it changed
+    pub fn update(&mut self, temp: u32) -> Option<bool> {
+        let threshold = self.threshold?;
+
+        let engaged = if self.engaged {
+            temp + self.hysteresis > threshold
+        } else {
+            temp >= threshold
+        };
+
+        if engaged == self.engaged {
+            return None;
+        }
+
+        self.engaged = engaged;
+        Some(engaged)
+    }
+}
@@ -7,4 +7,7 @@ pub mod devices_manager;
 pub mod logger;
 pub mod gpu_device;
 pub mod errors;
+pub mod runtime_state;
+pub mod overclock_guard;
+pub mod systemd;
 
@@ -4,13 +4,26 @@
 // TODO: Add second channel to DBus service to be 
 // receive notification about data updates
 
+use std::time::{Duration, Instant};
+
 use anyhow::Result;
 use mossd::{
     arg_parser::ArgsOptions, config_manager::ConfigManager,
-    dbus_service::DBusService, devices_manager::DevicesManager, logger,
-    state_manager::StateManager,
+    dbus_service::DBusService, devices_manager::DevicesManager,
+    gpu_device::{
+        simulated_device::ThermalModel, thermal_failsafe::FailsafeSettings,
+    },
+    logger, overclock_guard::OverclockGuard, state_manager::StateManager,
+    systemd::{self, Watchdog},
+};
+use tokio::{
+    select,
+    signal::{
+        ctrl_c,
+        unix::{SignalKind, signal},
+    },
+    sync::{mpsc, watch},
 };
-use tokio::{select, signal::ctrl_c, sync::mpsc};
 use tokio_util::{sync::CancellationToken, task::TaskTracker};
 
 #[tokio::main]
@@ -20,6 +33,29 @@ async fn main() -> Result<()> {
     // Parse the command line arguments
     let args_options = ArgsOptions::parse();
 
+    // A zero temperature use the thresholds of each device
+    let failsafe_settings = FailsafeSettings {
+        temp: Some(args_options.failsafe_temp).filter(|temp| *temp > 0),
+        hysteresis: args_options.failsafe_hysteresis,
+        drop_power_limit: args_options.failsafe_power_drop,
+        auto_on_fan_stall: args_options.auto_on_fan_stall,
+    };
+
+    // The devices recorded in the state file are restored when the
+    // devices manager is created, then released without starting it
+    if args_options.recover {
+        let mut devices_manager = DevicesManager::new(
+            &args_options.sysfs_root,
+            0,
+            ThermalModel::default(),
+            None,
+            failsafe_settings,
+            &args_options.state_file_path,
+        );
+
+        return Ok(devices_manager.release_devices()?);
+    }
+
     // This token and tracker will be used to handle graceful shutdown
     let tracker = TaskTracker::new();
     let token = CancellationToken::new();
@@ -33,36 +69,76 @@ async fn main() -> Result<()> {
     {
         let token = token.clone();
         let tx_err = tx_err.clone();
+        let config_file_path = args_options.config_file_path.clone();
 
         tracker.spawn(async move {
-            let mut config_manager =
-                ConfigManager::new(&args_options.config_file_path);
+            let mut config_manager = ConfigManager::new(&config_file_path);
 
             config_manager.run(token, rx_config_manager, tx_err).await;
         });
     }
 
     // Start the GPUs manager
+    // The events channel notify the state manager about
+    // the GPUs added or removed at runtime, the heartbeat
+    // channel tell the watchdog when the next fan update is due
     let (tx_gpus_manager, rx_gpus_manager) = mpsc::channel(16);
+    let (tx_gpus_event, rx_gpus_event) = mpsc::channel(16);
+    let (tx_heartbeat, rx_heartbeat) = watch::channel(Instant::now());
     {
         let token = token.clone();
         let tx_err = tx_err.clone();
 
+        // A zero interval disable the devices discovery
+        let discovery_interval = Some(args_options.discovery_interval)
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs);
+
         tracker.spawn(async move {
-            let mut devices_manager = DevicesManager::new();
-            devices_manager.run(token, rx_gpus_manager, tx_err).await;
+            let mut devices_manager = DevicesManager::new(
+                &args_options.sysfs_root,
+                args_options.simulated_gpus,
+                args_options.thermal_model,
+                discovery_interval,
+                failsafe_settings,
+                &args_options.state_file_path,
+            );
+            devices_manager
+                .run(
+                    token,
+                    rx_gpus_manager,
+                    tx_gpus_event,
+                    tx_heartbeat,
+                    tx_err,
+                )
+                .await;
+        });
+    }
+
+    // Start the systemd watchdog if enabled for the service,
+    // it's only pinged while the GPUs manager is progressing
+    if let Some(watchdog) = Watchdog::from_env() {
+        let token = token.clone();
+
+        tracker.spawn(async move {
+            watchdog.run(token, rx_heartbeat).await;
         });
     }
 
     // Start the D-Bus service
+    // The events channel notify the D-Bus service
+    // about the GPUs added or removed at runtime
     let (tx_dbus_service, rx_dbus_service) = mpsc::channel(16);
+    let (tx_dbus_event, rx_dbus_event) = mpsc::channel(16);
     {
         let token = token.clone();
         let tx_err = tx_err.clone();
 
         tracker.spawn(async move {
             let mut dbus_service = DBusService::new();
-            dbus_service.run(token, tx_dbus_service, tx_err).await;
+            dbus_service
+                .run(token, tx_dbus_service, rx_dbus_event, tx_err)
+                .await;
         });
     }
 
@@ -70,24 +146,40 @@ async fn main() -> Result<()> {
     {
         let token = token.clone();
 
+        // The overclocks left pending by the last boot are quarantined
+        let overclock_guard = OverclockGuard::new(
+            &args_options.overclock_guard_path,
+            Duration::from_secs(args_options.overclock_stable_time),
+        );
+
         tracker.spawn(async move {
             let mut state_manager = StateManager::new(
                 tx_config_manager,
                 tx_gpus_manager,
+                rx_gpus_event,
                 rx_dbus_service,
+                tx_dbus_event,
+                overclock_guard,
             );
 
             state_manager.run(token, rx_err).await;
         });
     }
 
-    // TODO: Handle different unix signal for graceful termination
+    // Systemd stop the service with SIGTERM
+    let mut sigterm = signal(SignalKind::terminate())?;
+
     select! {
         _ = ctrl_c() => {
 
+        },
+        _ = sigterm.recv() => {
+
         },
     }
 
+    systemd::notify_stopping();
+
     // Cancel the token to communicate the program
     // termination to the running tasks
     token.cancel();
@@ -0,0 +1,234 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File},
+    io::{BufReader, ErrorKind},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::{error, info, warn};
+
+use crate::runtime_state::write_json_atomic;
+
+// Default location of the guard file, it must
+// survive a reboot unlike the runtime state
+pub const DEFAULT_GUARD_FILE: &str = "/var/lib/mossd/overclock_guard.json";
+// Default time an overclock must run before it is considered stable
+pub const DEFAULT_STABLE_TIME: Duration = Duration::from_secs(300);
+
+// Identifier of the current boot, changed by the kernel on every boot
+const BOOT_ID_PATH: &str = "/proc/sys/kernel/random/boot_id";
+
+// Alias the result type for this module
+type Result<T> = std::result::Result<T, OverclockGuardError>;
+
+// Overclock guard errors enum
+#[derive(Debug, Error)]
+pub enum OverclockGuardError {
+    #[error("Overclock guard IO error: ({file}) {reason} - {error}")]
+    IO {
+        file: PathBuf,
+        reason: String,
+        error: anyhow::Error,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct OverclockGuardJson {
+    // Boot during which the pending overclocks were applied
+    pub boot_id: String,
+
+    pub pending: Vec<String>,
+    pub quarantined: Vec<String>,
+}
+
+// Protect against overclocks crashing the machine on every boot, an
+// overclock is pending from the moment it is applied until it ran for
+// the stable time, a pending overclock left by a previous boot is
+// quarantined and the clock offsets of the device are no longer
+// applied until the quarantine is cleared
+pub struct OverclockGuard {
+    path: PathBuf,
+    boot_id: String,
+    stable_time: Duration,
+
+    // Instant each pending overclock was applied by UUID
+    pending: HashMap<String, Instant>,
+    quarantined: HashSet<String>,
+}
+
+impl OverclockGuard {
+    // Load the guard file, the overclocks still pending from a
+    // previous boot are quarantined, a file that can't be read
+    // is reported and the guard starts without quarantine
+    pub fn new(path: &Path, stable_time: Duration) -> Self {
+        let boot_id = fs::read_to_string(BOOT_ID_PATH)
+            .map(|id| id.trim().to_string())
+            .unwrap_or_default();
+
+        let mut guard = Self {
+            path: path.to_path_buf(),
+            boot_id,
+            stable_time,
+
+            pending: HashMap::new(),
+            quarantined: HashSet::new(),
+        };
+
+        let guard_json = match guard.load() {
+            Ok(guard_json) => guard_json,
+            Err(e) => {
+                error!("Failed to load overclock guard: {}", e);
+                return guard;
+            }
+        };
+
+        guard.quarantined.extend(guard_json.quarantined);
+
+        if guard_json.boot_id != guard.boot_id {
+            for uuid in guard_json.pending {
+                error!(
+                    "Overclock of \"{}\" was never confirmed stable before \
+                    the last reboot, its clock offsets are quarantined \
+                    until the quarantine is cleared",
+                    uuid
+                );
+
+                guard.quarantined.insert(uuid);
+            }
+        } else {
+            // The daemon restarted during the same boot, the
+            // overclocks didn't crash the machine so far
+            let now = Instant::now();
+            guard
+                .pending
+                .extend(guard_json.pending.into_iter().map(|uuid| (uuid, now)));
+        }
+
+        if let Err(e) = guard.save() {
+            error!("Failed to save overclock guard: {}", e);
+        }
+
+        guard
+    }
+
+    // Return true if the overclock of the given device is quarantined
+    pub fn is_quarantined(&self, uuid: &str) -> bool {
+        self.quarantined.contains(uuid)
+    }
+
+    // Allow the clock offsets of the given device to be applied again
+    pub fn clear_quarantine(&mut self, uuid: &str) -> Result<()> {
+        if !self.quarantined.remove(uuid) {
+            return Ok(());
+        }
+
+        info!("Overclock quarantine of \"{}\" cleared", uuid);
+
+        self.save()
+    }
+
+    // Mark the overclock of the given device as pending,
+    // this must be saved before the overclock is applied
+    pub fn mark_pending(&mut self, uuid: &str) -> Result<()> {
+        self.pending.insert(uuid.to_string(), Instant::now());
+        self.save()
+    }
+
+    // Forget the overclock of the given device, the
+    // device no longer run with clock offsets
+    pub fn remove_pending(&mut self, uuid: &str) -> Result<()> {
+        if self.pending.remove(uuid).is_none() {
+            return Ok(());
+        }
+
+        self.save()
+    }
+
+    // Return the instant the next pending overclock is confirmed
+    pub fn next_confirmation(&self) -> Option<Instant> {
+        self.pending
+            .values()
+            .min()
+            .map(|applied| *applied + self.stable_time)
+    }
+
+    // Confirm the pending overclocks that ran for the stable time
+    pub fn confirm_stable(&mut self) -> Result<()> {
+        let stable: Vec<String> = self
+            .pending
+            .iter()
+            .filter(|(_, applied)| applied.elapsed() >= self.stable_time)
+            .map(|(uuid, _)| uuid.clone())
+            .collect();
+
+        if stable.is_empty() {
+            return Ok(());
+        }
+
+        for uuid in stable {
+            info!("Overclock of \"{}\" confirmed stable", uuid);
+            self.pending.remove(&uuid);
+        }
+
+        self.save()
+    }
+
+    // Forget every pending overclock when the daemon exit
+    // cleanly, the overclocks didn't crash the machine
+    pub fn clear_pending(&mut self) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        warn!("Quitting before the overclocks were confirmed stable");
+
+        self.pending.clear();
+        self.save()
+    }
+
+    // Read the guard file, an empty guard is returned if it doesn't exist
+    fn load(&self) -> Result<OverclockGuardJson> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Ok(OverclockGuardJson::default());
+            }
+            Err(e) => {
+                return Err(OverclockGuardError::IO {
+                    file: self.path.clone(),
+                    reason: "Failed to open overclock guard file".to_string(),
+                    error: e.into(),
+                });
+            }
+        };
+
+        serde_json::from_reader(BufReader::new(file)).map_err(|e| {
+            OverclockGuardError::IO {
+                file: self.path.clone(),
+                reason: "Failed to parse overclock guard file".to_string(),
+                error: e.into(),
+            }
+        })
+    }
+
+    // Write the pending and quarantined overclocks to the guard file
+    fn save(&self) -> Result<()> {
+        let guard_json = OverclockGuardJson {
+            boot_id: self.boot_id.clone(),
+
+            pending: self.pending.keys().cloned().collect(),
+            quarantined: self.quarantined.iter().cloned().collect(),
+        };
+
+        write_json_atomic(&self.path, &guard_json).map_err(|e| {
+            OverclockGuardError::IO {
+                file: self.path.clone(),
+                reason: "Failed to write overclock guard file".to_string(),
+                error: e.into(),
+            }
+        })
+    }
+}
@@ -0,0 +1,186 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufReader, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tracing::debug;
+
+use crate::gpu_device::gpu_config::OriginalSettings;
+
+// Default location of the runtime state file, /run is cleared
+// on reboot when the drivers reset the devices anyway
+pub const DEFAULT_STATE_FILE: &str = "/run/mossd/state.json";
+
+// Alias the result type for this module
+type Result<T> = std::result::Result<T, RuntimeStateError>;
+
+// Runtime state errors enum
+#[derive(Debug, Error)]
+pub enum RuntimeStateError {
+    #[error("Runtime state IO error: ({file}) {reason} - {error}")]
+    IO {
+        file: PathBuf,
+        reason: String,
+        error: anyhow::Error,
+    },
+    #[error("Runtime state Json error: ({file}) {reason} - {error}")]
+    Json {
+        file: PathBuf,
+        reason: String,
+        error: anyhow::Error,
+    },
+}
+
+// Original settings of a device taken over by the daemon
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct DeviceStateJson {
+    pub uuid: String,
+    pub original: OriginalSettings,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct RuntimeStateJson {
+    pub devices: Vec<DeviceStateJson>,
+}
+
+// Record the devices taken over by the daemon and their original
+// settings, the file is only left behind if the daemon didn't exit
+// cleanly, the next instance then restore the recorded settings
+pub struct RuntimeState {
+    path: PathBuf,
+
+    // Original settings of the recorded devices by UUID
+    devices: HashMap<String, OriginalSettings>,
+}
+
+impl RuntimeState {
+    // Create a state recording no device, saved to the given file
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            devices: HashMap::new(),
+        }
+    }
+
+    // Load the devices recorded in the given state file,
+    // no device is recorded if the file doesn't exist
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut state = Self::new(path);
+
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(state),
+            Err(e) => {
+                return Err(RuntimeStateError::IO {
+                    file: path.to_path_buf(),
+                    reason: "Failed to open runtime state file".to_string(),
+                    error: e.into(),
+                });
+            }
+        };
+
+        let state_json: RuntimeStateJson =
+            serde_json::from_reader(BufReader::new(file)).map_err(|e| {
+                RuntimeStateError::Json {
+                    file: path.to_path_buf(),
+                    reason: "Failed to parse runtime state file".to_string(),
+                    error: e.into(),
+                }
+            })?;
+
+        for device in state_json.devices {
+            state.devices.insert(device.uuid, device.original);
+        }
+
+        Ok(state)
+    }
+
+    // Return the UUIDs of the recorded devices
+    pub fn uuids(&self) -> Vec<String> {
+        self.devices.keys().cloned().collect()
+    }
+
+    // Return the recorded original settings of the given device
+    pub fn get(&self, uuid: &str) -> Option<&OriginalSettings> {
+        self.devices.get(uuid)
+    }
+
+    // Record the original settings of the given device
+    pub fn insert(
+        &mut self,
+        uuid: &str,
+        settings: OriginalSettings,
+    ) -> Result<()> {
+        self.devices.insert(uuid.to_string(), settings);
+        self.save()
+    }
+
+    // Stop recording the given device
+    pub fn remove(&mut self, uuid: &str) -> Result<()> {
+        if self.devices.remove(uuid).is_none() {
+            return Ok(());
+        }
+
+        self.save()
+    }
+
+    // Write the recorded devices to the state file,
+    // the file is removed once no device is recorded
+    fn save(&self) -> Result<()> {
+        debug!("Saving runtime state to: {:?}", self.path);
+
+        let io_error =
+            |reason: &str, e: std::io::Error| RuntimeStateError::IO {
+                file: self.path.clone(),
+                reason: reason.to_string(),
+                error: e.into(),
+            };
+
+        if self.devices.is_empty() {
+            return match fs::remove_file(&self.path) {
+                Err(e) if e.kind() != ErrorKind::NotFound => {
+                    Err(io_error("Failed to remove runtime state file", e))
+                }
+                _ => Ok(()),
+            };
+        }
+
+        let state_json = RuntimeStateJson {
+            devices: self
+                .devices
+                .iter()
+                .map(|(uuid, original)| DeviceStateJson {
+                    uuid: uuid.clone(),
+                    original: original.clone(),
+                })
+                .collect(),
+        };
+
+        write_json_atomic(&self.path, &state_json)
+            .map_err(|e| io_error("Failed to write runtime state file", e))
+    }
+}
+
+// Write the given value as Json to a temporary file then rename it
+// over the given path, so a crash never leaves the file half written
+// The parent directory is created if needed
+pub fn write_json_atomic<T: Serialize>(
+    path: &Path,
+    value: &T,
+) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let tmp_path = path.with_extension("tmp");
+    let file = File::create(&tmp_path)?;
+
+    serde_json::to_writer_pretty(&file, value)?;
+    file.sync_all()?;
+
+    fs::rename(&tmp_path, path)
+}
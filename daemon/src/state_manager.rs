@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::anyhow;
 use thiserror::Error;
@@ -15,14 +15,18 @@ use tracing::{error, warn};
 
 use crate::{
     config_manager::{ConfigMessage, ConfigMessageAnswer},
-    dbus_service::{DBusServiceAnswer, DBusServiceMessage},
-    devices_manager::{DevicesManagerAnswer, DevicesManagerMessage},
+    dbus_service::{DBusServiceAnswer, DBusServiceEvent, DBusServiceMessage},
+    devices_manager::{
+        DevicesManagerAnswer, DevicesManagerEvent, DevicesManagerMessage,
+    },
     errors::MossdError,
     fan_curve::{
-        fan_curve_info::FanCurveInfo, fan_mode::FanMode,
-        hysteresis_curve::HysteresisCurve, linear_curve::LinearCurve,
+        fan_curve_info::FanCurveInfo,
+        fan_mode::{FanMode, FanSelection},
     },
-    gpu_device::gpu_config::GpuConfig,
+    gpu_device::{gpu_config::GpuConfig, sensor_fault::SensorFaultPolicy},
+    overclock_guard::{OverclockGuard, OverclockGuardError},
+    systemd,
 };
 
 macro_rules! extract_answer {
@@ -52,24 +56,38 @@ pub enum StateManagerError {
     },
     #[error("State manager invalid response error: {reason}")]
     InvalidResponse { reason: String },
+    #[error(transparent)]
+    OverclockGuard(#[from] OverclockGuardError),
 }
 
 pub struct StateManager {
     tx_config_manager: Sender<ConfigMessage>,
     tx_devices_manager: Sender<DevicesManagerMessage>,
+    rx_devices_manager: Receiver<DevicesManagerEvent>,
     rx_dbus_service: Receiver<DBusServiceMessage>,
+    tx_dbus_service: Sender<DBusServiceEvent>,
+
+    // Quarantine the overclocks that crashed the machine
+    overclock_guard: OverclockGuard,
 }
 
 impl StateManager {
     pub fn new(
         tx_config_manager: Sender<ConfigMessage>,
         tx_devices_manager: Sender<DevicesManagerMessage>,
+        rx_devices_manager: Receiver<DevicesManagerEvent>,
         rx_dbus_service: Receiver<DBusServiceMessage>,
+        tx_dbus_service: Sender<DBusServiceEvent>,
+        overclock_guard: OverclockGuard,
     ) -> Self {
         Self {
             tx_config_manager,
             tx_devices_manager,
+            rx_devices_manager,
             rx_dbus_service,
+            tx_dbus_service,
+
+            overclock_guard,
         }
     }
 
@@ -84,11 +102,30 @@ impl StateManager {
             self.parse_error(Some(e.into()));
         }
 
+        // The fans are under control from now on
+        systemd::notify_ready();
+
         loop {
+            let next_confirmation = self.overclock_guard.next_confirmation();
+
             select! {
                 _ = run_token.cancelled() => {
+                    if let Err(e) = self.overclock_guard.clear_pending() {
+                        let err = StateManagerError::from(e);
+                        self.parse_error(Some(err.into()));
+                    }
+
                     break;
                 }
+                // Confirm the overclocks that ran long enough
+                _ = tokio::time::sleep_until(
+                    next_confirmation.unwrap_or_else(Instant::now).into()
+                ), if next_confirmation.is_some() => {
+                    if let Err(e) = self.overclock_guard.confirm_stable() {
+                        let err = StateManagerError::from(e);
+                        self.parse_error(Some(err.into()));
+                    }
+                }
                 err_message = rx_err.recv() => {
                     self.parse_error(err_message);
                 }
@@ -97,6 +134,11 @@ impl StateManager {
                         // TODO: Handle parse errors
                     }
                 }
+                Some(event) = self.rx_devices_manager.recv() => {
+                    if let Err(e) = self.parse_devices_event(event).await {
+                        self.parse_error(Some(e.into()));
+                    }
+                }
             }
         }
     }
@@ -195,6 +237,72 @@ impl StateManager {
                         DBusServiceAnswer::GpuVendorInfo(device_vendor_info),
                     ))
                 }
+                DBusServiceMessage::GetFanCurves {
+                    uuid,
+                    tx: tx_answer,
+                } => {
+                    let (tx, rx) = oneshot::channel();
+                    let message =
+                        DevicesManagerMessage::GetDeviceFanCurves { uuid, tx };
+                    let answer = self.query_device_manager(message, rx).await?;
+
+                    let fan_curves = extract_answer!(
+                        DevicesManagerAnswer::DeviceFanCurves,
+                        answer
+                    )?;
+
+                    Some((tx_answer, DBusServiceAnswer::FanCurves(fan_curves)))
+                }
+                DBusServiceMessage::GetOverclockQuarantined {
+                    uuid,
+                    tx: tx_answer,
+                } => {
+                    let quarantined =
+                        self.overclock_guard.is_quarantined(&uuid);
+
+                    Some((
+                        tx_answer,
+                        DBusServiceAnswer::OverclockQuarantined(quarantined),
+                    ))
+                }
+                DBusServiceMessage::ClearOverclockQuarantine { uuid } => {
+                    self.overclock_guard.clear_quarantine(&uuid)?;
+
+                    self.send_dbus_event(
+                        DBusServiceEvent::OverclockQuarantineChanged {
+                            uuid: uuid.clone(),
+                            quarantined: false,
+                        },
+                    )
+                    .await?;
+
+                    // Apply the clock offsets of the profile again
+                    let (tx, rx) = oneshot::channel();
+                    let message = ConfigMessage::GetConfig {
+                        uuid: uuid.clone(),
+                        tx,
+                    };
+
+                    let answer = self.query_config_manager(message, rx).await?;
+                    let config =
+                        extract_answer!(ConfigMessageAnswer::Config, answer)?;
+
+                    self.apply_config(&uuid, config).await?;
+
+                    None
+                }
+                DBusServiceMessage::ResetGpu { uuid } => {
+                    let message = DevicesManagerMessage::ResetDevice { uuid };
+
+                    self.tx_devices_manager.send(message).await.map_err(
+                        |_| StateManagerError::TX {
+                            reason: "Failed to send request to devices manager"
+                                .to_string(),
+                        },
+                    )?;
+
+                    None
+                }
             };
 
             // Send the message to channel if needed
@@ -208,6 +316,56 @@ impl StateManager {
         Ok(())
     }
 
+    // Configure the devices added at runtime, update the D-Bus
+    // objects and forward the thermal failsafe and fans health changes
+    async fn parse_devices_event(
+        &mut self,
+        event: DevicesManagerEvent,
+    ) -> Result<()> {
+        let dbus_event = match event {
+            DevicesManagerEvent::DeviceAdded { uuid } => {
+                // Publish the device even if its settings fail to apply
+                let result = self.apply_device_settings(&uuid).await;
+
+                self.send_dbus_event(DBusServiceEvent::GpuAdded { uuid })
+                    .await?;
+
+                return result;
+            }
+            DevicesManagerEvent::DeviceRemoved { uuid } => {
+                DBusServiceEvent::GpuRemoved { uuid }
+            }
+            DevicesManagerEvent::FailsafeEngaged { uuid, temp } => {
+                DBusServiceEvent::FailsafeChanged {
+                    uuid,
+                    engaged: true,
+                    temp,
+                }
+            }
+            DevicesManagerEvent::FailsafeReleased { uuid, temp } => {
+                DBusServiceEvent::FailsafeChanged {
+                    uuid,
+                    engaged: false,
+                    temp,
+                }
+            }
+            DevicesManagerEvent::FanHealthChanged { uuid, fan, health } => {
+                DBusServiceEvent::FanHealthChanged { uuid, fan, health }
+            }
+        };
+
+        self.send_dbus_event(dbus_event).await
+    }
+
+    // Notify the D-Bus service about a change in the devices
+    async fn send_dbus_event(&mut self, event: DBusServiceEvent) -> Result<()> {
+        self.tx_dbus_service.send(event).await.map_err(|_| {
+            StateManagerError::TX {
+                reason: "Failed to send event to D-Bus service".to_string(),
+            }
+        })
+    }
+
     // Query the configuration manager about the current settings
     // and applies them to the various devices at start-up
     async fn apply_settings(&mut self) -> Result<()> {
@@ -225,75 +383,122 @@ impl StateManager {
 
         // Request and apply the configuration information for every GPUs
         for uuid in uuids {
-            // Query the configuration manager for the fan curve
-            let (tx, rx) = oneshot::channel();
-            let message = ConfigMessage::GetFanCurve {
-                uuid: uuid.clone(),
-                tx,
-            };
+            self.apply_device_settings(&uuid).await?;
+        }
+
+        Ok(())
+    }
 
-            let answer = self.query_config_manager(message, rx).await?;
-            let fan_curve_info =
-                extract_answer!(ConfigMessageAnswer::FanCurve, answer)?;
+    // Query the configuration manager about the
+    // settings of a device and applies them
+    async fn apply_device_settings(&mut self, uuid: &str) -> Result<()> {
+        // Query the configuration manager for the fan curve
+        let (tx, rx) = oneshot::channel();
+        let message = ConfigMessage::GetFanCurve {
+            uuid: uuid.to_string(),
+            tx,
+        };
 
-            // Apply the fan curve settings
-            self.apply_fan_curve(&uuid, fan_curve_info).await?;
+        let answer = self.query_config_manager(message, rx).await?;
+        let fan_curve_info =
+            extract_answer!(ConfigMessageAnswer::FanCurve, answer)?;
 
-            // Query the configuration manager for the fan update interval
-            let (tx, rx) = oneshot::channel();
-            let message = ConfigMessage::GetFanUpdateInterval {
-                uuid: uuid.clone(),
-                tx,
-            };
+        // Apply the fan curve settings
+        self.apply_fan_curve(uuid, FanSelection::All, fan_curve_info)
+            .await?;
 
-            let answer = self.query_config_manager(message, rx).await?;
-            let update_interval = extract_answer!(
-                ConfigMessageAnswer::FanUpdateInterval,
-                answer
-            )?;
+        // Query the configuration manager for the fan update interval
+        let (tx, rx) = oneshot::channel();
+        let message = ConfigMessage::GetFanUpdateInterval {
+            uuid: uuid.to_string(),
+            tx,
+        };
 
-            // Apply the fan curve settings
-            self.apply_fan_update_interval(&uuid, update_interval)
-                .await?;
+        let answer = self.query_config_manager(message, rx).await?;
+        let update_interval =
+            extract_answer!(ConfigMessageAnswer::FanUpdateInterval, answer)?;
 
-            // Query the configuration manager for the fan mode
-            let (tx, rx) = oneshot::channel();
-            let message = ConfigMessage::GetFanMode {
-                uuid: uuid.clone(),
-                tx,
-            };
+        // Apply the fan curve settings
+        self.apply_fan_update_interval(uuid, update_interval)
+            .await?;
 
-            let answer = self.query_config_manager(message, rx).await?;
-            let fan_mode =
-                extract_answer!(ConfigMessageAnswer::FanMode, answer)?;
+        // Query the configuration manager for the sensor fault policy,
+        // applied before the fan mode uses the temperature
+        let (tx, rx) = oneshot::channel();
+        let message = ConfigMessage::GetSensorFaultPolicy {
+            uuid: uuid.to_string(),
+            tx,
+        };
 
-            // Apply the fan mode
-            self.apply_fan_mode(&uuid, fan_mode).await?;
+        let answer = self.query_config_manager(message, rx).await?;
+        let policy =
+            extract_answer!(ConfigMessageAnswer::SensorFaultPolicy, answer)?;
 
-            // Query the configuration manager for the fan update interval
-            let (tx, rx) = oneshot::channel();
-            let message = ConfigMessage::GetConfig {
-                uuid: uuid.clone(),
-                tx,
-            };
+        self.apply_sensor_fault_policy(uuid, policy).await?;
+
+        // Query the configuration manager for the fan mode
+        let (tx, rx) = oneshot::channel();
+        let message = ConfigMessage::GetFanMode {
+            uuid: uuid.to_string(),
+            tx,
+        };
+
+        let answer = self.query_config_manager(message, rx).await?;
+        let fan_mode = extract_answer!(ConfigMessageAnswer::FanMode, answer)?;
 
-            let answer = self.query_config_manager(message, rx).await?;
-            let config = extract_answer!(ConfigMessageAnswer::Config, answer)?;
+        // Apply the fan mode
+        self.apply_fan_mode(uuid, FanSelection::All, fan_mode)
+            .await?;
+
+        // Query the configuration manager for the per fan assignments
+        let (tx, rx) = oneshot::channel();
+        let message = ConfigMessage::GetFanAssignments {
+            uuid: uuid.to_string(),
+            tx,
+        };
+
+        let answer = self.query_config_manager(message, rx).await?;
+        let fan_assignments =
+            extract_answer!(ConfigMessageAnswer::FanAssignments, answer)?;
+
+        // Apply the per fan curves and modes on top
+        // of the settings for all the fans
+        for assignment in fan_assignments {
+            let fans = FanSelection::Index(assignment.index);
+
+            self.apply_fan_curve(uuid, fans, assignment.fan_curve)
+                .await?;
 
-            // Apply the fan curve settings
-            self.apply_config(&uuid, config).await?;
+            if let Some(fan_mode) = assignment.fan_mode {
+                self.apply_fan_mode(uuid, fans, fan_mode).await?;
+            }
         }
 
+        // Query the configuration manager for the fan update interval
+        let (tx, rx) = oneshot::channel();
+        let message = ConfigMessage::GetConfig {
+            uuid: uuid.to_string(),
+            tx,
+        };
+
+        let answer = self.query_config_manager(message, rx).await?;
+        let config = extract_answer!(ConfigMessageAnswer::Config, answer)?;
+
+        // Apply the fan curve settings
+        self.apply_config(uuid, config).await?;
+
         Ok(())
     }
 
     async fn apply_fan_mode(
         &mut self,
         uuid: &str,
+        fans: FanSelection,
         fan_mode: FanMode,
     ) -> Result<()> {
         let message = DevicesManagerMessage::SetDeviceFanMode {
             uuid: uuid.to_string(),
+            fans,
             fan_mode,
         };
 
@@ -306,10 +511,11 @@ impl StateManager {
         Ok(())
     }
 
-    // Apply the fan curve to the device
+    // Apply the fan curve to the selected device fans
     async fn apply_fan_curve(
         &mut self,
         uuid: &str,
+        fans: FanSelection,
         curve_info_opt: Option<FanCurveInfo>,
     ) -> Result<()> {
         // Only apply fan curve settings if the config manager
@@ -317,12 +523,11 @@ impl StateManager {
         if let Some(fan_curve_info) = curve_info_opt {
             // Generate the actual fan curve to
             // then pass to the devices manager
-            let fan_curve = Box::new(
-                HysteresisCurve::<LinearCurve>::from_info(&fan_curve_info),
-            );
+            let fan_curve = fan_curve_info.build_curve();
 
             let message = DevicesManagerMessage::SetDeviceFanCurve {
                 uuid: uuid.to_string(),
+                fans,
                 fan_curve,
             };
 
@@ -363,28 +568,61 @@ impl StateManager {
         Ok(())
     }
 
+    async fn apply_sensor_fault_policy(
+        &mut self,
+        uuid: &str,
+        policy: SensorFaultPolicy,
+    ) -> Result<()> {
+        let message = DevicesManagerMessage::SetDeviceSensorFaultPolicy {
+            uuid: uuid.to_string(),
+            policy,
+        };
+
+        self.tx_devices_manager.send(message).await.map_err(|_| {
+            StateManagerError::TX {
+                reason: "Failed to send request to devices manager".to_string(),
+            }
+        })?;
+
+        Ok(())
+    }
+
     async fn apply_config(
         &mut self,
         uuid: &str,
         config_opt: Option<GpuConfig>,
     ) -> Result<()> {
-        // Only apply config settings if the config manager
-        // returned a config profile
-        if let Some(config) = config_opt {
-            let message = DevicesManagerMessage::ApplyDeviceGpuConfig {
-                uuid: uuid.to_string(),
-                config,
-            };
+        // Without a config profile the device
+        // settings are restored to their original value
+        let mut config = config_opt.unwrap_or_default();
+
+        // The overclock is marked as pending before it is applied,
+        // a quarantined device keep its original offsets instead
+        if !config.has_overclock() {
+            self.overclock_guard.remove_pending(uuid)?;
+        } else if self.overclock_guard.is_quarantined(uuid) {
+            error!(
+                "Overclock of \"{}\" is quarantined, skipping its clock \
+                offsets until the quarantine is cleared",
+                uuid
+            );
 
-            self.tx_devices_manager.send(message).await.map_err(|_| {
-                StateManagerError::TX {
-                    reason: format!(
-                        "Failed to send request to devices manager"
-                    ),
-                }
-            })?;
+            config = config.without_overclock();
+        } else {
+            self.overclock_guard.mark_pending(uuid)?;
         }
 
+        let message = DevicesManagerMessage::ApplyDeviceGpuConfig {
+            uuid: uuid.to_string(),
+            config,
+        };
+
+        self.tx_devices_manager.send(message).await.map_err(|_| {
+            StateManagerError::TX {
+                reason: "Failed to send request to devices manager".to_string(),
+            }
+        })?;
+
         Ok(())
     }
 
@@ -0,0 +1,106 @@
+use std::time::{Duration, Instant};
+
+use sd_notify::NotifyState;
+use tokio::{select, sync::watch};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::devices_manager::HEARTBEAT_INTERVAL;
+
+// Notify systemd that the daemon finished starting up, the status
+// is kept up to date by the devices manager
+pub fn notify_ready() {
+    notify(&[NotifyState::Ready]);
+}
+
+// Update the status shown by systemctl
+pub fn notify_status(status: &str) {
+    notify(&[NotifyState::Status(status)]);
+}
+
+// Notify systemd that the daemon is shutting down
+pub fn notify_stopping() {
+    notify(&[
+        NotifyState::Stopping,
+        NotifyState::Status("Restoring the GPUs settings"),
+    ]);
+}
+
+// Send the given states to systemd, nothing is sent
+// if the daemon wasn't started by systemd
+fn notify(state: &[NotifyState]) {
+    if let Err(err) = sd_notify::notify(false, state) {
+        warn!("Failed to notify systemd: {}", err);
+    }
+}
+
+// Ping the systemd watchdog while the devices manager loop is
+// progressing, systemd then restart the daemon if it get stuck
+pub struct Watchdog {
+    // Time after which systemd consider the daemon hung
+    timeout: Duration,
+}
+
+impl Watchdog {
+    // Return None if the watchdog isn't enabled for the service
+    pub fn from_env() -> Option<Self> {
+        let mut usec = 0;
+
+        if !sd_notify::watchdog_enabled(false, &mut usec) || usec == 0 {
+            return None;
+        }
+
+        Some(Self {
+            timeout: Duration::from_micros(usec),
+        })
+    }
+
+    // Ping the watchdog twice per timeout, the ping is skipped while
+    // the devices manager is late on its heartbeat deadline, the
+    // deadline move forward every time the fans are updated
+    pub async fn run(
+        &self,
+        run_token: CancellationToken,
+        rx_heartbeat: watch::Receiver<Instant>,
+    ) {
+        info!("Systemd watchdog: Running, timeout {:?}", self.timeout);
+
+        let ping_period = self.timeout / 2;
+        // Leave the devices manager time to complete the fan update
+        let max_delay = ping_period.max(HEARTBEAT_INTERVAL * 2);
+
+        let mut ping_interval = tokio::time::interval(ping_period);
+        let mut stalled = false;
+
+        loop {
+            select! {
+                _ = run_token.cancelled() => {
+                    info!("Systemd watchdog: Quiting");
+
+                    break;
+                }
+                _ = ping_interval.tick() => {
+                    let delay = Instant::now()
+                        .saturating_duration_since(*rx_heartbeat.borrow());
+
+                    if delay <= max_delay {
+                        if stalled {
+                            info!("Devices manager is progressing again");
+                            stalled = false;
+                        }
+
+                        debug!("Pinging systemd watchdog");
+                        notify(&[NotifyState::Watchdog]);
+                    } else if !stalled {
+                        warn!(
+                            "Devices manager stalled for {:?}, \
+                            not pinging the systemd watchdog",
+                            delay
+                        );
+                        stalled = true;
+                    }
+                }
+            }
+        }
+    }
+}
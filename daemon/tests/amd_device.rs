@@ -0,0 +1,228 @@
+mod common;
+
+use std::{fs, path::PathBuf, time::Duration};
+
+use common::FakeSysfs;
+use mossd::fan_curve::fan_mode::{FanMode, FanSelection};
+use mossd::gpu_device::{
+    DeviceError, GpuDevice,
+    amd_device::AmdDevice,
+    gpu_config::{AmdConfig, AmdStockClocks, GpuConfig},
+    gpu_info::GpuVendorInfo,
+    sysfs,
+};
+
+// Overdrive table of a card with every overdrive setting
+const OD_TABLE: &str = "OD_SCLK:
+0: 500Mhz
+1: 2615Mhz
+OD_MCLK:
+0: 97Mhz
+1: 1250MHz
+OD_VDDGFX_OFFSET:
+0mV
+OD_RANGE:
+SCLK:     500Mhz       3150Mhz
+MCLK:     97Mhz       1500Mhz
+";
+
+// Build an AMD card with a single fan under card0 of the tree
+fn new_card(sysfs: &FakeSysfs, od_table: &str) -> PathBuf {
+    let device_path = sysfs.card(0);
+    let hwmon_path = device_path.join("hwmon/hwmon3");
+
+    sysfs.write_all(
+        &device_path,
+        &[
+            ("vendor", "0x1002"),
+            ("device", "0x744c"),
+            ("product_name", "Radeon RX 7900 XTX"),
+            ("uevent", "DRIVER=amdgpu\nPCI_SLOT_NAME=0000:03:00.0\n"),
+            ("current_link_speed", "16.0 GT/s PCIe"),
+            ("current_link_width", "16"),
+            ("mem_info_vram_total", "25753026560"),
+            ("mem_info_vram_used", "1073741824"),
+            ("gpu_busy_percent", "12"),
+            ("pp_od_clk_voltage", od_table),
+        ],
+    );
+    sysfs.write_all(
+        &hwmon_path,
+        &[
+            ("temp1_input", "45000"),
+            ("temp2_input", "52000"),
+            ("temp1_crit", "100000"),
+            ("pwm1", "128"),
+            ("pwm1_enable", "2"),
+            ("pwm1_min", "0"),
+            ("pwm1_max", "255"),
+            ("fan1_input", "1200"),
+            ("power1_cap", "300000000"),
+            ("power1_cap_max", "350000000"),
+            ("power1_cap_min", "200000000"),
+            ("power1_cap_default", "300000000"),
+            ("power1_average", "85000000"),
+        ],
+    );
+
+    device_path
+}
+
+#[test]
+fn cards_are_found_under_the_sysfs_root() {
+    let sysfs = FakeSysfs::new("amd-discovery");
+    let device_path = new_card(&sysfs, OD_TABLE);
+
+    // Connectors are not cards
+    fs::create_dir_all(sysfs.root().join("class/drm/card0-DP-1/device"))
+        .unwrap();
+
+    assert_eq!(
+        sysfs::drm_card_devices(sysfs.root()),
+        vec![device_path.clone()]
+    );
+    assert!(AmdDevice::is_amd_device(&device_path));
+    assert_eq!(AmdDevice::get_uuid(&device_path), "AMD-0000:03:00.0");
+}
+
+#[test]
+fn info_and_data_are_read_from_hwmon() {
+    let sysfs = FakeSysfs::new("amd-data");
+    let device_path = new_card(&sysfs, OD_TABLE);
+
+    let mut device = AmdDevice::new(&device_path).unwrap();
+    let info = device.get_info();
+
+    assert_eq!(info.name, "Radeon RX 7900 XTX");
+    assert_eq!(info.pcie_gen, 4);
+    assert_eq!(info.fan_count, 1);
+    assert_eq!(info.power_limit_max, 350_000);
+    assert_eq!(info.power_limit_min, 200_000);
+    assert!(info.capabilities.fan_control);
+    assert!(info.capabilities.clock_offsets);
+
+    let GpuVendorInfo::AMD { edge_max_temp, .. } = device.get_vendor_info()
+    else {
+        panic!("Expected AMD vendor info");
+    };
+    assert_eq!(edge_max_temp, Some(100));
+
+    let data = device.get_data().unwrap();
+    assert_eq!(data.temp_gpu, Some(45));
+    assert_eq!(data.power_usage, Some(85_000));
+    assert_eq!(data.power_limit, Some(300_000));
+    assert_eq!(data.fans[0].speed, Some(50));
+    assert_eq!(data.fans[0].speed_rpm, Some(1200));
+    assert_eq!(data.core_clock_offset, Some(0));
+}
+
+#[test]
+fn manual_fan_speed_is_written_to_pwm() {
+    let sysfs = FakeSysfs::new("amd-fan");
+    let device_path = new_card(&sysfs, OD_TABLE);
+    let hwmon_path = device_path.join("hwmon/hwmon3");
+
+    let mut device = AmdDevice::new(&device_path).unwrap();
+    device
+        .set_fan_mode(FanSelection::All, FanMode::Manual(50))
+        .unwrap();
+
+    assert_eq!(
+        sysfs::read_string(&hwmon_path.join("pwm1_enable")).unwrap(),
+        "1"
+    );
+    assert_eq!(sysfs::read_string(&hwmon_path.join("pwm1")).unwrap(), "127");
+
+    // Reset give the fan back to the driver
+    device.reset().unwrap();
+    assert_eq!(
+        sysfs::read_string(&hwmon_path.join("pwm1_enable")).unwrap(),
+        "2"
+    );
+}
+
+#[test]
+fn power_limit_is_written_in_microwatts() {
+    let sysfs = FakeSysfs::new("amd-power");
+    let device_path = new_card(&sysfs, OD_TABLE);
+
+    let mut device = AmdDevice::new(&device_path).unwrap();
+    device
+        .apply_gpu_config(GpuConfig {
+            power_limit: Some(250_000),
+            ..Default::default()
+        })
+        .unwrap();
+
+    let power_cap = device_path.join("hwmon/hwmon3/power1_cap");
+    assert_eq!(sysfs::read_string(&power_cap).unwrap(), "250000000");
+}
+
+#[test]
+fn voltage_offset_requires_the_table_section() {
+    let sysfs = FakeSysfs::new("amd-voltage");
+    // Table without clock states nor voltage offset
+    let od_table = "OD_RANGE:\nSCLK:     500Mhz       3150Mhz\n";
+    let device_path = new_card(&sysfs, od_table);
+
+    let mut device = AmdDevice::new(&device_path).unwrap();
+    let result = device.apply_gpu_config(GpuConfig {
+        amd_config: AmdConfig {
+            voltage_offset: Some(-50),
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+
+    assert!(matches!(result, Err(DeviceError::Unsupported { .. })));
+
+    // Nothing was written to the table
+    let od_path = device_path.join("pp_od_clk_voltage");
+    assert_eq!(sysfs::read_string(&od_path).unwrap(), od_table.trim());
+}
+
+#[test]
+fn overdrive_settings_are_committed() {
+    let sysfs = FakeSysfs::new("amd-overdrive");
+    let device_path = new_card(&sysfs, OD_TABLE);
+
+    let mut device = AmdDevice::new(&device_path).unwrap();
+    device.set_data_update_interval(Duration::ZERO);
+    device
+        .apply_gpu_config(GpuConfig {
+            amd_config: AmdConfig {
+                core_clock_offset: Some(100),
+                voltage_offset: Some(-50),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .unwrap();
+
+    // The commit command is the last one written
+    let od_path = device_path.join("pp_od_clk_voltage");
+    assert_eq!(sysfs::read_string(&od_path).unwrap(), "c");
+    assert_eq!(device.get_data().unwrap().core_clock_offset, Some(100));
+}
+
+#[test]
+fn recorded_stock_clocks_replace_the_current_ones() {
+    let sysfs = FakeSysfs::new("amd-stock-clocks");
+
+    // Table left overclocked by an instance that crashed
+    let device_path = new_card(&sysfs, &OD_TABLE.replace("2615Mhz", "2715Mhz"));
+
+    let mut device = AmdDevice::new(&device_path).unwrap();
+    let mut settings = device.get_original_settings();
+    assert_eq!(settings.amd_stock_clocks.core_clock_max, Some(2715));
+
+    settings.amd_stock_clocks = AmdStockClocks {
+        core_clock_max: Some(2615),
+        mem_clock_max: Some(1250),
+    };
+    device.set_original_settings(settings);
+
+    let stock_clocks = device.get_original_settings().amd_stock_clocks;
+    assert_eq!(stock_clocks.core_clock_max, Some(2615));
+    assert_eq!(stock_clocks.mem_clock_max, Some(1250));
+}
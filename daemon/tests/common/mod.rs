@@ -0,0 +1,55 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+// Sysfs tree in a temporary directory, passed to the
+// daemon as sysfs root and removed when dropped
+pub struct FakeSysfs {
+    root: PathBuf,
+}
+
+impl FakeSysfs {
+    // Create an empty tree, the name must be unique among the tests
+    pub fn new(name: &str) -> Self {
+        let root = std::env::temp_dir().join(format!(
+            "mossd-sysfs-{}-{}",
+            name,
+            process::id()
+        ));
+
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("class/drm")).unwrap();
+
+        Self { root }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    // Return the device directory of the given DRM card
+    pub fn card(&self, index: u32) -> PathBuf {
+        self.root.join(format!("class/drm/card{}/device", index))
+    }
+
+    // Write an attribute, creating the missing directories
+    pub fn write(&self, path: &Path, value: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, value).unwrap();
+    }
+
+    // Write every attribute of the given directory
+    pub fn write_all(&self, dir: &Path, attributes: &[(&str, &str)]) {
+        for (name, value) in attributes {
+            self.write(&dir.join(name), value);
+        }
+    }
+}
+
+impl Drop for FakeSysfs {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
@@ -0,0 +1,85 @@
+use std::time::{Duration, Instant};
+
+use mossd::{
+    devices_manager::{DevicesManager, DevicesManagerMessage},
+    gpu_device::{
+        simulated_device::ThermalModel, thermal_failsafe::FailsafeSettings,
+    },
+};
+use tokio::sync::{mpsc, watch};
+use tokio_util::sync::CancellationToken;
+
+// UUID of the first simulated device
+const SIMULATED_UUID: &str = "SIM-0000";
+
+#[tokio::test]
+async fn fan_updates_fire_while_messages_keep_the_loop_busy() {
+    let temp_dir = std::env::temp_dir();
+    let state_file = temp_dir
+        .join(format!("mossd-busy-loop-state-{}.json", std::process::id()));
+
+    // Only the simulated device, no sysfs tree to discover
+    let mut devices_manager = DevicesManager::new(
+        &temp_dir.join("mossd-no-sysfs"),
+        1,
+        ThermalModel::default(),
+        None,
+        FailsafeSettings::default(),
+        &state_file,
+    );
+
+    let run_token = CancellationToken::new();
+    let (tx_message, rx_message) = mpsc::channel(16);
+    let (tx_event, _rx_event) = mpsc::channel(16);
+    let (tx_heartbeat, mut rx_heartbeat) = watch::channel(Instant::now());
+    let (tx_err, _rx_err) = mpsc::channel(16);
+
+    let handle = {
+        let run_token = run_token.clone();
+
+        tokio::spawn(async move {
+            devices_manager
+                .run(run_token, rx_message, tx_event, tx_heartbeat, tx_err)
+                .await;
+        })
+    };
+
+    let fan_update_interval = Duration::from_millis(50);
+    tx_message
+        .send(DevicesManagerMessage::SetDeviceFanUpdateInterval {
+            uuid: SIMULATED_UUID.to_string(),
+            interval: fan_update_interval,
+        })
+        .await
+        .unwrap();
+
+    // Send messages far more often than the fans are updated,
+    // every fan update move the heartbeat deadline forward
+    let start = Instant::now();
+    let mut deadlines = Vec::new();
+
+    while start.elapsed() < Duration::from_millis(500) {
+        tx_message
+            .send(DevicesManagerMessage::SetDeviceDataUpdateInterval {
+                uuid: SIMULATED_UUID.to_string(),
+                interval: Duration::from_secs(1),
+            })
+            .await
+            .unwrap();
+
+        if rx_heartbeat.has_changed().unwrap() {
+            deadlines.push(*rx_heartbeat.borrow_and_update());
+        }
+
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+
+    run_token.cancel();
+    handle.await.unwrap();
+    let _ = std::fs::remove_file(&state_file);
+
+    assert!(deadlines.len() >= 5, "{} fan updates", deadlines.len());
+    assert!(deadlines.windows(2).all(|pair| {
+        pair[1].duration_since(pair[0]) >= fan_update_interval / 2
+    }));
+}
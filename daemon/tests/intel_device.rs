@@ -0,0 +1,188 @@
+mod common;
+
+use std::{fs, path::PathBuf, time::Duration};
+
+use common::FakeSysfs;
+use mossd::fan_curve::fan_mode::{FanMode, FanSelection};
+use mossd::gpu_device::{
+    DeviceError, GpuDevice, gpu_config::GpuConfig, gpu_data::GpuVendorData,
+    gpu_info::GpuVendorInfo, intel_device::IntelDevice, sysfs,
+};
+
+// Build an Intel card driven by the xe driver under card0 of the tree
+fn new_card(sysfs: &FakeSysfs) -> PathBuf {
+    let device_path = sysfs.card(0);
+    let hwmon_path = device_path.join("hwmon/hwmon5");
+
+    sysfs.write_all(
+        &device_path,
+        &[
+            ("vendor", "0x8086"),
+            ("device", "0xe20b"),
+            ("uevent", "DRIVER=xe\nPCI_SLOT_NAME=0000:04:00.0\n"),
+            ("current_link_width", "8"),
+        ],
+    );
+    sysfs.write_all(
+        &hwmon_path,
+        &[
+            ("name", "xe"),
+            ("temp1_label", "vram"),
+            ("temp1_input", "38000"),
+            ("temp2_label", "pkg"),
+            ("temp2_input", "51000"),
+            ("power1_max", "190000000"),
+            ("power1_rated_max", "190000000"),
+            ("power1_min", "50000000"),
+            ("energy1_input", "1000000"),
+            ("fan1_input", "1500"),
+            ("fan2_input", "1450"),
+        ],
+    );
+
+    device_path
+}
+
+#[test]
+fn only_xe_and_i915_cards_are_intel_devices() {
+    let sysfs = FakeSysfs::new("intel-discovery");
+    let device_path = new_card(&sysfs);
+
+    assert_eq!(
+        sysfs::drm_card_devices(sysfs.root()),
+        vec![device_path.clone()]
+    );
+    assert!(IntelDevice::is_intel_device(&device_path));
+
+    // Integrated GPUs without a GPU hwmon interface are ignored
+    sysfs.write(&device_path.join("hwmon/hwmon5/name"), "coretemp");
+    assert!(!IntelDevice::is_intel_device(&device_path));
+}
+
+#[test]
+fn uuid_falls_back_to_the_card_name() {
+    let sysfs = FakeSysfs::new("intel-uuid");
+    let device_path = new_card(&sysfs);
+
+    assert_eq!(IntelDevice::get_uuid(&device_path), "INTEL-0000:04:00.0");
+
+    fs::remove_file(device_path.join("uevent")).unwrap();
+    assert_eq!(IntelDevice::get_uuid(&device_path), "INTEL-card0");
+}
+
+#[test]
+fn info_and_data_are_read_from_hwmon() {
+    let sysfs = FakeSysfs::new("intel-data");
+    let device_path = new_card(&sysfs);
+
+    let mut device = IntelDevice::new(&device_path).unwrap();
+    let info = device.get_info();
+
+    assert_eq!(info.name, "Intel Graphics (0xe20b)");
+    assert_eq!(info.fan_count, 2);
+    assert_eq!(info.power_limit_max, 190_000);
+    assert_eq!(info.power_limit_min, 50_000);
+    assert!(!info.capabilities.fan_control);
+    assert!(info.capabilities.power_usage);
+
+    let GpuVendorInfo::Intel { driver, .. } = device.get_vendor_info() else {
+        panic!("Expected Intel vendor info");
+    };
+    assert_eq!(driver, "xe");
+
+    // The package temperature is used over the first channel
+    let data = device.get_data().unwrap();
+    assert_eq!(data.temp_gpu, Some(51));
+    assert_eq!(data.power_limit, Some(190_000));
+    assert_eq!(data.fans[1].speed_rpm, Some(1450));
+}
+
+#[test]
+fn missing_temperature_is_not_reported_as_zero() {
+    let sysfs = FakeSysfs::new("intel-no-temp");
+    let device_path = new_card(&sysfs);
+
+    for name in ["temp1_input", "temp2_input"] {
+        fs::remove_file(device_path.join("hwmon/hwmon5").join(name)).unwrap();
+    }
+
+    let mut device = IntelDevice::new(&device_path).unwrap();
+
+    assert_eq!(device.get_data().unwrap().temp_gpu, None);
+}
+
+#[test]
+fn power_usage_is_computed_from_the_energy_counter() {
+    let sysfs = FakeSysfs::new("intel-energy");
+    let device_path = new_card(&sysfs);
+    let energy_path = device_path.join("hwmon/hwmon5/energy1_input");
+
+    let mut device = IntelDevice::new(&device_path).unwrap();
+    device.set_data_update_interval(Duration::ZERO);
+
+    std::thread::sleep(Duration::from_millis(10));
+    sysfs.write(&energy_path, "2000000");
+
+    let power_usage = device.get_data().unwrap().power_usage;
+    assert!(power_usage.is_some_and(|power| power > 0));
+
+    let GpuVendorData::Intel { energy, .. } = device.get_vendor_data().unwrap()
+    else {
+        panic!("Expected Intel vendor data");
+    };
+    assert_eq!(energy, Some(2_000_000));
+}
+
+#[test]
+fn power_limit_is_the_only_setting() {
+    let sysfs = FakeSysfs::new("intel-config");
+    let device_path = new_card(&sysfs);
+
+    let mut device = IntelDevice::new(&device_path).unwrap();
+    device
+        .apply_gpu_config(GpuConfig {
+            power_limit: Some(150_000),
+            ..Default::default()
+        })
+        .unwrap();
+
+    let power_max = device_path.join("hwmon/hwmon5/power1_max");
+    assert_eq!(sysfs::read_string(&power_max).unwrap(), "150000000");
+
+    // The fans are always controlled by the firmware
+    let result = device.set_fan_mode(FanSelection::All, FanMode::Manual(50));
+    assert!(matches!(result, Err(DeviceError::Unsupported { .. })));
+
+    // Reset restore the power limit read at startup
+    device.reset().unwrap();
+    assert_eq!(sysfs::read_string(&power_max).unwrap(), "190000000");
+}
+
+#[test]
+fn power_limit_without_rated_max_uses_the_current_one() {
+    let sysfs = FakeSysfs::new("intel-no-rated");
+    let device_path = new_card(&sysfs);
+    let hwmon_path = device_path.join("hwmon/hwmon5");
+
+    fs::remove_file(hwmon_path.join("power1_rated_max")).unwrap();
+    sysfs.write(&hwmon_path.join("power1_max"), "170000000");
+
+    let mut device = IntelDevice::new(&device_path).unwrap();
+    let info = device.get_info();
+
+    assert_eq!(info.power_limit_max, 170_000);
+    assert_eq!(info.power_limit_default, 170_000);
+    assert_eq!(info.power_limit_min, 50_000);
+
+    device
+        .apply_gpu_config(GpuConfig {
+            power_limit: Some(150_000),
+            ..Default::default()
+        })
+        .unwrap();
+
+    // Reset restore the power limit read at startup
+    device.reset().unwrap();
+    let power_max = hwmon_path.join("power1_max");
+    assert_eq!(sysfs::read_string(&power_max).unwrap(), "170000000");
+}
@@ -0,0 +1,1209 @@
+use mossd::{
+    fan_curve::{
+        fan_curve_info::{FanCurveInfo, FanCurveType},
+        fan_mode::{FanMode, FanSelection},
+        filter_curve::TempFilter,
+        linear_curve::LinearCurve,
+        pid_curve::{PidCurve, PidSettings},
+        zero_rpm_curve::ZeroRpmSettings,
+    },
+    gpu_device::{
+        DeviceError, GpuDevice,
+        fake_nvml_backend::{FakeNvmlBackend, FakeNvmlState, NvmlOperation},
+        fan_monitor::{FanHealth, FanMonitor, STALL_UPDATES},
+        gpu_config::{GpuConfig, NvidiaConfig, OriginalSettings},
+        gpu_data::GpuVendorData,
+        gpu_info::GpuVendorInfo,
+        nvidia_device::NvidiaDevice,
+        nvml_backend::NvmlFailure,
+        sensor_fault::{SENSOR_FAULT_REPORT, SensorFaultPolicy},
+        thermal_failsafe::{FailsafeSettings, ThermalFailsafe},
+    },
+    overclock_guard::OverclockGuard,
+};
+use nvml_wrapper::{
+    enum_wrappers::device::TemperatureThreshold,
+    enums::device::FanControlPolicy, error::NvmlError,
+};
+
+// Create a device on top of a fake driver with the default state
+fn new_device() -> (NvidiaDevice, FakeNvmlBackend) {
+    new_device_with(FakeNvmlState::default())
+}
+
+// Create a device on top of a fake driver with the given state
+fn new_device_with(state: FakeNvmlState) -> (NvidiaDevice, FakeNvmlBackend) {
+    let fake = FakeNvmlBackend::new(state);
+    let device = NvidiaDevice::new(Box::new(fake.clone())).unwrap();
+
+    (device, fake)
+}
+
+#[test]
+fn initialization_reads_device_info() {
+    let (device, _) = new_device();
+    let info = device.get_info();
+
+    assert_eq!(info.name, "Fake Nvidia GPU");
+    assert_eq!(info.power_limit_min, 100_000);
+    assert_eq!(info.power_limit_max, 300_000);
+    assert_eq!(info.power_limit_default, 250_000);
+}
+
+#[test]
+fn unsupported_thresholds_are_reported_as_none() {
+    let (device, _) = new_device();
+
+    let GpuVendorInfo::Nvidia {
+        max_temp,
+        mem_max_temp,
+        slowdown_temp,
+        ..
+    } = device.get_vendor_info()
+    else {
+        panic!("Expected Nvidia vendor info");
+    };
+
+    assert_eq!(max_temp, Some(93));
+    assert_eq!(mem_max_temp, None);
+    assert_eq!(slowdown_temp, Some(90));
+}
+
+#[test]
+fn unsupported_clocks_are_reported_as_none() {
+    let (mut device, _) = new_device();
+
+    let GpuVendorData::Nvidia {
+        sm_freq,
+        graphics_boost_freq,
+        ..
+    } = device.get_vendor_data().unwrap()
+    else {
+        panic!("Expected Nvidia vendor data");
+    };
+
+    assert_eq!(sm_freq, Some(1800));
+    assert_eq!(graphics_boost_freq, None);
+}
+
+#[test]
+fn threshold_errors_other_than_not_supported_fail_initialization() {
+    let fake = FakeNvmlBackend::default();
+    fake.fail(NvmlOperation::TemperatureThreshold, || {
+        NvmlError::NoPermission
+    });
+
+    assert!(NvidiaDevice::new(Box::new(fake)).is_err());
+}
+
+#[test]
+fn lost_gpu_fails_initialization() {
+    let fake = FakeNvmlBackend::default();
+    fake.fail(NvmlOperation::Name, || NvmlError::GpuLost);
+
+    assert!(NvidiaDevice::new(Box::new(fake)).is_err());
+}
+
+#[test]
+fn failed_utilization_query_does_not_fail_data_update() {
+    let fake = FakeNvmlBackend::default();
+    fake.fail(NvmlOperation::UtilizationRates, || NvmlError::NotSupported);
+    fake.fail(NvmlOperation::MemoryInfo, || NvmlError::NoPermission);
+
+    let mut device = NvidiaDevice::new(Box::new(fake)).unwrap();
+    let data = device.get_data().unwrap();
+
+    assert_eq!(data.core_usage, None);
+    assert_eq!(data.total_memory, None);
+}
+
+#[test]
+fn automatic_policy_at_startup_leaves_fans_alone() {
+    let (mut device, fake) = new_device();
+
+    device.update_fan().unwrap();
+
+    let state = fake.state();
+    assert_eq!(state.fan_speeds[0], 30);
+    assert_eq!(
+        state.fan_policies[0],
+        FanControlPolicy::TemperatureContinousSw
+    );
+}
+
+#[test]
+fn manual_policy_at_startup_is_detected_as_curve_mode() {
+    let mut state = FakeNvmlState::default();
+    state.fan_policies[0] = FanControlPolicy::Manual;
+
+    let (mut device, fake) = new_device_with(state);
+
+    // The default curve always run the fans at full speed
+    device.update_fan().unwrap();
+
+    assert_eq!(fake.state().fan_speeds[0], 100);
+}
+
+#[test]
+fn fan_mode_changes_set_the_fan_policy() {
+    let (mut device, fake) = new_device();
+
+    device
+        .set_fan_mode(FanSelection::All, FanMode::Manual(40))
+        .unwrap();
+    assert_eq!(fake.state().fan_policies[0], FanControlPolicy::Manual);
+
+    device.update_fan().unwrap();
+    assert_eq!(fake.state().fan_speeds[0], 40);
+
+    device
+        .set_fan_mode(FanSelection::All, FanMode::Auto)
+        .unwrap();
+    assert_eq!(
+        fake.state().fan_policies[0],
+        FanControlPolicy::TemperatureContinousSw
+    );
+}
+
+#[test]
+fn curve_mode_follows_the_fan_curve() {
+    let (mut device, fake) = new_device();
+    fake.state().temperature = 60;
+
+    let curve = LinearCurve::new(&[(40, 20), (80, 60)]);
+    device
+        .set_fan_curve(FanSelection::All, Box::new(curve))
+        .unwrap();
+    device
+        .set_fan_mode(FanSelection::All, FanMode::Curve)
+        .unwrap();
+    device.update_fan().unwrap();
+
+    assert_eq!(fake.state().fan_speeds[0], 40);
+}
+
+#[test]
+fn pid_curve_integrates_over_the_fan_update_interval() {
+    let (mut device, fake) = new_device();
+    fake.state().temperature = 80;
+
+    // Integral only controller, 10 degrees above the target
+    let settings = PidSettings {
+        target: 70,
+        kp: 0.0,
+        ki: 1.0,
+        kd: 0.0,
+        ..Default::default()
+    };
+
+    device.set_fan_update_interval(std::time::Duration::from_secs(2));
+    device
+        .set_fan_curve(FanSelection::All, Box::new(PidCurve::new(settings)))
+        .unwrap();
+    device
+        .set_fan_mode(FanSelection::All, FanMode::Curve)
+        .unwrap();
+
+    // The first update integrates over the fan update interval
+    assert_eq!(fake.state().fan_speeds[0], 20);
+}
+
+#[test]
+fn filtered_temperature_is_reported_with_the_raw_one() {
+    let (mut device, fake) = new_device();
+    device.set_data_update_interval(std::time::Duration::ZERO);
+    fake.state().temperature = 60;
+
+    let info = FanCurveInfo {
+        points: vec![(40, 20), (80, 60)],
+        filter: Some(TempFilter::Mean(3)),
+        ..Default::default()
+    };
+    device
+        .set_fan_curve(FanSelection::All, info.build_curve())
+        .unwrap();
+    device
+        .set_fan_mode(FanSelection::All, FanMode::Curve)
+        .unwrap();
+
+    fake.state().temperature = 70;
+    device.update_fan().unwrap();
+    fake.state().temperature = 80;
+    device.update_fan().unwrap();
+
+    // Mean of the last three temperatures
+    assert_eq!(fake.state().fan_speeds[0], 50);
+
+    let data = device.get_data().unwrap();
+    assert_eq!(data.temp_gpu, Some(80));
+    assert_eq!(data.temp_gpu_filtered, Some(70.0));
+}
+
+#[test]
+fn zero_rpm_keeps_the_board_minimum_speed() {
+    let (mut device, fake) = new_device_with(FakeNvmlState {
+        fan_speed_min: 25,
+        temperature: 40,
+        ..Default::default()
+    });
+    assert!(!device.get_info().supports_zero_rpm());
+
+    // Zero RPM curve stopping the fans below 45°C
+    let info = FanCurveInfo {
+        points: vec![(40, 20), (80, 60)],
+        zero_rpm: Some(ZeroRpmSettings {
+            stop_temp: 45,
+            start_temp: 55,
+            kick_duty: 60,
+            kick_time: std::time::Duration::from_secs(2),
+            min_duty: 30,
+        }),
+        ..Default::default()
+    };
+    device
+        .set_fan_curve(FanSelection::All, info.build_curve())
+        .unwrap();
+    device
+        .set_fan_mode(FanSelection::All, FanMode::Curve)
+        .unwrap();
+
+    assert_eq!(fake.state().fan_speeds[0], 25);
+}
+
+#[test]
+fn failed_temperature_read_runs_the_fans_at_full_speed() {
+    let (mut device, fake) = new_device();
+
+    let curve = LinearCurve::new(&[(40, 20), (100, 80)]);
+    device
+        .set_fan_curve(FanSelection::All, Box::new(curve))
+        .unwrap();
+    device
+        .set_fan_mode(FanSelection::All, FanMode::Curve)
+        .unwrap();
+
+    fake.fail(NvmlOperation::Temperature, || NvmlError::Unknown);
+    device.update_fan().unwrap();
+
+    assert_eq!(fake.state().fan_speeds[0], 100);
+}
+
+#[test]
+fn failed_fan_speed_write_is_reported() {
+    let (mut device, fake) = new_device();
+    device
+        .set_fan_mode(FanSelection::All, FanMode::Manual(50))
+        .unwrap();
+
+    fake.fail(NvmlOperation::SetFanSpeed, || NvmlError::GpuLost);
+
+    assert!(device.update_fan().is_err());
+}
+
+#[test]
+fn power_limit_outside_bounds_is_ignored() {
+    let (mut device, fake) = new_device();
+
+    let mut config = GpuConfig {
+        power_limit: Some(400_000),
+        ..Default::default()
+    };
+    device.apply_gpu_config(config.clone()).unwrap();
+    assert_eq!(fake.state().power_limit, 250_000);
+
+    config.power_limit = Some(50_000);
+    device.apply_gpu_config(config.clone()).unwrap();
+    assert_eq!(fake.state().power_limit, 250_000);
+
+    config.power_limit = Some(200_000);
+    device.apply_gpu_config(config).unwrap();
+    assert_eq!(fake.state().power_limit, 200_000);
+}
+
+#[test]
+fn clock_offsets_are_applied() {
+    let (mut device, fake) = new_device();
+
+    let mut config = GpuConfig::default();
+    config.nvidia_config.core_clock_offset = Some(150);
+    config.nvidia_config.mem_clock_offset = Some(-500);
+    device.apply_gpu_config(config).unwrap();
+
+    let state = fake.state();
+    assert_eq!(state.gpc_clock_offset, 150);
+    assert_eq!(state.mem_clock_offset, -500);
+}
+
+#[test]
+fn config_errors_are_reported() {
+    let (mut device, fake) = new_device();
+    fake.fail(NvmlOperation::SetGpcClockVfOffset, || {
+        NvmlError::NoPermission
+    });
+
+    let mut config = GpuConfig::default();
+    config.nvidia_config.core_clock_offset = Some(150);
+
+    assert!(device.apply_gpu_config(config).is_err());
+}
+
+#[test]
+fn data_is_refreshed_after_the_update_interval() {
+    let (mut device, fake) = new_device();
+    device.set_data_update_interval(std::time::Duration::ZERO);
+
+    fake.state().temperature = 72;
+    assert_eq!(device.get_data().unwrap().temp_gpu, Some(72));
+
+    fake.fail(NvmlOperation::Temperature, || NvmlError::GpuLost);
+    assert!(device.get_data().is_err());
+}
+
+#[test]
+fn supported_memory_threshold_is_reported() {
+    let mut state = FakeNvmlState::default();
+    state
+        .temperature_thresholds
+        .insert(TemperatureThreshold::MemoryMax, 95);
+
+    let (device, _) = new_device_with(state);
+
+    let GpuVendorInfo::Nvidia { mem_max_temp, .. } = device.get_vendor_info()
+    else {
+        panic!("Expected Nvidia vendor info");
+    };
+
+    assert_eq!(mem_max_temp, Some(95));
+}
+
+// Create a device on top of a fake driver with three fans
+fn new_triple_fan_device() -> (NvidiaDevice, FakeNvmlBackend) {
+    let state = FakeNvmlState {
+        fan_speeds: vec![30, 31, 32],
+        fan_speeds_rpm: vec![1000, 1010, 1020],
+        fan_policies: vec![FanControlPolicy::TemperatureContinousSw; 3],
+        ..Default::default()
+    };
+
+    new_device_with(state)
+}
+
+#[test]
+fn data_reports_every_fan() {
+    let (mut device, _) = new_triple_fan_device();
+
+    let fans = device.get_data().unwrap().fans;
+
+    assert_eq!(fans.len(), 3);
+    assert_eq!(fans[1].speed, Some(31));
+    assert_eq!(fans[2].speed_rpm, Some(1020));
+}
+
+#[test]
+fn fan_mode_for_all_fans_sets_every_fan() {
+    let (mut device, fake) = new_triple_fan_device();
+
+    device
+        .set_fan_mode(FanSelection::All, FanMode::Manual(60))
+        .unwrap();
+
+    let state = fake.state();
+    assert_eq!(state.fan_speeds, vec![60, 60, 60]);
+    assert_eq!(state.fan_policies, vec![FanControlPolicy::Manual; 3]);
+}
+
+#[test]
+fn fan_mode_for_a_single_fan_leaves_the_others_alone() {
+    let (mut device, fake) = new_triple_fan_device();
+
+    device
+        .set_fan_mode(FanSelection::Index(1), FanMode::Manual(70))
+        .unwrap();
+    device.update_fan().unwrap();
+
+    let state = fake.state();
+    assert_eq!(state.fan_speeds, vec![30, 70, 32]);
+    assert_eq!(
+        state.fan_policies,
+        vec![
+            FanControlPolicy::TemperatureContinousSw,
+            FanControlPolicy::Manual,
+            FanControlPolicy::TemperatureContinousSw,
+        ]
+    );
+}
+
+#[test]
+fn per_fan_curve_overrides_the_device_curve() {
+    let (mut device, fake) = new_triple_fan_device();
+    fake.state().temperature = 60;
+
+    let curve = LinearCurve::new(&[(40, 20), (80, 60)]);
+    device
+        .set_fan_curve(FanSelection::All, Box::new(curve))
+        .unwrap();
+
+    let curve = LinearCurve::new(&[(0, 90)]);
+    device
+        .set_fan_curve(FanSelection::Index(2), Box::new(curve))
+        .unwrap();
+
+    device
+        .set_fan_mode(FanSelection::All, FanMode::Curve)
+        .unwrap();
+
+    assert_eq!(fake.state().fan_speeds, vec![40, 40, 90]);
+}
+
+#[test]
+fn device_curve_replaces_the_per_fan_curves() {
+    let (mut device, fake) = new_triple_fan_device();
+
+    let curve = LinearCurve::new(&[(0, 90)]);
+    device
+        .set_fan_curve(FanSelection::Index(0), Box::new(curve))
+        .unwrap();
+
+    let curve = LinearCurve::new(&[(0, 25)]);
+    device
+        .set_fan_curve(FanSelection::All, Box::new(curve))
+        .unwrap();
+
+    device
+        .set_fan_mode(FanSelection::All, FanMode::Curve)
+        .unwrap();
+
+    assert_eq!(fake.state().fan_speeds, vec![25, 25, 25]);
+}
+
+#[test]
+fn fan_curves_are_reported_per_fan() {
+    let (mut device, _) = new_triple_fan_device();
+
+    let device_info = FanCurveInfo {
+        points: vec![(40, 20), (80, 60)],
+        upper_threshold: Some(2),
+        ..Default::default()
+    };
+    let fan_info = FanCurveInfo {
+        curve_type: FanCurveType::Step,
+        points: vec![(0, 90)],
+        ..Default::default()
+    };
+
+    device
+        .set_fan_curve(FanSelection::All, device_info.build_curve())
+        .unwrap();
+    device
+        .set_fan_curve(FanSelection::Index(1), fan_info.build_curve())
+        .unwrap();
+
+    assert_eq!(
+        device.get_fan_curves(),
+        vec![device_info.clone(), fan_info, device_info]
+    );
+}
+
+#[test]
+fn built_curves_convert_back_to_their_info() {
+    let info = FanCurveInfo {
+        curve_type: FanCurveType::Monotone,
+        points: vec![(30, 0), (50, 40), (80, 100)],
+        lower_threshold: Some(3),
+        upper_threshold: Some(1),
+        hold_time: Some(std::time::Duration::from_secs(5)),
+        decrease_delay: Some(std::time::Duration::from_secs(2)),
+        ramp_up: Some(10.0),
+        ramp_down: Some(2.0),
+        filter: Some(TempFilter::Mean(4)),
+        zero_rpm: Some(ZeroRpmSettings {
+            stop_temp: 45,
+            start_temp: 55,
+            kick_duty: 60,
+            kick_time: std::time::Duration::from_secs(2),
+            min_duty: 30,
+        }),
+        ..Default::default()
+    };
+    let curve = info.build_curve();
+
+    assert_eq!(curve.points(), info.points);
+    assert_eq!(curve.to_info(), info);
+
+    let info = FanCurveInfo {
+        curve_type: FanCurveType::Pid,
+        pid: Some(PidSettings::default()),
+        ..Default::default()
+    };
+    let curve = info.build_curve();
+
+    assert!(curve.points().is_empty());
+    assert_eq!(curve.to_info(), info);
+}
+
+#[test]
+fn out_of_range_fan_is_rejected() {
+    let (mut device, fake) = new_triple_fan_device();
+
+    assert!(
+        device
+            .set_fan_mode(FanSelection::Index(3), FanMode::Manual(50))
+            .is_err()
+    );
+
+    let curve = LinearCurve::new(&[(0, 50)]);
+    assert!(
+        device
+            .set_fan_curve(FanSelection::Index(3), Box::new(curve))
+            .is_err()
+    );
+
+    assert_eq!(fake.state().fan_speeds, vec![30, 31, 32]);
+}
+
+#[test]
+fn fan_modes_are_detected_per_fan_at_startup() {
+    let state = FakeNvmlState {
+        fan_speeds: vec![30, 30],
+        fan_speeds_rpm: vec![1000, 1000],
+        fan_policies: vec![
+            FanControlPolicy::TemperatureContinousSw,
+            FanControlPolicy::Manual,
+        ],
+        ..Default::default()
+    };
+
+    let (mut device, fake) = new_device_with(state);
+    device.update_fan().unwrap();
+
+    assert_eq!(fake.state().fan_speeds, vec![30, 100]);
+}
+
+#[test]
+fn device_without_fans_is_supported() {
+    let state = FakeNvmlState {
+        fan_speeds: Vec::new(),
+        fan_speeds_rpm: Vec::new(),
+        fan_policies: Vec::new(),
+        ..Default::default()
+    };
+
+    let (mut device, _) = new_device_with(state);
+
+    assert!(!device.get_info().capabilities.fan_control);
+    assert!(matches!(
+        device.set_fan_mode(FanSelection::All, FanMode::Curve),
+        Err(DeviceError::Unsupported { .. })
+    ));
+    device
+        .set_fan_mode(FanSelection::All, FanMode::Auto)
+        .unwrap();
+    device.update_fan().unwrap();
+
+    assert!(device.get_data().unwrap().fans.is_empty());
+}
+
+#[test]
+fn fan_capabilities_are_reported() {
+    let state = FakeNvmlState {
+        fan_speed_min: 30,
+        fan_speed_max: 90,
+        ..Default::default()
+    };
+
+    let (device, _) = new_device_with(state);
+    let info = device.get_info();
+
+    assert_eq!(info.fan_count, 1);
+    assert_eq!(info.fan_speed_min, 30);
+    assert_eq!(info.fan_speed_max, 90);
+}
+
+#[test]
+fn unsupported_fan_speed_range_assumes_full_range() {
+    let fake = FakeNvmlBackend::default();
+    fake.fail(NvmlOperation::MinMaxFanSpeed, || NvmlError::NotSupported);
+
+    let device = NvidiaDevice::new(Box::new(fake)).unwrap();
+    let info = device.get_info();
+
+    assert_eq!(info.fan_speed_min, 0);
+    assert_eq!(info.fan_speed_max, 100);
+}
+
+#[test]
+fn fan_speeds_are_clamped_to_the_supported_range() {
+    let state = FakeNvmlState {
+        fan_speed_min: 30,
+        fan_speed_max: 90,
+        ..Default::default()
+    };
+
+    let (mut device, fake) = new_device_with(state);
+
+    device
+        .set_fan_mode(FanSelection::All, FanMode::Manual(10))
+        .unwrap();
+    assert_eq!(fake.state().fan_speeds[0], 30);
+
+    let curve = LinearCurve::new(&[(0, 100)]);
+    device
+        .set_fan_curve(FanSelection::All, Box::new(curve))
+        .unwrap();
+    device
+        .set_fan_mode(FanSelection::All, FanMode::Curve)
+        .unwrap();
+    assert_eq!(fake.state().fan_speeds[0], 90);
+}
+
+#[test]
+fn capabilities_are_reported() {
+    let (device, _) = new_device();
+    let capabilities = device.get_info().capabilities;
+
+    assert!(capabilities.fan_control);
+    assert!(capabilities.fan_rpm);
+    assert!(capabilities.clock_offsets);
+    assert!(capabilities.power_limit);
+    assert!(capabilities.power_usage);
+    assert!(!capabilities.mem_temp);
+}
+
+#[test]
+fn passive_card_initializes_without_fan_control() {
+    let fake = FakeNvmlBackend::default();
+    fake.fail(NvmlOperation::NumFans, || NvmlError::NotSupported);
+    fake.fail(NvmlOperation::FanControlPolicy, || NvmlError::NotSupported);
+
+    let mut device = NvidiaDevice::new(Box::new(fake)).unwrap();
+    let info = device.get_info();
+
+    assert_eq!(info.fan_count, 0);
+    assert!(!info.capabilities.fan_control);
+    assert!(device.get_data().unwrap().fans.is_empty());
+}
+
+#[test]
+fn fan_control_without_policy_support_is_unsupported() {
+    let fake = FakeNvmlBackend::default();
+    fake.fail(NvmlOperation::FanControlPolicy, || NvmlError::NotSupported);
+
+    let mut device = NvidiaDevice::new(Box::new(fake.clone())).unwrap();
+
+    assert!(!device.get_info().capabilities.fan_control);
+    assert!(matches!(
+        device.set_fan_mode(FanSelection::All, FanMode::Manual(50)),
+        Err(DeviceError::Unsupported { .. })
+    ));
+    device
+        .set_fan_mode(FanSelection::All, FanMode::Auto)
+        .unwrap();
+    device.update_fan().unwrap();
+
+    assert_eq!(fake.state().fan_speeds[0], 30);
+}
+
+#[test]
+fn unsupported_power_limit_is_reported() {
+    let fake = FakeNvmlBackend::default();
+    fake.fail(NvmlOperation::PowerManagementLimitConstraints, || {
+        NvmlError::NotSupported
+    });
+    fake.fail(NvmlOperation::PowerManagementLimitDefault, || {
+        NvmlError::NotSupported
+    });
+
+    let mut device = NvidiaDevice::new(Box::new(fake.clone())).unwrap();
+    let info = device.get_info();
+
+    assert!(!info.capabilities.power_limit);
+    assert_eq!(info.power_limit_max, 0);
+
+    let config = GpuConfig {
+        power_limit: Some(200_000),
+        ..Default::default()
+    };
+
+    assert!(matches!(
+        device.apply_gpu_config(config),
+        Err(DeviceError::Unsupported { .. })
+    ));
+    assert_eq!(fake.state().power_limit, 250_000);
+}
+
+#[test]
+fn supported_settings_are_applied_alongside_unsupported_ones() {
+    let fake = FakeNvmlBackend::default();
+    fake.fail(NvmlOperation::GpcClockVfOffset, || NvmlError::NotSupported);
+
+    let mut device = NvidiaDevice::new(Box::new(fake.clone())).unwrap();
+    assert!(!device.get_info().capabilities.clock_offsets);
+
+    let mut config = GpuConfig {
+        power_limit: Some(200_000),
+        ..Default::default()
+    };
+    config.nvidia_config.core_clock_offset = Some(150);
+
+    assert!(matches!(
+        device.apply_gpu_config(config),
+        Err(DeviceError::Unsupported { .. })
+    ));
+
+    let state = fake.state();
+    assert_eq!(state.power_limit, 200_000);
+    assert_eq!(state.gpc_clock_offset, 0);
+}
+
+#[test]
+fn unsupported_data_is_reported_as_none() {
+    let fake = FakeNvmlBackend::default();
+    fake.fail(NvmlOperation::PowerUsage, || NvmlError::NotSupported);
+    fake.fail(NvmlOperation::FanSpeedRpm, || NvmlError::NotSupported);
+    fake.fail(NvmlOperation::GpcClockVfOffset, || NvmlError::NotSupported);
+
+    let mut device = NvidiaDevice::new(Box::new(fake)).unwrap();
+    let capabilities = device.get_info().capabilities;
+    let data = device.get_data().unwrap();
+
+    assert!(!capabilities.power_usage);
+    assert!(!capabilities.fan_rpm);
+    assert_eq!(data.power_usage, None);
+    assert_eq!(data.core_clock_offset, None);
+    assert_eq!(data.fans[0].speed, Some(30));
+    assert_eq!(data.fans[0].speed_rpm, None);
+}
+
+#[test]
+fn lost_gpu_is_unavailable() {
+    let (device, fake) = new_device();
+    assert!(device.is_available());
+
+    fake.fail(NvmlOperation::Uuid, || NvmlError::NoPermission);
+    assert!(device.is_available());
+
+    fake.fail(NvmlOperation::Uuid, || NvmlError::GpuLost);
+    assert!(!device.is_available());
+}
+
+#[test]
+fn driver_failures_are_told_apart_from_call_failures() {
+    let classify = |error| NvmlFailure::classify(&error);
+
+    assert_eq!(
+        classify(NvmlError::LibraryNotFound),
+        NvmlFailure::LibraryNotFound
+    );
+    assert_eq!(
+        classify(NvmlError::DriverNotLoaded),
+        NvmlFailure::DriverNotLoaded
+    );
+    assert_eq!(classify(NvmlError::NoPermission), NvmlFailure::NoPermission);
+
+    assert!(classify(NvmlError::DriverNotLoaded).is_driver_level());
+    assert!(classify(NvmlError::Uninitialized).is_driver_level());
+    assert!(!classify(NvmlError::NoPermission).is_driver_level());
+    assert!(!classify(NvmlError::NotSupported).is_driver_level());
+    assert!(!classify(NvmlError::GpuLost).is_driver_level());
+    assert!(!classify(NvmlError::Unknown).is_driver_level());
+}
+
+#[test]
+fn reset_restores_the_original_settings() {
+    let state = FakeNvmlState {
+        power_limit: 220_000,
+        gpc_clock_offset: 50,
+        ..Default::default()
+    };
+    let (mut device, fake) = new_device_with(state);
+
+    let mut config = GpuConfig {
+        power_limit: Some(280_000),
+        ..Default::default()
+    };
+    config.nvidia_config.core_clock_offset = Some(150);
+    config.nvidia_config.mem_clock_offset = Some(-500);
+    device.apply_gpu_config(config).unwrap();
+
+    device
+        .set_fan_mode(FanSelection::All, FanMode::Manual(80))
+        .unwrap();
+
+    device.reset().unwrap();
+
+    let state = fake.state();
+    assert_eq!(state.power_limit, 220_000);
+    assert_eq!(state.gpc_clock_offset, 50);
+    assert_eq!(state.mem_clock_offset, 0);
+    assert_eq!(
+        state.fan_policies[0],
+        FanControlPolicy::TemperatureContinousSw
+    );
+}
+
+#[test]
+fn settings_missing_from_the_config_are_restored() {
+    let (mut device, fake) = new_device();
+
+    let mut config = GpuConfig {
+        power_limit: Some(200_000),
+        ..Default::default()
+    };
+    config.nvidia_config.core_clock_offset = Some(150);
+    device.apply_gpu_config(config).unwrap();
+
+    // A profile that only set the power limit
+    let config = GpuConfig {
+        power_limit: Some(180_000),
+        ..Default::default()
+    };
+    device.apply_gpu_config(config).unwrap();
+
+    let state = fake.state();
+    assert_eq!(state.power_limit, 180_000);
+    assert_eq!(state.gpc_clock_offset, 0);
+}
+
+#[test]
+fn manual_fan_policy_at_startup_is_given_back_to_the_driver_on_reset() {
+    let mut state = FakeNvmlState::default();
+    state.fan_policies[0] = FanControlPolicy::Manual;
+    state.fan_speeds[0] = 45;
+
+    let (mut device, fake) = new_device_with(state);
+    assert!(matches!(
+        device.get_original_settings().fan_modes[..],
+        [FanMode::Auto]
+    ));
+
+    device.reset().unwrap();
+
+    assert_eq!(
+        fake.state().fan_policies[0],
+        FanControlPolicy::TemperatureContinousSw
+    );
+}
+
+#[test]
+fn recorded_settings_are_restored_after_a_crash() {
+    // Settings left behind by a crashed instance
+    let mut state = FakeNvmlState {
+        power_limit: 300_000,
+        gpc_clock_offset: 150,
+        ..Default::default()
+    };
+    state.fan_policies[0] = FanControlPolicy::Manual;
+    state.fan_speeds[0] = 80;
+
+    let (mut device, fake) = new_device_with(state);
+
+    // Settings recorded before the crash
+    let recorded = OriginalSettings {
+        fan_modes: vec![FanMode::Auto],
+        config: GpuConfig {
+            power_limit: Some(220_000),
+            nvidia_config: NvidiaConfig {
+                core_clock_offset: Some(0),
+                mem_clock_offset: Some(0),
+            },
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    device.set_original_settings(recorded);
+    device.reset().unwrap();
+
+    let state = fake.state();
+    assert_eq!(state.power_limit, 220_000);
+    assert_eq!(state.gpc_clock_offset, 0);
+    assert_eq!(
+        state.fan_policies[0],
+        FanControlPolicy::TemperatureContinousSw
+    );
+
+    let original = device.get_original_settings();
+    assert!(matches!(original.fan_modes[..], [FanMode::Auto]));
+    assert_eq!(original.config.power_limit, Some(220_000));
+}
+
+#[test]
+fn quarantined_overclock_restores_the_original_offsets() {
+    let (mut device, fake) = new_device();
+
+    let config = GpuConfig {
+        power_limit: Some(250_000),
+        nvidia_config: NvidiaConfig {
+            core_clock_offset: Some(150),
+            mem_clock_offset: Some(500),
+        },
+        ..Default::default()
+    };
+    assert!(config.has_overclock());
+
+    device.apply_gpu_config(config.clone()).unwrap();
+    assert_eq!(fake.state().gpc_clock_offset, 150);
+
+    // Only the power limit is kept
+    let safe_config = config.without_overclock();
+    assert!(!safe_config.has_overclock());
+
+    device.apply_gpu_config(safe_config).unwrap();
+
+    let state = fake.state();
+    assert_eq!(state.gpc_clock_offset, 0);
+    assert_eq!(state.mem_clock_offset, 0);
+    assert_eq!(state.power_limit, 250_000);
+}
+
+#[test]
+fn overclock_pending_from_a_previous_boot_is_quarantined() {
+    let path = std::env::temp_dir()
+        .join(format!("mossd-guard-{}.json", std::process::id()));
+
+    // Pending overclock left by a boot that crashed
+    std::fs::write(
+        &path,
+        r#"{ "boot_id": "previous-boot", "pending": ["gpu-0"], "quarantined": [] }"#,
+    )
+    .unwrap();
+
+    let mut guard =
+        OverclockGuard::new(&path, std::time::Duration::from_secs(300));
+    assert!(guard.is_quarantined("gpu-0"));
+    assert!(!guard.is_quarantined("gpu-1"));
+    assert!(guard.next_confirmation().is_none());
+
+    // The quarantine survives a daemon restart until it is cleared
+    guard.mark_pending("gpu-1").unwrap();
+    guard.clear_pending().unwrap();
+
+    let mut guard =
+        OverclockGuard::new(&path, std::time::Duration::from_secs(300));
+    assert!(guard.is_quarantined("gpu-0"));
+    assert!(!guard.is_quarantined("gpu-1"));
+
+    guard.clear_quarantine("gpu-0").unwrap();
+
+    let guard = OverclockGuard::new(&path, std::time::Duration::ZERO);
+    assert!(!guard.is_quarantined("gpu-0"));
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn fan_failsafe_forces_full_speed_until_released() {
+    let (mut device, fake) = new_device();
+
+    device
+        .set_fan_mode(FanSelection::All, FanMode::Manual(20))
+        .unwrap();
+    device.set_fan_failsafe(true).unwrap();
+    assert_eq!(fake.state().fan_speeds[0], 100);
+
+    // Fan mode changes are delayed until the failsafe is released
+    device
+        .set_fan_mode(FanSelection::All, FanMode::Auto)
+        .unwrap();
+    device.update_fan().unwrap();
+
+    let state = fake.state().clone();
+    assert_eq!(state.fan_policies[0], FanControlPolicy::Manual);
+    assert_eq!(state.fan_speeds[0], 100);
+
+    device.set_fan_failsafe(false).unwrap();
+    assert_eq!(
+        fake.state().fan_policies[0],
+        FanControlPolicy::TemperatureContinousSw
+    );
+}
+
+#[test]
+fn failsafe_uses_the_slowdown_temperature_with_hysteresis() {
+    let (device, _) = new_device();
+    let mut failsafe = ThermalFailsafe::new(
+        &FailsafeSettings::default(),
+        &device.get_vendor_info(),
+    );
+
+    assert_eq!(failsafe.threshold(), Some(90));
+
+    assert_eq!(failsafe.update(89), None);
+    assert_eq!(failsafe.update(90), Some(true));
+    assert_eq!(failsafe.update(86), None);
+    assert!(failsafe.is_engaged());
+    assert_eq!(failsafe.update(85), Some(false));
+}
+
+#[test]
+fn configured_failsafe_temperature_overrides_the_device_one() {
+    let fake = FakeNvmlBackend::default();
+    fake.fail(NvmlOperation::TemperatureThreshold, || {
+        NvmlError::NotSupported
+    });
+
+    let device = NvidiaDevice::new(Box::new(fake)).unwrap();
+    let vendor_info = device.get_vendor_info();
+
+    let failsafe =
+        ThermalFailsafe::new(&FailsafeSettings::default(), &vendor_info);
+    assert_eq!(failsafe.threshold(), None);
+
+    let settings = FailsafeSettings {
+        temp: Some(80),
+        ..Default::default()
+    };
+    let mut failsafe = ThermalFailsafe::new(&settings, &vendor_info);
+    assert_eq!(failsafe.update(80), Some(true));
+}
+
+#[test]
+fn stalled_fan_is_detected_after_consecutive_updates() {
+    let (mut device, fake) = new_device();
+    device.set_data_update_interval(std::time::Duration::ZERO);
+
+    let mut monitor = FanMonitor::new(&device.get_info());
+    assert_eq!(monitor.health(), vec![FanHealth::Ok]);
+
+    fake.state().fan_speeds[0] = 60;
+    fake.state().fan_speeds_rpm[0] = 2000;
+    assert!(monitor.update(&device.get_data().unwrap().fans).is_empty());
+
+    fake.state().fan_speeds_rpm[0] = 0;
+    for _ in 1..STALL_UPDATES {
+        assert!(monitor.update(&device.get_data().unwrap().fans).is_empty());
+    }
+    assert_eq!(
+        monitor.update(&device.get_data().unwrap().fans),
+        vec![(0, FanHealth::Stalled)]
+    );
+
+    fake.state().fan_speeds_rpm[0] = 1900;
+    assert_eq!(
+        monitor.update(&device.get_data().unwrap().fans),
+        vec![(0, FanHealth::Ok)]
+    );
+}
+
+#[test]
+fn fans_stopped_at_low_duty_are_not_stalled() {
+    let (mut device, fake) = new_device();
+    device.set_data_update_interval(std::time::Duration::ZERO);
+
+    let mut monitor = FanMonitor::new(&device.get_info());
+
+    fake.state().fan_speeds[0] = 0;
+    fake.state().fan_speeds_rpm[0] = 0;
+
+    for _ in 0..STALL_UPDATES * 2 {
+        assert!(monitor.update(&device.get_data().unwrap().fans).is_empty());
+    }
+    assert_eq!(monitor.health(), vec![FanHealth::Ok]);
+}
+
+#[test]
+fn fan_health_is_unknown_without_rpm() {
+    let fake = FakeNvmlBackend::default();
+    fake.fail(NvmlOperation::FanSpeedRpm, || NvmlError::NotSupported);
+
+    let mut device = NvidiaDevice::new(Box::new(fake)).unwrap();
+    let mut monitor = FanMonitor::new(&device.get_info());
+
+    assert!(monitor.update(&device.get_data().unwrap().fans).is_empty());
+    assert_eq!(monitor.health(), vec![FanHealth::Unknown]);
+}
+
+// Create a device in curve mode following the given temperature
+fn new_curve_device(temp: u32) -> (NvidiaDevice, FakeNvmlBackend) {
+    let (mut device, fake) = new_device();
+    fake.state().temperature = temp;
+
+    let curve = LinearCurve::new(&[(40, 20), (80, 60)]);
+    device
+        .set_fan_curve(FanSelection::All, Box::new(curve))
+        .unwrap();
+    device
+        .set_fan_mode(FanSelection::All, FanMode::Curve)
+        .unwrap();
+
+    (device, fake)
+}
+
+#[test]
+fn fixed_duty_policy_is_applied_on_sensor_fault() {
+    let (mut device, fake) = new_curve_device(60);
+    device.set_sensor_fault_policy(SensorFaultPolicy::FixedDuty(70));
+
+    fake.fail(NvmlOperation::Temperature, || NvmlError::Unknown);
+    device.update_fan().unwrap();
+    assert_eq!(fake.state().fan_speeds[0], 70);
+
+    fake.clear_failure(NvmlOperation::Temperature);
+    device.update_fan().unwrap();
+    assert_eq!(fake.state().fan_speeds[0], 40);
+}
+
+#[test]
+fn last_good_temperature_is_held_for_the_configured_duration() {
+    let (mut device, fake) = new_curve_device(60);
+    device.set_sensor_fault_policy(SensorFaultPolicy::LastGood(
+        std::time::Duration::from_secs(60),
+    ));
+
+    fake.fail(NvmlOperation::Temperature, || NvmlError::Unknown);
+    device.update_fan().unwrap();
+    assert_eq!(fake.state().fan_speeds[0], 40);
+
+    // Once the hold expired the fans run at full speed
+    device.set_sensor_fault_policy(SensorFaultPolicy::LastGood(
+        std::time::Duration::ZERO,
+    ));
+    device.update_fan().unwrap();
+    assert_eq!(fake.state().fan_speeds[0], 100);
+}
+
+#[test]
+fn auto_policy_gives_the_fans_back_to_the_driver() {
+    let (mut device, fake) = new_curve_device(60);
+    device.set_sensor_fault_policy(SensorFaultPolicy::Auto);
+
+    fake.fail(NvmlOperation::Temperature, || NvmlError::Unknown);
+    device.update_fan().unwrap();
+    assert_eq!(
+        fake.state().fan_policies[0],
+        FanControlPolicy::TemperatureContinousSw
+    );
+
+    // The fans are taken back once the sensor recovered
+    fake.clear_failure(NvmlOperation::Temperature);
+    device.update_fan().unwrap();
+    assert_eq!(fake.state().fan_policies[0], FanControlPolicy::Manual);
+    assert_eq!(fake.state().fan_speeds[0], 40);
+}
+
+#[test]
+fn sensor_fault_is_reported_after_consecutive_failures() {
+    let (mut device, fake) = new_curve_device(60);
+    device.set_data_update_interval(std::time::Duration::ZERO);
+
+    fake.fail(NvmlOperation::Temperature, || NvmlError::Unknown);
+
+    for _ in 1..SENSOR_FAULT_REPORT {
+        device.update_fan().unwrap();
+    }
+
+    assert!(matches!(
+        device.update_fan(),
+        Err(DeviceError::SensorFault { .. })
+    ));
+    // The fans are still updated and the fault is only reported once
+    assert_eq!(fake.state().fan_speeds[0], 100);
+    device.update_fan().unwrap();
+
+    let data = device.get_data().unwrap();
+    assert_eq!(data.temp_gpu, None);
+    assert_eq!(data.temp_sensor_failures, SENSOR_FAULT_REPORT + 1);
+
+    fake.clear_failure(NvmlOperation::Temperature);
+    device.update_fan().unwrap();
+    assert_eq!(device.get_data().unwrap().temp_sensor_failures, 0);
+}